@@ -0,0 +1,423 @@
+//! Skeletal animation read from XSM motions.
+//!
+//! Every XSM submotion animates the XAC node of the same name. Keys are
+//! mirrored on X like the skeleton in `Scene::from_xac_root`, so a track can
+//! replace the local transform of its node as it is. A channel without keys
+//! holds the submotion's pose value, keeping the whole pose defined by the
+//! motion rather than falling back to the bind pose.
+//!
+//! Whichever chunk version a motion uses (f32 or 16-bit quaternion keys, or
+//! wavelet compressed samples), it becomes the same `AnimationClip` with f32
+//! quaternions, which `sample` interpolates at any point in time.
+
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::mesh::{Vector3, Vector4};
+use crate::xsm::{
+    File16BitQuaternion, FileQuaternion, FileVector3, XSMChunkData, XSMRoot, XSMSkeletalSubMotion2,
+    XSMVector3Key,
+};
+
+const IDENTITY_QUAT: Vector4 = Vector4 {
+    x: 0.0,
+    y: 0.0,
+    z: 0.0,
+    w: 1.0,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Keyframe<T> {
+    /// Seconds from the start of the motion
+    pub time: f32,
+    pub value: T,
+}
+
+/// Keys of one skeleton node
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BoneTrack {
+    /// Name of the XAC node the track animates
+    pub name: String,
+    /// Skeleton node index, set by `Scene::add_animation`
+    #[serde(default)]
+    pub node_index: Option<u32>,
+    pub translations: Vec<Keyframe<Vector3>>,
+    pub rotations: Vec<Keyframe<Vector4>>,
+    pub scales: Vec<Keyframe<Vector3>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AnimationClip {
+    pub name: String,
+    /// Frame rate the motion was exported at
+    #[serde(default)]
+    pub fps: u32,
+    /// Seconds, the time of the last key
+    #[serde(default)]
+    pub duration: f32,
+    pub tracks: Vec<BoneTrack>,
+}
+
+/// Local transform of one node at a point in time
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BonePose {
+    pub name: String,
+    pub node_index: Option<u32>,
+    pub translation: Vector3,
+    pub rotation: Vector4,
+    pub scale: Vector3,
+}
+
+impl AnimationClip {
+    /// Tracks of the submotion chunks, decompressing wavelet compressed ones
+    pub fn from_xsm_root(xsm: &XSMRoot) -> io::Result<Self> {
+        let mut animation = AnimationClip::default();
+        for entry in &xsm.chunks {
+            match &entry.chunk_data {
+                XSMChunkData::Info(info) => {
+                    animation.name = info.motion_name.clone();
+                    animation.fps = info.motion_fps;
+                }
+                XSMChunkData::Info2(info) => {
+                    animation.name = info.motion_name.clone();
+                    animation.fps = info.motion_fps;
+                }
+                XSMChunkData::Info3(info) => {
+                    animation.name = info.motion_name.clone();
+                    animation.fps = info.motion_fps;
+                }
+                XSMChunkData::SubMotions(sub_motions) => {
+                    animation.tracks.extend(
+                        sub_motions
+                            .sub_motions
+                            .iter()
+                            .map(BoneTrack::from_sub_motion),
+                    );
+                }
+                XSMChunkData::SubMotions2(sub_motions) => {
+                    animation
+                        .tracks
+                        .extend(sub_motions.sub_motions.iter().map(|s| {
+                            BoneTrack::new(
+                                &s.name,
+                                (&s.pose_pos, &s.pose_scale),
+                                decode_quat16(&s.pose_rot),
+                                &s.pos_keys,
+                                s.rot_keys.iter().map(|k| (k.time, decode_quat16(&k.value))),
+                                &s.scale_keys,
+                            )
+                        }));
+                }
+                XSMChunkData::WaveletInfo(wavelet) => {
+                    let sub_motions = wavelet.decompress()?;
+                    animation
+                        .tracks
+                        .extend(sub_motions.iter().map(BoneTrack::from_sub_motion));
+                }
+            }
+        }
+        animation.duration = animation
+            .tracks
+            .iter()
+            .flat_map(|track| {
+                [
+                    track.translations.last().map(|k| k.time),
+                    track.rotations.last().map(|k| k.time),
+                    track.scales.last().map(|k| k.time),
+                ]
+            })
+            .flatten()
+            .fold(0.0, f32::max);
+        Ok(animation)
+    }
+
+    /// Pose of every track at `time` seconds, clamped to the clip
+    pub fn sample(&self, time: f32) -> Vec<BonePose> {
+        let time = time.clamp(0.0, self.duration);
+        self.tracks.iter().map(|track| track.sample(time)).collect()
+    }
+}
+
+impl BoneTrack {
+    fn from_sub_motion(s: &XSMSkeletalSubMotion2) -> Self {
+        BoneTrack::new(
+            &s.name,
+            (&s.pose_pos, &s.pose_scale),
+            quat(&s.pose_rot),
+            &s.pos_keys,
+            s.rot_keys.iter().map(|k| (k.time, quat(&k.value))),
+            &s.scale_keys,
+        )
+    }
+
+    /// Linear between translation and scale keys, spherical between
+    /// rotations. Times outside the keys hold the first or last key.
+    pub fn sample(&self, time: f32) -> BonePose {
+        BonePose {
+            name: self.name.clone(),
+            node_index: self.node_index,
+            translation: sample_keys(&self.translations, time, lerp).unwrap_or_default(),
+            rotation: sample_keys(&self.rotations, time, slerp).unwrap_or(IDENTITY_QUAT),
+            scale: sample_keys(&self.scales, time, lerp).unwrap_or(Vector3 {
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            }),
+        }
+    }
+
+    /// Scale rotation keys are dropped, glTF has no channel for them
+    fn new(
+        name: &str,
+        (pose_pos, pose_scale): (&FileVector3, &FileVector3),
+        pose_rot: Vector4,
+        pos_keys: &[XSMVector3Key],
+        rot_keys: impl Iterator<Item = (f32, Vector4)>,
+        scale_keys: &[XSMVector3Key],
+    ) -> Self {
+        let mut rotations = keyframes(
+            rot_keys.map(|(time, q)| (time, mirror_rotation(q))),
+            mirror_rotation(pose_rot),
+        );
+        // Keep neighbours in the same hemisphere so slerp takes the short way
+        for i in 1..rotations.len() {
+            let (prev, next) = (&rotations[i - 1].value, &rotations[i].value);
+            if prev.x * next.x + prev.y * next.y + prev.z * next.z + prev.w * next.w < 0.0 {
+                let q = &mut rotations[i].value;
+                (q.x, q.y, q.z, q.w) = (-q.x, -q.y, -q.z, -q.w);
+            }
+        }
+
+        BoneTrack {
+            name: name.to_string(),
+            node_index: None,
+            translations: keyframes(
+                pos_keys.iter().map(|k| (k.time, mirror_position(&k.value))),
+                mirror_position(pose_pos),
+            ),
+            rotations,
+            scales: keyframes(
+                scale_keys.iter().map(|k| (k.time, vector(&k.value))),
+                vector(pose_scale),
+            ),
+        }
+    }
+}
+
+fn sample_keys<T: Clone>(
+    keys: &[Keyframe<T>],
+    time: f32,
+    interpolate: fn(&T, &T, f32) -> T,
+) -> Option<T> {
+    let next = keys.partition_point(|k| k.time <= time);
+    if next == 0 {
+        return keys.first().map(|k| k.value.clone());
+    }
+    let prev = &keys[next - 1];
+    let Some(next) = keys.get(next) else {
+        return Some(prev.value.clone());
+    };
+    let t = (time - prev.time) / (next.time - prev.time);
+    Some(interpolate(&prev.value, &next.value, t))
+}
+
+fn lerp(a: &Vector3, b: &Vector3, t: f32) -> Vector3 {
+    Vector3 {
+        x: a.x + (b.x - a.x) * t,
+        y: a.y + (b.y - a.y) * t,
+        z: a.z + (b.z - a.z) * t,
+    }
+}
+
+/// Shortest arc, normalized lerp where the two are too close to divide by
+fn slerp(a: &Vector4, b: &Vector4, t: f32) -> Vector4 {
+    let mut dot = a.x * b.x + a.y * b.y + a.z * b.z + a.w * b.w;
+    let sign = if dot < 0.0 { -1.0 } else { 1.0 };
+    dot *= sign;
+    let (wa, wb) = if dot > 0.9995 {
+        (1.0 - t, t)
+    } else {
+        let angle = dot.acos();
+        let sin = angle.sin();
+        (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+    };
+    let wb = wb * sign;
+    normalize(Vector4 {
+        x: a.x * wa + b.x * wb,
+        y: a.y * wa + b.y * wb,
+        z: a.z * wa + b.z * wb,
+        w: a.w * wa + b.w * wb,
+    })
+}
+
+/// Keys with increasing times, or the pose alone when there are none
+fn keyframes<T>(keys: impl Iterator<Item = (f32, T)>, pose: T) -> Vec<Keyframe<T>> {
+    let mut frames: Vec<Keyframe<T>> = Vec::new();
+    for (time, value) in keys {
+        let increasing = frames.last().is_none_or(|last| time > last.time);
+        if time.is_finite() && time >= 0.0 && increasing {
+            frames.push(Keyframe { time, value });
+        }
+    }
+    if frames.is_empty() {
+        frames.push(Keyframe {
+            time: 0.0,
+            value: pose,
+        });
+    }
+    frames
+}
+
+/// Unit quaternion of a 16-bit one
+pub fn decode_quat16(q: &File16BitQuaternion) -> Vector4 {
+    quat(&q.to_quaternion())
+}
+
+fn quat(q: &FileQuaternion) -> Vector4 {
+    normalize(Vector4 {
+        x: q.x,
+        y: q.y,
+        z: q.z,
+        w: q.w,
+    })
+}
+
+fn normalize(q: Vector4) -> Vector4 {
+    let len = (q.x * q.x + q.y * q.y + q.z * q.z + q.w * q.w).sqrt();
+    if len > 0.0 && len.is_finite() {
+        Vector4 {
+            x: q.x / len,
+            y: q.y / len,
+            z: q.z / len,
+            w: q.w / len,
+        }
+    } else {
+        IDENTITY_QUAT
+    }
+}
+
+fn vector(v: &FileVector3) -> Vector3 {
+    Vector3 {
+        x: v.x,
+        y: v.y,
+        z: v.z,
+    }
+}
+
+fn mirror_position(v: &FileVector3) -> Vector3 {
+    Vector3 {
+        x: -v.x,
+        y: v.y,
+        z: v.z,
+    }
+}
+
+fn mirror_rotation(q: Vector4) -> Vector4 {
+    Vector4 {
+        x: q.x,
+        y: -q.y,
+        z: -q.z,
+        w: q.w,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_quat16() {
+        let q = decode_quat16(&File16BitQuaternion {
+            x: 0,
+            y: 23170,
+            z: 0,
+            w: 23170,
+        });
+        assert!((q.y - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-4);
+        assert!((q.w - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-4);
+        let identity = decode_quat16(&File16BitQuaternion::default());
+        assert_eq!(identity.w, 1.0);
+    }
+
+    #[test]
+    fn test_sample_track() {
+        let vector = |x| Vector3 { x, y: 0.0, z: 0.0 };
+        let half_turn = Vector4 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+            w: 0.0,
+        };
+        let track = BoneTrack {
+            name: "Bip01".to_string(),
+            translations: vec![
+                Keyframe {
+                    time: 0.0,
+                    value: vector(0.0),
+                },
+                Keyframe {
+                    time: 1.0,
+                    value: vector(4.0),
+                },
+            ],
+            rotations: vec![
+                Keyframe {
+                    time: 0.0,
+                    value: IDENTITY_QUAT,
+                },
+                Keyframe {
+                    time: 1.0,
+                    value: half_turn,
+                },
+            ],
+            ..BoneTrack::default()
+        };
+        let clip = AnimationClip {
+            duration: 1.0,
+            tracks: vec![track],
+            ..AnimationClip::default()
+        };
+
+        let pose = &clip.sample(0.25)[0];
+        assert!((pose.translation.x - 1.0).abs() < 1e-5);
+        // A quarter of a half turn about Y
+        let angle = std::f32::consts::PI / 8.0;
+        assert!((pose.rotation.y - angle.sin()).abs() < 1e-5);
+        assert!((pose.rotation.w - angle.cos()).abs() < 1e-5);
+        // No scale keys, times past the end hold the last key
+        assert_eq!(pose.scale.x, 1.0);
+        let end = &clip.sample(5.0)[0];
+        assert!((end.translation.x - 4.0).abs() < 1e-5);
+        assert!((end.rotation.y - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_animation_from_xsm() -> io::Result<()> {
+        let xsm = XSMRoot::from_file("tests/npc_lecifer_run.xsm")?;
+        let animation = AnimationClip::from_xsm_root(&xsm)?;
+        assert!(!animation.tracks.is_empty());
+        for track in &animation.tracks {
+            for frames in [track.translations.len(), track.rotations.len()] {
+                assert!(frames >= 1, "{} has no keys", track.name);
+            }
+            assert!(
+                track
+                    .rotations
+                    .windows(2)
+                    .all(|pair| pair[0].time < pair[1].time)
+            );
+            for key in &track.rotations {
+                let q = &key.value;
+                let len = (q.x * q.x + q.y * q.y + q.z * q.z + q.w * q.w).sqrt();
+                assert!((len - 1.0).abs() < 1e-4);
+            }
+        }
+        assert!(animation.tracks.iter().any(|t| t.rotations.len() > 1));
+        assert_eq!(animation.fps, 30);
+        assert!(animation.duration > 0.0);
+        let last = animation.sample(animation.duration);
+        assert_eq!(last.len(), animation.tracks.len());
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 use actix_files::NamedFile;
-use actix_web::{HttpResponse, Responder, get, web};
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse, Responder, get, post, web};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::collections::HashMap;
@@ -9,16 +10,24 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use tera::{Context, Tera};
 
-use crate::category::Folder;
-use crate::ies::IESRoot;
-use crate::ipf::FileSizeStats;
+use crate::category::{
+    FileFilter, FileRef, FileVersions, Folder, FolderRef, FolderStats, PathMatcher,
+    TreeMemoryUsage, encode_query_path, natural_cmp,
+};
+use crate::class_index::ClassIndex;
+use crate::collection::CollectionData;
+use crate::content_index::{self, ContextLine, IndexedDoc, SharedContentIndex};
+use crate::handler::{FormatRegistry, HandlerContext, HandlerError, Output};
+use crate::http_cache::{self, CachedPreview, PreviewCache};
 use crate::ipf::IPFFileTable;
-use crate::mesh::*;
-use crate::threedworld::World;
-use crate::xac::XACRoot;
+use crate::ipf::{ExtensionStats, FileSizeStats, IPFHeader};
+use crate::patch::PatchTimeline;
+use crate::recipe::{Recipe, RecipeGraph};
+use crate::sniff::{self, FileFormat};
+use crate::spawn::{SpawnData, SpawnGroup};
+use crate::texture::TextureIndex;
 use crate::xml;
-use crate::xpm::XPMRoot;
-use crate::xsm::XSMRoot;
+use crate::zip::{ChannelWriter, ZipWriter};
 
 /// -------------------------
 /// Startup Info Endpoint
@@ -39,6 +48,26 @@ pub struct InfoResponse {
     pub uncompressed_lowest: u32,
     pub uncompressed_highest: u32,
     pub uncompressed_avg: u32,
+    /// Size histograms per lowercase extension
+    pub extensions: BTreeMap<String, ExtensionStats>,
+    pub memory: MemoryReport,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemoryReport {
+    pub tree: TreeMemoryUsage,
+    /// Decoded previews held by the preview cache
+    pub preview_cache_bytes: usize,
+    /// Resident set size of the whole process, when the OS reports it
+    pub resident_bytes: Option<u64>,
+}
+
+/// VmRSS from /proc/self/status (Linux only)
+fn resident_memory_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
 }
 
 pub struct Duplicates {
@@ -49,12 +78,19 @@ pub struct Duplicates {
     pub dds: Arc<HashMap<String, String>>,
 }
 
+pub struct ClassIndexes {
+    pub items: Arc<ClassIndex>,
+    pub monsters: Arc<ClassIndex>,
+    pub maps: Arc<ClassIndex>,
+}
+
 #[get("/api/info")]
 pub async fn api_info(
     folder_tree: web::Data<Arc<Folder>>,
     game_root: web::Data<PathBuf>,
     file_stats: web::Data<FileSizeStats>,
     duplicates: web::Data<Duplicates>,
+    preview_cache: web::Data<PreviewCache>,
 ) -> impl Responder {
     let game_root_data = game_root.to_str().unwrap().to_string();
 
@@ -73,22 +109,97 @@ pub async fn api_info(
         uncompressed_lowest: file_stats.uncompressed_lowest,
         uncompressed_highest: file_stats.uncompressed_highest,
         uncompressed_avg: file_stats.uncompressed_avg,
+        extensions: file_stats.extensions.clone(),
+        memory: MemoryReport {
+            tree: folder_tree.memory_usage(),
+            preview_cache_bytes: preview_cache.used_bytes(),
+            resident_bytes: resident_memory_bytes(),
+        },
     })
 }
 
+/// -------------------------
+/// Folder Size Tree
+/// -------------------------
+#[derive(Debug, Deserialize)]
+pub struct TreeStatsQuery {
+    #[serde(default)]
+    pub path: String,
+    /// Levels of child folders to include, capped at `MAX_TREE_STATS_DEPTH`
+    #[serde(default)]
+    pub depth: Option<usize>,
+}
+
+const MAX_TREE_STATS_DEPTH: usize = 8;
+
+#[get("/api/stats/tree")]
+pub async fn tree_stats(
+    query: web::Query<TreeStatsQuery>,
+    folder_tree: web::Data<Arc<Folder>>,
+) -> impl Responder {
+    let Some(folder) = folder_tree.folder(&query.path) else {
+        return HttpResponse::NotFound().body("Folder not found");
+    };
+    let depth = query.depth.unwrap_or(1).min(MAX_TREE_STATS_DEPTH);
+    let stats: FolderStats = folder.stats(depth);
+    HttpResponse::Ok().json(stats)
+}
+
 /// -------------------------
 /// Shallow Folder Search
 /// -------------------------
 #[derive(Debug, Deserialize)]
 pub struct ShallowSearchQuery {
     pub folder_name: String,
+    /// "name" (default), "size", "versions" or "type"
+    #[serde(default)]
+    pub sort_by: Option<String>,
+    #[serde(default)]
+    pub desc: Option<bool>,
+    /// Paging runs over subfolders first, then files
+    #[serde(default)]
+    pub offset: Option<usize>,
+    /// Defaults to `DEFAULT_LISTING_LIMIT`
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Rows returned when no limit is given, every file row sniffs its content
+const DEFAULT_LISTING_LIMIT: usize = 500;
+
+#[derive(Debug, Serialize)]
+pub struct FolderListingEntry {
+    pub name: String,
+    pub path: String,
+    /// Distinct files in this folder and below
+    pub file_count: u32,
+    pub version_count: u32,
+    /// Uncompressed size of the latest version of every file below
+    pub total_size: u64,
 }
 
 #[derive(Debug, Serialize)]
-pub struct ShallowSearchResponse {
+pub struct FileListingEntry<'a> {
+    pub name: &'a str,
+    pub path: String,
+    pub versions: usize,
+    pub size_compressed: u32,
+    pub size_uncompressed: u32,
+    /// Archives holding the versions, oldest first
+    pub archives: Vec<&'a str>,
+    pub file_type: &'static str,
+    /// Sniffed from the latest version's content
+    pub format: FileFormat,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ShallowSearchResponse<'a> {
     pub folder_name: String,
-    pub subfolders: Vec<String>,
-    pub files: Vec<String>,
+    pub total_subfolders: usize,
+    pub total_files: usize,
+    pub offset: usize,
+    pub subfolders: Vec<FolderListingEntry>,
+    pub files: Vec<FileListingEntry<'a>>,
 }
 
 #[get("/api/folder/shallow")]
@@ -96,31 +207,103 @@ pub async fn folder_shallow(
     query: web::Query<ShallowSearchQuery>,
     folder_tree: web::Data<Arc<Folder>>,
 ) -> impl Responder {
-    if let Some((subfolders, files)) = folder_tree.search_folder_shallow(&query.folder_name) {
-        // deduplicate subfolders
-        let subfolders: Vec<String> = subfolders
-            .iter()
-            .collect::<HashSet<_>>()
-            .into_iter()
-            .cloned()
-            .collect();
+    let Some(folder) = folder_tree.folder(&query.folder_name) else {
+        return HttpResponse::NotFound().body("Folder not found");
+    };
+    let folder_path = folder.path();
+    let join = |name: &str| {
+        if folder_path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", folder_path, name)
+        }
+    };
 
-        // deduplicate files
-        let files: Vec<String> = files
-            .iter()
-            .collect::<HashSet<_>>()
-            .into_iter()
-            .cloned()
-            .collect();
+    let mut subfolders: Vec<FolderRef> = folder.subfolders().collect();
+    let mut files: Vec<FileVersions> = folder.file_versions().collect();
 
-        HttpResponse::Ok().json(ShallowSearchResponse {
-            folder_name: query.folder_name.clone(),
-            subfolders,
-            files,
-        })
-    } else {
-        HttpResponse::NotFound().body("Folder not found")
+    match query.sort_by.as_deref().unwrap_or("name") {
+        "size" => {
+            subfolders.sort_by_key(|f| f.total_size());
+            files.sort_by_key(|f| f.latest().file_size_uncompressed);
+        }
+        "versions" => {
+            subfolders.sort_by_key(|f| f.version_count());
+            files.sort_by_key(|f| f.len());
+        }
+        "type" => {
+            subfolders.sort_by(|a, b| natural_cmp(a.name(), b.name()));
+            files.sort_by(|a, b| {
+                FileFormat::from_extension(a.name())
+                    .kind()
+                    .cmp(FileFormat::from_extension(b.name()).kind())
+                    .then_with(|| natural_cmp(a.name(), b.name()))
+            });
+        }
+        "name" => {
+            subfolders.sort_by(|a, b| natural_cmp(a.name(), b.name()));
+            files.sort_by(|a, b| natural_cmp(a.name(), b.name()));
+        }
+        other => {
+            return HttpResponse::BadRequest().body(format!("Unknown sort_by: {}", other));
+        }
+    }
+    if query.desc.unwrap_or(false) {
+        subfolders.reverse();
+        files.reverse();
     }
+
+    let offset = query.offset.unwrap_or(0);
+    let end = offset.saturating_add(query.limit.unwrap_or(DEFAULT_LISTING_LIMIT));
+    let folder_count = subfolders.len();
+
+    let subfolder_entries = subfolders
+        .iter()
+        .take(end.min(folder_count))
+        .skip(offset)
+        .map(|f| FolderListingEntry {
+            name: f.name().to_string(),
+            path: join(f.name()),
+            file_count: f.file_count(),
+            version_count: f.version_count(),
+            total_size: f.total_size(),
+        })
+        .collect();
+
+    let file_entries = files
+        .iter()
+        .take(end.saturating_sub(folder_count))
+        .skip(offset.saturating_sub(folder_count))
+        .map(|f| {
+            let latest = f.latest();
+            let mut archives: Vec<&str> = Vec::new();
+            for version in f.versions() {
+                let name = version.archive().name.as_str();
+                if !archives.contains(&name) {
+                    archives.push(name);
+                }
+            }
+            FileListingEntry {
+                name: f.name(),
+                path: join(f.name()),
+                versions: f.len(),
+                size_compressed: latest.file_size_compressed,
+                size_uncompressed: latest.file_size_uncompressed,
+                archives,
+                file_type: FileFormat::from_extension(f.name()).kind(),
+                format: latest.format(),
+            }
+        })
+        .collect();
+
+    HttpResponse::Ok().json(ShallowSearchResponse {
+        folder_name: folder_path.clone(),
+        total_subfolders: folder_count,
+        total_files: files.len(),
+        offset,
+        subfolders: subfolder_entries,
+        files: file_entries,
+    })
 }
 
 /// -------------------------
@@ -129,12 +312,29 @@ pub async fn folder_shallow(
 #[derive(Debug, Deserialize)]
 pub struct FileSearchQuery {
     pub file_name: String,
+    /// "words" (default), "prefix", "glob" or "regex"
+    #[serde(default)]
+    pub mode: Option<String>,
+    /// Comma separated extensions, e.g. "xac,xsm"
+    #[serde(default)]
+    pub ext: Option<String>,
+    /// Uncompressed size bounds in bytes
+    #[serde(default)]
+    pub min_size: Option<u32>,
+    #[serde(default)]
+    pub max_size: Option<u32>,
+    #[serde(default)]
+    pub offset: Option<usize>,
+    /// Defaults to `DEFAULT_LISTING_LIMIT`
+    #[serde(default)]
+    pub limit: Option<usize>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct FileSearchItemVersioned<'a> {
     pub version: usize, // version index
     pub file_path: &'a str,
+    pub format: FileFormat,
     pub download_url: String,
     pub parse_url: String,
 }
@@ -142,6 +342,8 @@ pub struct FileSearchItemVersioned<'a> {
 #[derive(Debug, Serialize)]
 pub struct RecursiveSearchResponseVersioned<'a> {
     pub file_name: &'a str,
+    pub total: usize,
+    pub offset: usize,
     pub found_files: Vec<FileSearchItemVersioned<'a>>,
 }
 
@@ -150,15 +352,35 @@ pub async fn search_file_recursive(
     query: web::Query<FileSearchQuery>,
     folder_tree: web::Data<Arc<Folder>>,
 ) -> impl Responder {
-    let results = folder_tree.search_file_recursive(&query.file_name, "");
+    let matcher = match query.mode.as_deref().unwrap_or("words") {
+        "words" => PathMatcher::words(&query.file_name),
+        "prefix" => PathMatcher::prefix(&query.file_name),
+        "glob" => PathMatcher::glob(&query.file_name),
+        "regex" => match PathMatcher::regex(&query.file_name) {
+            Ok(matcher) => matcher,
+            Err(e) => return HttpResponse::BadRequest().body(format!("Invalid regex: {}", e)),
+        },
+        other => {
+            return HttpResponse::BadRequest().body(format!("Unknown search mode: {}", other));
+        }
+    };
+    let filter = FileFilter::new(matcher)
+        .with_extensions(query.ext.as_deref().unwrap_or_default())
+        .with_size(query.min_size, query.max_size);
+
+    let results = folder_tree.search_files(&filter);
+    let offset = query.offset.unwrap_or(0);
 
     let items: Vec<FileSearchItemVersioned> = results
         .iter()
         .enumerate() // enumerate to get version
+        .skip(offset)
+        .take(query.limit.unwrap_or(DEFAULT_LISTING_LIMIT))
         .map(
-            |(version, (full_path, _file_table))| FileSearchItemVersioned {
+            |(version, (full_path, file_table))| FileSearchItemVersioned {
                 version,
                 file_path: full_path.as_str(),
+                format: file_table.format(),
                 download_url: format!("/api/file/download?path={}&version={}", full_path, version),
                 parse_url: format!("/api/file/parse?path={}&version={}", full_path, version),
             },
@@ -167,10 +389,105 @@ pub async fn search_file_recursive(
 
     HttpResponse::Ok().json(RecursiveSearchResponseVersioned {
         file_name: &query.file_name,
+        total: results.len(),
+        offset,
         found_files: items,
     })
 }
 
+/// -------------------------
+/// Full-Text Content Search
+/// -------------------------
+#[derive(Debug, Deserialize)]
+pub struct ContentSearchQuery {
+    pub query: String,
+    #[serde(default)]
+    pub offset: Option<usize>,
+    /// Defaults to 50, every result re-extracts its file for context
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContentSearchItem {
+    pub file_path: String,
+    pub version: usize,
+    pub download_url: String,
+    pub matches: Vec<ContextLine>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContentSearchResponse<'a> {
+    pub query: &'a str,
+    pub total: usize,
+    pub offset: usize,
+    pub results: Vec<ContentSearchItem>,
+}
+
+#[get("/api/content/search")]
+pub async fn content_search(
+    query: web::Query<ContentSearchQuery>,
+    content_index: web::Data<SharedContentIndex>,
+    folder_tree: web::Data<Arc<Folder>>,
+) -> impl Responder {
+    let offset = query.offset.unwrap_or(0);
+    let (total, page) = {
+        let Ok(guard) = content_index.read() else {
+            return HttpResponse::InternalServerError().body("Content index is unavailable");
+        };
+        let Some(index) = guard.as_ref() else {
+            return HttpResponse::ServiceUnavailable().body("Content index is still being built");
+        };
+        let docs = index.search(&query.query);
+        let page: Vec<IndexedDoc> = docs
+            .iter()
+            .skip(offset)
+            .take(query.limit.unwrap_or(50))
+            .map(|&doc| doc.clone())
+            .collect();
+        (docs.len(), page)
+    };
+
+    // Every result extracts and decompresses its file, keep that off the workers
+    let tree = folder_tree.get_ref().clone();
+    let words = query.query.clone();
+    let results = web::block(move || {
+        page.into_iter()
+            .map(|doc| {
+                let versions = tree.search_file_by_full_path(&doc.path);
+                let matches = versions
+                    .get(doc.version)
+                    .and_then(|(_, file_table)| file_table.extract_data().ok())
+                    .and_then(|data| content_index::extract_text(&doc.path, &data))
+                    .map(|text| content_index::match_context(&text, &words, 5))
+                    .unwrap_or_default();
+
+                ContentSearchItem {
+                    download_url: format!(
+                        "/api/file/download?path={}&version={}",
+                        encode_query_path(&doc.path),
+                        doc.version
+                    ),
+                    file_path: doc.path,
+                    version: doc.version,
+                    matches,
+                }
+            })
+            .collect::<Vec<_>>()
+    })
+    .await;
+    let Ok(results) = results else {
+        return HttpResponse::InternalServerError().body("Content search failed");
+    };
+
+    HttpResponse::Ok().json(ContentSearchResponse {
+        query: &query.query,
+        total,
+        offset,
+        results,
+    })
+}
+
 /// -------------------------
 /// Full Path File Search
 /// -------------------------
@@ -188,6 +505,8 @@ pub struct FileFullPathInfo<'a> {
     pub file_size_compressed: u32,
     pub file_size_uncompressed: u32,
     pub file_pointer: u32, // offset in the IPF archive
+    /// Sniffed from the version's content
+    pub format: FileFormat,
     pub download_url: String,
     pub parse_url: String,
 }
@@ -207,16 +526,13 @@ pub async fn search_file_fullpath(
         .enumerate() // <-- get vector index for version
         .map(|(version, (_full_path, file_table))| FileFullPathInfo {
             version,
-            file_path: file_table
-                .file_path
-                .as_ref()
-                .map(|p| p.to_string_lossy().to_string())
-                .unwrap_or_else(|| "".to_string()),
-            container_name: &file_table.container_name,
+            file_path: file_table.file_path().to_string_lossy().to_string(),
+            container_name: file_table.container_name(),
             crc32: file_table.crc32,
             file_size_compressed: file_table.file_size_compressed,
             file_size_uncompressed: file_table.file_size_uncompressed,
             file_pointer: file_table.file_pointer,
+            format: file_table.format(),
             download_url: format!(
                 "/api/file/download?path={}&version={}",
                 query.full_path, version
@@ -231,6 +547,215 @@ pub async fn search_file_fullpath(
     HttpResponse::Ok().json(items)
 }
 
+/// -------------------------
+/// Archive Browsing
+/// -------------------------
+#[derive(Debug, Serialize)]
+pub struct ArchiveInfo<'a> {
+    pub id: u16,
+    pub name: &'a str,
+    pub path: String,
+    pub is_patch: bool,
+    /// File versions of this archive present in the tree
+    pub file_count: u32,
+    pub header: Option<&'a IPFHeader>,
+}
+
+#[get("/api/archives")]
+pub async fn archive_list(folder_tree: web::Data<Arc<Folder>>) -> impl Responder {
+    let counts = folder_tree.archive_file_counts();
+    let archives: Vec<ArchiveInfo> = folder_tree
+        .archives()
+        .iter()
+        .enumerate()
+        .map(|(id, archive)| ArchiveInfo {
+            id: id as u16,
+            name: &archive.name,
+            path: archive.path.to_string_lossy().to_string(),
+            is_patch: archive.is_patch(),
+            file_count: counts[id],
+            header: archive.header.as_ref(),
+        })
+        .collect();
+
+    HttpResponse::Ok().json(archives)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArchiveFilesQuery {
+    #[serde(default)]
+    pub offset: Option<usize>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchiveFileEntry<'a> {
+    pub path: String,
+    pub version: usize,
+    pub container_name: &'a str,
+    pub crc32: u32,
+    pub file_size_compressed: u32,
+    pub file_size_uncompressed: u32,
+    pub file_pointer: u32,
+    pub download_url: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchiveFilesResponse<'a> {
+    pub archive: ArchiveInfo<'a>,
+    pub total: usize,
+    pub offset: usize,
+    pub files: Vec<ArchiveFileEntry<'a>>,
+}
+
+#[get("/api/archives/{name}/files")]
+pub async fn archive_files(
+    path: web::Path<String>,
+    query: web::Query<ArchiveFilesQuery>,
+    folder_tree: web::Data<Arc<Folder>>,
+) -> impl Responder {
+    let Some(id) = folder_tree.archives().find(&path) else {
+        return HttpResponse::NotFound().body("Archive not found");
+    };
+    let archive = folder_tree.archives().get(id);
+    let found = folder_tree.archive_files(id);
+    let offset = query.offset.unwrap_or(0);
+    let limit = query.limit.unwrap_or(usize::MAX);
+
+    let files = found
+        .iter()
+        .skip(offset)
+        .take(limit)
+        .map(|(full_path, file)| {
+            let version = file.version();
+            ArchiveFileEntry {
+                download_url: format!("/api/file/download?path={}&version={}", full_path, version),
+                path: full_path.clone(),
+                version,
+                container_name: file.container_name(),
+                crc32: file.crc32,
+                file_size_compressed: file.file_size_compressed,
+                file_size_uncompressed: file.file_size_uncompressed,
+                file_pointer: file.file_pointer,
+            }
+        })
+        .collect();
+
+    HttpResponse::Ok().json(ArchiveFilesResponse {
+        archive: ArchiveInfo {
+            id,
+            name: &archive.name,
+            path: archive.path.to_string_lossy().to_string(),
+            is_patch: archive.is_patch(),
+            file_count: found.len() as u32,
+            header: archive.header.as_ref(),
+        },
+        total: found.len(),
+        offset,
+        files,
+    })
+}
+
+/// -------------------------
+/// Patch Timeline
+/// -------------------------
+#[derive(Debug, Serialize)]
+pub struct PatchSummary<'a> {
+    pub name: &'a str,
+    pub version_to_patch: u32,
+    pub new_version: u32,
+    pub added: usize,
+    pub overridden: usize,
+    pub detail_url: String,
+}
+
+#[get("/api/patch/timeline")]
+pub async fn patch_timeline(timeline: web::Data<PatchTimeline>) -> impl Responder {
+    let patches: Vec<PatchSummary> = timeline
+        .patches
+        .iter()
+        .map(|p| PatchSummary {
+            name: &p.name,
+            version_to_patch: p.version_to_patch,
+            new_version: p.new_version,
+            added: p.added.len(),
+            overridden: p.overridden.len(),
+            detail_url: format!("/api/patch/timeline/{}", p.name),
+        })
+        .collect();
+
+    HttpResponse::Ok().json(patches)
+}
+
+#[get("/api/patch/timeline/{name}")]
+pub async fn patch_detail(
+    path: web::Path<String>,
+    timeline: web::Data<PatchTimeline>,
+) -> impl Responder {
+    match timeline.patch(&path) {
+        Some(patch) => HttpResponse::Ok().json(patch),
+        None => HttpResponse::NotFound().body("Patch not found"),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FileHistoryQuery {
+    pub path: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileHistoryEntry<'a> {
+    /// Version index as used by the download and parse endpoints
+    pub version: usize,
+    pub archive: &'a str,
+    /// `new_version` of the patch that introduced this version, `None` for base archives
+    pub patch: Option<u32>,
+    pub crc32: u32,
+    pub file_size_compressed: u32,
+    pub file_size_uncompressed: u32,
+    /// Whether the content differs from the previous version
+    pub changed: bool,
+}
+
+#[get("/api/file/history")]
+pub async fn file_history(
+    query: web::Query<FileHistoryQuery>,
+    folder_tree: web::Data<Arc<Folder>>,
+    timeline: web::Data<PatchTimeline>,
+) -> impl Responder {
+    let results = folder_tree.search_file_by_full_path(&query.path);
+    if results.is_empty() {
+        return HttpResponse::NotFound().body("File not found");
+    }
+
+    let versions: Vec<_> = results.iter().map(|(_, file)| *file).collect();
+    let mut previous_crc = None;
+    let history: Vec<FileHistoryEntry> = timeline
+        .history(&versions)
+        .into_iter()
+        .map(|file| {
+            let archive = file.archive();
+            let entry = FileHistoryEntry {
+                version: file.version(),
+                archive: &archive.name,
+                patch: archive
+                    .is_patch()
+                    .then(|| archive.header.as_ref().map(|h| h.new_version))
+                    .flatten(),
+                crc32: file.crc32,
+                file_size_compressed: file.file_size_compressed,
+                file_size_uncompressed: file.file_size_uncompressed,
+                changed: previous_crc != Some(file.crc32),
+            };
+            previous_crc = Some(file.crc32);
+            entry
+        })
+        .collect();
+
+    HttpResponse::Ok().json(history)
+}
+
 /// -------------------------
 /// Download Raw Binary File
 /// -------------------------
@@ -243,298 +768,863 @@ pub struct FileDownloadQuery {
 
 #[get("/api/file/download")]
 pub async fn download_file(
+    req: HttpRequest,
     query: web::Query<FileDownloadQuery>,
     folder_tree: web::Data<Arc<Folder>>,
 ) -> impl Responder {
     let results = folder_tree.search_file_by_full_path(&query.path);
 
     let version = query.version.unwrap_or(0); // default to 0
-    if let Some((_full_path, file_table)) = results.get(version) {
-        if let Ok(data) = file_table.extract_data() {
-            let filename = file_table.directory_name.as_str();
-            return HttpResponse::Ok()
-                .insert_header((
-                    "Content-Disposition",
-                    format!("attachment; filename=\"{}\"", filename),
-                ))
-                .content_type("application/octet-stream")
-                .body(data);
-        }
+    let Some((_full_path, file_table)) = results.get(version) else {
+        return HttpResponse::NotFound().body("File not found");
+    };
+
+    let etag = http_cache::entry_etag(file_table, "raw");
+    if http_cache::not_modified(&req, &etag) {
+        return http_cache::not_modified_response(&etag);
     }
 
-    HttpResponse::NotFound().body("File not found")
+    let Ok(data) = file_table.extract_data() else {
+        return HttpResponse::InternalServerError().body("Failed to extract file data");
+    };
+
+    let mut builder = HttpResponse::Ok();
+    builder.insert_header((
+        "Content-Disposition",
+        format!("attachment; filename=\"{}\"", file_table.name()),
+    ));
+    http_cache::ranged_response(
+        &req,
+        builder,
+        "application/octet-stream",
+        &etag,
+        Bytes::from(data),
+    )
 }
 
 /// -------------------------
-/// Parse as IES
+/// Parse, Preview and Export
 /// -------------------------
+fn handler_error_response(error: HandlerError) -> HttpResponse {
+    match error {
+        HandlerError::Unsupported(msg) => HttpResponse::UnsupportedMediaType().body(msg),
+        HandlerError::Parse(msg) => HttpResponse::InternalServerError().body(msg),
+        HandlerError::Missing(msg) => HttpResponse::NotFound().body(msg),
+        HandlerError::BadRequest(msg) => HttpResponse::BadRequest().body(msg),
+    }
+}
+
+fn output_response(output: Output) -> HttpResponse {
+    match output {
+        Output::Json(value) => HttpResponse::Ok().json(value),
+        Output::Bytes { content_type, data } => {
+            HttpResponse::Ok().content_type(content_type).body(data)
+        }
+    }
+}
+
 #[get("/api/file/parse")]
-pub async fn parse_file_as_ies(
+pub async fn parse_file(
     query: web::Query<FileDownloadQuery>,
     folder_tree: web::Data<Arc<Folder>>,
+    mesh_map: web::Data<HashMap<String, String>>,
+    textures: web::Data<TextureIndex>,
+    registry: web::Data<FormatRegistry>,
 ) -> impl Responder {
     let results = folder_tree.search_file_by_full_path(&query.path);
 
     let version = query.version.unwrap_or(0); // default to 0
-    if let Some((_full_path, file_table)) = results.get(version) {
-        if let Ok(data) = file_table.extract_data() {
-            if let Ok(ies) = IESRoot::from_bytes(&data) {
-                return HttpResponse::Ok().json(ies);
-            }
-        }
-    }
+    let Some((full_path, file_table)) = results.get(version) else {
+        return HttpResponse::NotFound().body("File/version not found");
+    };
+    let Ok(data) = file_table.extract_data() else {
+        return HttpResponse::InternalServerError().body("Failed to extract file data");
+    };
 
-    HttpResponse::InternalServerError().body("Failed to parse as IES")
+    let format = sniff::detect(file_table.name(), &data, data.len() as u64);
+    let Some(handler) = registry.find(format, file_table.name()) else {
+        return HttpResponse::UnsupportedMediaType()
+            .body(format!("No parser for {} files", format.name()));
+    };
+    let ctx = HandlerContext {
+        tree: &folder_tree,
+        full_path,
+        mesh_map: &mesh_map,
+        textures: &textures,
+        animations: &[],
+        mip: None,
+        face: None,
+    };
+
+    match handler.parse(&data, &ctx) {
+        Ok(json) => HttpResponse::Ok().json(json),
+        Err(e) => handler_error_response(e),
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct FilePreviewQuery {
     pub path: String,
     pub version: Option<usize>,
+    /// DDS mip level to show instead of the top level
+    pub mip: Option<usize>,
+    /// Cubemap face to show instead of all faces side by side
+    pub face: Option<usize>,
 }
 
 #[get("/api/file/preview")]
 pub async fn preview_file(
+    req: HttpRequest,
     query: web::Query<FilePreviewQuery>,
     folder_tree: web::Data<Arc<Folder>>,
     mesh_map: web::Data<HashMap<String, String>>,
+    textures: web::Data<TextureIndex>,
+    registry: web::Data<FormatRegistry>,
+    preview_cache: web::Data<PreviewCache>,
 ) -> impl Responder {
     // Find file by full path
     let results = folder_tree.search_file_by_full_path(&query.path);
     let version = query.version.unwrap_or(0);
 
-    let (_full_path, file_table) = match results.get(version) {
+    let (full_path, file_table) = match results.get(version) {
         Some(entry) => entry,
         None => return HttpResponse::NotFound().body("File/version not found"),
     };
 
+    let variant = match (query.mip, query.face) {
+        (None, None) => "preview".to_string(),
+        (mip, face) => format!("preview-mip{}-face{}", mip.unwrap_or(0), face.unwrap_or(0)),
+    };
+    let etag = http_cache::entry_etag(file_table, &variant);
+    if http_cache::not_modified(&req, &etag) {
+        return http_cache::not_modified_response(&etag);
+    }
+    if let Some(cached) = preview_cache.get(&etag) {
+        return http_cache::ranged_response(
+            &req,
+            HttpResponse::Ok(),
+            cached.content_type,
+            &etag,
+            cached.body,
+        );
+    }
+
     // Extract raw file bytes
     let data = match file_table.extract_data() {
         Ok(d) => d,
         Err(_) => return HttpResponse::InternalServerError().body("Failed to extract file data"),
     };
 
-    // Get extension
-    let ext = _full_path.split('.').last().unwrap_or("").to_lowercase();
-
-    // Group image formats
-    let image_extensions = ["tga", "png", "jpg", "jpeg", "bmp", "dds"];
-
-    let is_dds = data.len() > 4 && &data[0..4] == b"DDS ";
+    let format = sniff::detect(file_table.name(), &data, data.len() as u64);
+    let Some(handler) = registry.find(format, file_table.name()) else {
+        // Fallback binary, not worth caching
+        return http_cache::ranged_response(
+            &req,
+            HttpResponse::Ok(),
+            "application/octet-stream",
+            &etag,
+            Bytes::from(data),
+        );
+    };
+    let ctx = HandlerContext {
+        tree: &folder_tree,
+        full_path,
+        mesh_map: &mesh_map,
+        textures: &textures,
+        animations: &[],
+        mip: query.mip,
+        face: query.face,
+    };
 
-    if _full_path.contains("nocolor") {
-        return match crate::stb::load_tga_from_memory(&data) {
-            Some(img) => match crate::stb::encode_png_to_memory(&img) {
-                Some(png_bytes) => HttpResponse::Ok().content_type("image/png").body(png_bytes),
-                None => HttpResponse::InternalServerError().body("Failed to encode PNG from TGA"),
+    let output = match handler.preview(data, &ctx) {
+        Ok(output) => output,
+        Err(e) => return handler_error_response(e),
+    };
+    // Files served in their own format (PNG, MP3, fonts...) are as cheap to
+    // extract again as to cache, only keep what a handler converted
+    let converted = !matches!(
+        &output,
+        Output::Bytes { content_type, .. } if *content_type == format.mime_type()
+    );
+    let preview = match output {
+        Output::Json(value) => match serde_json::to_vec(&value) {
+            Ok(json) => CachedPreview {
+                content_type: "application/json",
+                body: Bytes::from(json),
             },
-            None => HttpResponse::InternalServerError().body("Failed to decode TGA image"),
-        };
+            Err(_) => return HttpResponse::InternalServerError().body("Failed to encode JSON"),
+        },
+        Output::Bytes { content_type, data } => CachedPreview {
+            content_type,
+            body: Bytes::from(data),
+        },
+    };
+
+    if converted {
+        preview_cache.insert(etag.clone(), preview.clone());
     }
+    http_cache::ranged_response(
+        &req,
+        HttpResponse::Ok(),
+        preview.content_type,
+        &etag,
+        preview.body,
+    )
+}
 
-    if image_extensions.contains(&ext.as_str()) {
-        // TGA conversion
-        if !is_dds && ext == "tga" || _full_path.contains("nocolor") {
-            return match crate::stb::load_tga_from_memory(&data) {
-                Some(img) => match crate::stb::encode_png_to_memory(&img) {
-                    Some(png_bytes) => HttpResponse::Ok().content_type("image/png").body(png_bytes),
-                    None => {
-                        HttpResponse::InternalServerError().body("Failed to encode PNG from TGA")
-                    }
-                },
-                None => HttpResponse::InternalServerError().body("Failed to decode TGA image"),
-            };
-        }
+#[derive(Debug, Deserialize)]
+pub struct FileExportQuery {
+    pub path: String,
+    pub version: Option<usize>,
+    /// Export target such as "csv" or "png", see `/api/formats`
+    pub format: String,
+    /// Comma separated XSM paths to play on an exported model
+    #[serde(default)]
+    pub animations: Option<String>,
+    /// DDS mip level to export instead of the top level
+    pub mip: Option<usize>,
+    /// Cubemap face to export instead of all faces side by side
+    pub face: Option<usize>,
+}
 
-        // Detect MIME type via magic bytes for other images
-        let mime_type = if data.starts_with(b"\x89PNG\r\n\x1a\n") {
-            "image/png"
-        } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
-            "image/jpeg"
-        } else if data.starts_with(b"BM") {
-            "image/bmp"
-        } else if data.len() > 4 && &data[0..4] == b"DDS " {
-            "image/dds"
-        } else {
-            "application/octet-stream"
-        };
+#[get("/api/file/export")]
+pub async fn export_file(
+    query: web::Query<FileExportQuery>,
+    folder_tree: web::Data<Arc<Folder>>,
+    mesh_map: web::Data<HashMap<String, String>>,
+    textures: web::Data<TextureIndex>,
+    registry: web::Data<FormatRegistry>,
+) -> impl Responder {
+    let results = folder_tree.search_file_by_full_path(&query.path);
+    let Some((full_path, file_table)) = results.get(query.version.unwrap_or(0)) else {
+        return HttpResponse::NotFound().body("File/version not found");
+    };
+    let Ok(data) = file_table.extract_data() else {
+        return HttpResponse::InternalServerError().body("Failed to extract file data");
+    };
 
-        return HttpResponse::Ok().content_type(mime_type).body(data);
-    }
+    let format = sniff::detect(file_table.name(), &data, data.len() as u64);
+    let Some(handler) = registry.find(format, file_table.name()) else {
+        return HttpResponse::UnsupportedMediaType()
+            .body(format!("No exporter for {} files", format.name()));
+    };
+    let animations: Vec<String> = query
+        .animations
+        .iter()
+        .flat_map(|paths| paths.split(','))
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(String::from)
+        .collect();
+    let ctx = HandlerContext {
+        tree: &folder_tree,
+        full_path,
+        mesh_map: &mesh_map,
+        textures: &textures,
+        animations: &animations,
+        mip: query.mip,
+        face: query.face,
+    };
 
-    // MP3 audio
-    if ext == "mp3" {
-        return HttpResponse::Ok().content_type("audio/mpeg").body(data);
+    let target = query.format.to_lowercase();
+    let stem = file_table
+        .name()
+        .rsplit_once('.')
+        .map_or(file_table.name(), |(stem, _)| stem);
+    let disposition = (
+        "Content-Disposition",
+        format!("attachment; filename=\"{}.{}\"", stem, target),
+    );
+    match handler.export(data, &target, &ctx) {
+        Ok(Output::Json(value)) => HttpResponse::Ok().insert_header(disposition).json(value),
+        Ok(Output::Bytes { content_type, data }) => HttpResponse::Ok()
+            .insert_header(disposition)
+            .content_type(content_type)
+            .body(data),
+        Err(e) => handler_error_response(e),
     }
+}
 
-    // Fonts
-    if ext == "ttf" {
-        return HttpResponse::Ok().content_type("font/ttf").body(data);
-    }
+#[derive(Debug, Serialize)]
+pub struct FormatHandlerInfo {
+    pub name: &'static str,
+    pub export_targets: &'static [&'static str],
+}
 
-    // IES format
-    if ext == "ies" {
-        return match IESRoot::from_bytes(&data) {
-            Ok(ies) => HttpResponse::Ok().json(ies),
-            Err(_) => HttpResponse::InternalServerError().body("Failed to parse IES file"),
-        };
-    }
+#[get("/api/formats")]
+pub async fn format_list(registry: web::Data<FormatRegistry>) -> impl Responder {
+    let handlers: Vec<FormatHandlerInfo> = registry
+        .handlers()
+        .map(|h| FormatHandlerInfo {
+            name: h.name(),
+            export_targets: h.export_targets(),
+        })
+        .collect();
+    HttpResponse::Ok().json(handlers)
+}
 
-    // XPM format
-    if ext == "xpm" {
-        return match XPMRoot::from_bytes(&data) {
-            Ok(xpm) => HttpResponse::Ok().json(xpm),
-            Err(_) => HttpResponse::InternalServerError().body("Failed to parse XPM file"),
-        };
+/// -------------------------
+/// ZIP Downloads
+/// -------------------------
+#[derive(Debug, Deserialize)]
+pub struct FolderDownloadQuery {
+    pub path: String,
+    /// Comma separated export targets, e.g. "png,csv,glb"
+    #[serde(default)]
+    pub convert: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ZipFileRequest {
+    pub path: String,
+    /// Defaults to version 0, like the other file endpoints
+    #[serde(default)]
+    pub version: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ZipDownloadRequest {
+    pub files: Vec<ZipFileRequest>,
+    #[serde(default)]
+    pub convert: Vec<String>,
+}
+
+/// Extract one file for the archive, converting it to the first requested
+/// export target its handler supports. Failed conversions keep the raw file.
+fn zip_entry(
+    name: &str,
+    file: FileRef,
+    convert: &[String],
+    tree: &Folder,
+    mesh_map: &HashMap<String, String>,
+    textures: &TextureIndex,
+    registry: &FormatRegistry,
+) -> std::io::Result<(String, Vec<u8>)> {
+    let data = file.extract_data()?;
+    if convert.is_empty() {
+        return Ok((name.to_string(), data));
     }
 
-    // XSM format
-    if ext == "xsm" {
-        return match XSMRoot::from_bytes(&data) {
-            Ok(xsm) => HttpResponse::Ok().json(xsm),
-            Err(_) => HttpResponse::InternalServerError().body("Failed to parse XSM file"),
+    let format = sniff::detect(file.name(), &data, data.len() as u64);
+    let Some(handler) = registry.find(format, file.name()) else {
+        return Ok((name.to_string(), data));
+    };
+    let Some(target) = convert
+        .iter()
+        .find(|target| handler.export_targets().contains(&target.as_str()))
+    else {
+        return Ok((name.to_string(), data));
+    };
+
+    let ctx = HandlerContext {
+        tree,
+        full_path: name,
+        mesh_map,
+        textures,
+        animations: &[],
+        mip: None,
+        face: None,
+    };
+    let converted = match handler.export(data.clone(), target, &ctx) {
+        Ok(Output::Bytes { data, .. }) => data,
+        Ok(Output::Json(value)) => serde_json::to_vec(&value).unwrap_or_default(),
+        Err(e) => {
+            eprintln!("Failed to convert {} to {}: {}", name, target, e);
+            return Ok((name.to_string(), data));
+        }
+    };
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    Ok((format!("{}.{}", stem, target), converted))
+}
+
+/// Stream a ZIP of `files` (entry name, file id), packed on its own thread so
+/// extraction and compression never block the server
+fn zip_response(
+    archive_name: &str,
+    files: Vec<(String, u32)>,
+    convert: Vec<String>,
+    folder_tree: Arc<Folder>,
+    mesh_map: web::Data<HashMap<String, String>>,
+    textures: web::Data<TextureIndex>,
+    registry: web::Data<FormatRegistry>,
+) -> HttpResponse {
+    let (writer, stream) = ChannelWriter::channel();
+
+    std::thread::spawn(move || {
+        let mut writer = writer;
+        let result = (|| -> std::io::Result<()> {
+            let mut zip = ZipWriter::new(&mut writer);
+            let mut used_names = HashSet::new();
+            for (name, id) in files {
+                let Some(file) = folder_tree.file(id) else {
+                    continue;
+                };
+                let (mut entry_name, data) = match zip_entry(
+                    &name,
+                    file,
+                    &convert,
+                    &folder_tree,
+                    &mesh_map,
+                    &textures,
+                    &registry,
+                ) {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        eprintln!("Failed to extract {} for ZIP download: {}", name, e);
+                        continue;
+                    }
+                };
+                // Several versions of one path, keep them apart by version index
+                if !used_names.insert(entry_name.to_lowercase()) {
+                    entry_name = match entry_name.rsplit_once('.') {
+                        Some((stem, ext)) => format!("{}.v{}.{}", stem, file.version(), ext),
+                        None => format!("{}.v{}", entry_name, file.version()),
+                    };
+                    used_names.insert(entry_name.to_lowercase());
+                }
+                zip.add_file(&entry_name, &data)?;
+            }
+            zip.finish()?;
+            Ok(())
+        })();
+        match result {
+            Ok(()) => {}
+            // The client went away, nobody is left to tell
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+            // Fail the response rather than end it like a complete archive
+            Err(e) => {
+                eprintln!("Failed to write ZIP download: {}", e);
+                writer.abort(e);
+            }
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("application/zip")
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{}.zip\"", archive_name),
+        ))
+        .streaming(stream)
+}
+
+fn convert_targets<'a>(targets: impl Iterator<Item = &'a str>) -> Vec<String> {
+    targets
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+#[get("/api/folder/download")]
+pub async fn download_folder(
+    query: web::Query<FolderDownloadQuery>,
+    folder_tree: web::Data<Arc<Folder>>,
+    mesh_map: web::Data<HashMap<String, String>>,
+    textures: web::Data<TextureIndex>,
+    registry: web::Data<FormatRegistry>,
+) -> impl Responder {
+    let Some(folder) = folder_tree.folder(&query.path) else {
+        return HttpResponse::NotFound().body("Folder not found");
+    };
+    let files: Vec<(String, u32)> = folder
+        .latest_files()
+        .into_iter()
+        .map(|(path, file)| (path, file.id()))
+        .collect();
+    let archive_name = match folder.name() {
+        "" => "root".to_string(),
+        name => name.to_string(),
+    };
+    let convert = convert_targets(query.convert.as_deref().unwrap_or("").split(','));
+
+    zip_response(
+        &archive_name,
+        files,
+        convert,
+        folder_tree.get_ref().clone(),
+        mesh_map,
+        textures,
+        registry,
+    )
+}
+
+#[post("/api/files/download")]
+pub async fn download_files(
+    request: web::Json<ZipDownloadRequest>,
+    folder_tree: web::Data<Arc<Folder>>,
+    mesh_map: web::Data<HashMap<String, String>>,
+    textures: web::Data<TextureIndex>,
+    registry: web::Data<FormatRegistry>,
+) -> impl Responder {
+    let mut files = Vec::with_capacity(request.files.len());
+    for requested in &request.files {
+        let results = folder_tree.search_file_by_full_path(&requested.path);
+        let version = requested.version.unwrap_or(0);
+        let Some((full_path, file)) = results.get(version) else {
+            return HttpResponse::NotFound()
+                .body(format!("File/version not found: {}", requested.path));
         };
+        files.push((full_path.clone(), file.id()));
     }
+    let convert = convert_targets(request.convert.iter().map(String::as_str));
+
+    zip_response(
+        "files",
+        files,
+        convert,
+        folder_tree.get_ref().clone(),
+        mesh_map,
+        textures,
+        registry,
+    )
+}
 
-    // 3dworld format
-    if ext == "3dworld" {
-        return match World::from_bytes(&data) {
-            Ok(dworld) => {
-                let mut scenes_data = Vec::new();
+/// -------------------------
+/// Recipe / Crafting Graph
+/// -------------------------
+#[derive(Debug, Deserialize)]
+pub struct RecipeItemQuery {
+    pub item: String,
+    #[serde(default)]
+    pub count: Option<u64>, // optional, default to 1
+}
 
-                let ipf_name = dworld.model_dirs[0].ipf_name.clone();
+#[derive(Debug, Serialize)]
+pub struct IngredientView {
+    pub item: String,
+    pub name: String,
+    pub count: u64,
+}
 
-                let base_path = dworld.model_dirs[0].path.clone();
+#[derive(Debug, Serialize)]
+pub struct RecipeView {
+    pub class_name: String,
+    pub target_item: String,
+    pub target_name: String,
+    pub target_count: u32,
+    pub ingredients: Vec<IngredientView>,
+}
 
-                for model in &dworld.models {
-                    fn normalize_path(p: &str) -> String {
-                        p.replace('\\', "/").trim_matches('/').to_string()
-                    }
+impl RecipeView {
+    fn new(recipe: &Recipe, items: &ClassIndex) -> Self {
+        RecipeView {
+            class_name: recipe.class_name.clone(),
+            target_item: recipe.target_item.clone(),
+            target_name: items.display_name(&recipe.target_item),
+            target_count: recipe.target_count,
+            ingredients: recipe
+                .ingredients
+                .iter()
+                .map(|ing| IngredientView {
+                    item: ing.item.clone(),
+                    name: items.display_name(&ing.item),
+                    count: ing.count as u64,
+                })
+                .collect(),
+        }
+    }
+}
 
-                    let ipf_name = normalize_path(&dworld.model_dirs[0].ipf_name);
-                    let base_path = normalize_path(&dworld.model_dirs[0].path);
-                    let file_name = normalize_path(&model.file);
+#[derive(Debug, Serialize)]
+pub struct RecipeItemResponse {
+    pub item: String,
+    pub name: String,
+    pub recipes: Vec<RecipeView>,
+    pub used_in: Vec<RecipeView>,
+}
 
-                    let model_path = format!("{}/{}/{}", ipf_name, base_path, file_name);
-                    let results_data = folder_tree.search_file_by_full_path(&model_path);
+#[get("/api/recipe/item")]
+pub async fn recipe_item(
+    query: web::Query<RecipeItemQuery>,
+    recipes: web::Data<RecipeGraph>,
+    class_indexes: web::Data<ClassIndexes>,
+) -> impl Responder {
+    let items = &class_indexes.items;
 
-                    let (_full_path, file_table) = match results_data.last() {
-                        Some(entry) => entry,
-                        None => return HttpResponse::NotFound().body("File/version not found"),
-                    };
+    HttpResponse::Ok().json(RecipeItemResponse {
+        item: query.item.clone(),
+        name: items.display_name(&query.item),
+        recipes: recipes
+            .recipes_for(&query.item)
+            .into_iter()
+            .map(|r| RecipeView::new(r, items))
+            .collect(),
+        used_in: recipes
+            .used_in(&query.item)
+            .into_iter()
+            .map(|r| RecipeView::new(r, items))
+            .collect(),
+    })
+}
 
-                    // Extract file
-                    let data = match file_table.extract_data() {
-                        Ok(d) => d,
-                        Err(_) => {
-                            return HttpResponse::InternalServerError()
-                                .body("Failed to extract file data");
-                        }
-                    };
+#[get("/api/recipe/tree")]
+pub async fn recipe_tree(
+    query: web::Query<RecipeItemQuery>,
+    recipes: web::Data<RecipeGraph>,
+    class_indexes: web::Data<ClassIndexes>,
+) -> impl Responder {
+    let count = query.count.unwrap_or(1).max(1);
+    HttpResponse::Ok().json(recipes.expand(&query.item, count, &class_indexes.items))
+}
 
-                    // Parse XAC
-                    let xac_root = match crate::xac::XACRoot::from_bytes(&data) {
-                        Ok(root) => root,
-                        Err(_) => {
-                            return HttpResponse::InternalServerError()
-                                .body("Failed to parse XAC file");
-                        }
-                    };
+#[get("/api/recipe/raw")]
+pub async fn recipe_raw_materials(
+    query: web::Query<RecipeItemQuery>,
+    recipes: web::Data<RecipeGraph>,
+    class_indexes: web::Data<ClassIndexes>,
+) -> impl Responder {
+    let items = &class_indexes.items;
+    let count = query.count.unwrap_or(1).max(1);
+
+    let materials: Vec<IngredientView> = recipes
+        .raw_materials(&query.item, count, items)
+        .into_iter()
+        .map(|(item, count)| IngredientView {
+            name: items.display_name(&item),
+            item,
+            count,
+        })
+        .collect();
 
-                    // Build texture root based on TexDir
-                    let texture_path = String::new();
+    HttpResponse::Ok().json(materials)
+}
 
-                    // Build scene
-                    let mut scene = crate::mesh::Scene::from_xac_root(&xac_root, texture_path);
-                    // Convert optional pos/rot/scale to Vector3/Vector4
-                    if let Some(pos_str) = &model.pos {
-                        scene.position = Some(dx_to_gl_position(to_vec3(pos_str)));
-                    }
-                    if let Some(rot_str) = &model.rot {
-                        scene.rotation = Some(dx_to_gl_quat(to_quat(rot_str)));
-                    }
-                    if let Some(scale_str) = &model.scale {
-                        scene.scale = Some(dx_to_gl_scale(to_vec3(scale_str)));
-                    }
-                    scenes_data.push(scene);
-                }
+#[derive(Debug, Deserialize)]
+pub struct RecipeSearchQuery {
+    pub name: String,
+}
 
-                HttpResponse::Ok().json(scenes_data)
-            }
-            Err(_) => HttpResponse::InternalServerError().body("Failed to parse 3dworld file"),
-        };
-    }
+/// Search craftable items by class name or display name
+#[get("/api/recipe/search")]
+pub async fn recipe_search(
+    query: web::Query<RecipeSearchQuery>,
+    recipes: web::Data<RecipeGraph>,
+    class_indexes: web::Data<ClassIndexes>,
+) -> impl Responder {
+    let items = &class_indexes.items;
+    let needle = query.name.to_lowercase();
 
-    // XAC format
-    if ext == "xac" {
-        match crate::xac::XACRoot::from_bytes(&data) {
-            Ok(xac_root) => {
-                // Lowercase the lookup key
-                let full_path_lc = _full_path.to_lowercase();
-
-                // Try to get texture path
-                let texture_path = match mesh_map.get(&full_path_lc) {
-                    Some(path) => path.clone(),
-                    None => String::new(),
-                };
+    let mut seen = HashSet::new();
+    let results: Vec<RecipeView> = recipes
+        .recipes()
+        .iter()
+        .filter(|r| seen.insert(r.target_item.to_lowercase()))
+        .filter(|r| {
+            r.target_item.to_lowercase().contains(&needle)
+                || items
+                    .display_name(&r.target_item)
+                    .to_lowercase()
+                    .contains(&needle)
+        })
+        .take(200)
+        .map(|r| RecipeView::new(r, items))
+        .collect();
 
-                let scene = crate::mesh::Scene::from_xac_root(&xac_root, texture_path);
-                return HttpResponse::Ok().json(scene);
-            }
-            Err(_) => return HttpResponse::InternalServerError().body("Failed to parse XAC file"),
+    HttpResponse::Ok().json(results)
+}
+
+/// -------------------------
+/// Collections & Card Album
+/// -------------------------
+#[derive(Debug, Deserialize)]
+pub struct CollectionListQuery {
+    /// Only return entries requiring this item (collections) or depicting this monster (cards)
+    #[serde(default)]
+    pub class_name: Option<String>,
+    #[serde(default)]
+    pub offset: Option<usize>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PagedResponse<T> {
+    pub total: usize,
+    pub offset: usize,
+    pub items: Vec<T>,
+}
+
+impl<T: Clone> PagedResponse<T> {
+    fn from_slice(items: &[&T], offset: Option<usize>, limit: Option<usize>) -> Self {
+        let offset = offset.unwrap_or(0);
+        let limit = limit.unwrap_or(usize::MAX);
+        PagedResponse {
+            total: items.len(),
+            offset,
+            items: items
+                .iter()
+                .skip(offset)
+                .take(limit)
+                .map(|&item| item.clone())
+                .collect(),
         }
     }
+}
 
-    // Check for .tok extension
-    if ext == "tok" {
-        let mut cursor = std::io::Cursor::new(&data);
+#[get("/api/collection/list")]
+pub async fn collection_list(
+    query: web::Query<CollectionListQuery>,
+    collections: web::Data<CollectionData>,
+) -> impl Responder {
+    let filtered: Vec<_> = collections
+        .collections
+        .iter()
+        .filter(|c| match &query.class_name {
+            Some(item) => c
+                .required_items
+                .iter()
+                .any(|i| i.class_name.eq_ignore_ascii_case(item)),
+            None => true,
+        })
+        .collect();
 
-        // Parse .tok into TokNode
-        let parser = match crate::tok::TokParser::new(&mut cursor) {
-            Ok(p) => p,
-            Err(_) => {
-                return HttpResponse::InternalServerError().body("Failed to create TOK parser");
-            }
-        };
+    HttpResponse::Ok().json(PagedResponse::from_slice(
+        &filtered,
+        query.offset,
+        query.limit,
+    ))
+}
 
-        let root = match parser.parse() {
-            Ok(r) => r,
-            Err(_) => return HttpResponse::InternalServerError().body("Failed to parse TOK file"),
-        };
+#[get("/api/collection/{class_name}")]
+pub async fn collection_detail(
+    path: web::Path<String>,
+    collections: web::Data<CollectionData>,
+) -> impl Responder {
+    match collections.collection(&path) {
+        Some(collection) => HttpResponse::Ok().json(collection),
+        None => HttpResponse::NotFound().body("Collection not found"),
+    }
+}
 
-        // Export SVG to memory (instead of file)
-        let mut svg_string = Vec::new();
-        {
-            use std::io::Write;
+#[get("/api/card/list")]
+pub async fn card_list(
+    query: web::Query<CollectionListQuery>,
+    collections: web::Data<CollectionData>,
+) -> impl Responder {
+    let filtered: Vec<_> = collections
+        .cards
+        .iter()
+        .filter(|c| match (&query.class_name, &c.monster) {
+            (Some(monster), Some(linked)) => linked.class_name.eq_ignore_ascii_case(monster),
+            (Some(_), None) => false,
+            (None, _) => true,
+        })
+        .collect();
 
-            let mut cursor = std::io::Cursor::new(&mut svg_string);
-            if let Err(_) = crate::tok::export_to_svg(&root, &mut cursor, 512.0, 512.0) {
-                return HttpResponse::InternalServerError().body("Failed to export SVG");
-            }
-        }
+    HttpResponse::Ok().json(PagedResponse::from_slice(
+        &filtered,
+        query.offset,
+        query.limit,
+    ))
+}
 
-        return HttpResponse::Ok()
-            .content_type("image/svg+xml")
-            .body(svg_string);
+#[get("/api/card/{class_name}")]
+pub async fn card_detail(
+    path: web::Path<String>,
+    collections: web::Data<CollectionData>,
+) -> impl Responder {
+    match collections.card(&path) {
+        Some(card) => HttpResponse::Ok().json(card),
+        None => HttpResponse::NotFound().body("Card not found"),
     }
+}
 
-    // Text-like formats
-    let text_extensions = [
-        "xml", "skn", "3dprop", "3dworld", "3drender", "3deffect", "x", "fx", "fxh", "sani",
-        "effect", "json", "atlas", "sprbin", "xsd", "lua", "lst", "export",
-    ];
-
-    if text_extensions.contains(&ext.as_str()) {
-        let text = String::from_utf8_lossy(&data);
-        return HttpResponse::Ok()
-            .content_type("text/plain")
-            .body(text.to_string());
+/// -------------------------
+/// Maps, Monster Spawns & Dungeon Rewards
+/// -------------------------
+#[derive(Debug, Clone, Serialize)]
+pub struct MapSummary {
+    pub map: String,
+    pub name: String,
+    pub spawn_groups: usize,
+    pub anchors: usize,
+}
+
+#[get("/api/map/list")]
+pub async fn map_list(
+    query: web::Query<CollectionListQuery>,
+    spawns: web::Data<SpawnData>,
+) -> impl Responder {
+    let summaries: Vec<MapSummary> = spawns
+        .maps
+        .values()
+        .filter(|m| match &query.class_name {
+            Some(monster) => m
+                .spawns
+                .iter()
+                .any(|g| g.monster.class_name.eq_ignore_ascii_case(monster)),
+            None => true,
+        })
+        .map(|m| MapSummary {
+            map: m.map.clone(),
+            name: m.name.clone(),
+            spawn_groups: m.spawns.len(),
+            anchors: m.spawns.iter().map(|g| g.anchors.len()).sum(),
+        })
+        .collect();
+
+    let refs: Vec<&MapSummary> = summaries.iter().collect();
+    HttpResponse::Ok().json(PagedResponse::from_slice(&refs, query.offset, query.limit))
+}
+
+#[get("/api/map/{name}/spawns")]
+pub async fn map_spawns(path: web::Path<String>, spawns: web::Data<SpawnData>) -> impl Responder {
+    match spawns.map(&path) {
+        Some(map) => HttpResponse::Ok().json(map),
+        None => HttpResponse::NotFound().body("Map not found"),
     }
+}
 
-    // Fallback binary
-    HttpResponse::Ok()
-        .content_type("application/octet-stream")
-        .body(data)
+#[derive(Debug, Serialize)]
+pub struct MonsterSpawnView<'a> {
+    pub map: &'a str,
+    pub map_name: &'a str,
+    #[serde(flatten)]
+    pub group: &'a SpawnGroup,
+}
+
+#[get("/api/monster/{class_name}/spawns")]
+pub async fn monster_spawns(
+    path: web::Path<String>,
+    spawns: web::Data<SpawnData>,
+) -> impl Responder {
+    let results: Vec<MonsterSpawnView> = spawns
+        .monster_spawns(&path)
+        .into_iter()
+        .map(|(map, group)| MonsterSpawnView {
+            map: &map.map,
+            map_name: &map.name,
+            group,
+        })
+        .collect();
+
+    HttpResponse::Ok().json(results)
+}
+
+#[get("/api/dungeon/rewards")]
+pub async fn dungeon_rewards(
+    query: web::Query<CollectionListQuery>,
+    spawns: web::Data<SpawnData>,
+) -> impl Responder {
+    let filtered: Vec<_> = spawns
+        .dungeon_rewards
+        .iter()
+        .filter(|r| match &query.class_name {
+            Some(name) => {
+                r.class_name.eq_ignore_ascii_case(name)
+                    || r.items
+                        .iter()
+                        .any(|i| i.class_name.eq_ignore_ascii_case(name))
+            }
+            None => true,
+        })
+        .collect();
+
+    HttpResponse::Ok().json(PagedResponse::from_slice(
+        &filtered,
+        query.offset,
+        query.limit,
+    ))
 }
 
 /// -------------------------
@@ -542,10 +1632,33 @@ pub async fn preview_file(
 /// -------------------------
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(api_info);
+    cfg.service(tree_stats);
     cfg.service(folder_shallow);
     cfg.service(search_file_recursive);
     cfg.service(search_file_fullpath);
+    cfg.service(archive_list);
+    cfg.service(archive_files);
+    cfg.service(patch_timeline);
+    cfg.service(patch_detail);
+    cfg.service(file_history);
+    cfg.service(content_search);
     cfg.service(download_file);
-    cfg.service(parse_file_as_ies);
+    cfg.service(parse_file);
     cfg.service(preview_file);
+    cfg.service(export_file);
+    cfg.service(format_list);
+    cfg.service(download_folder);
+    cfg.service(download_files);
+    cfg.service(recipe_item);
+    cfg.service(recipe_tree);
+    cfg.service(recipe_raw_materials);
+    cfg.service(recipe_search);
+    cfg.service(collection_list);
+    cfg.service(collection_detail);
+    cfg.service(card_list);
+    cfg.service(card_detail);
+    cfg.service(map_list);
+    cfg.service(map_spawns);
+    cfg.service(monster_spawns);
+    cfg.service(dungeon_rewards);
 }
@@ -1,209 +1,1126 @@
-use std::collections::BTreeMap;
-use std::path::PathBuf;
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
 
-use serde::{Deserialize, Serialize};
+use percent_encoding::{AsciiSet, CONTROLS, utf8_percent_encode};
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
 
-use crate::ipf::IPFFileTable;
+use crate::ipf::{self, ExtensionStats, IPFFileTable, IPFHeader, IPFRoot};
+use crate::sniff::{self, FileFormat};
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Folder {
-    pub files: Vec<IPFFileTable>,
-    pub subfolders: BTreeMap<String, Folder>,
+/// How `Folder::search_files` matches a file path
+#[derive(Debug, Clone)]
+pub enum PathMatcher {
+    /// Whitespace-separated words that must all appear in the file name
+    Words(Vec<String>),
+    /// Full path starts with the prefix, e.g. "ies_drop/anchor_"
+    Prefix(String),
+    /// Glob such as "ies_drop/zonedropitemlist_*.ies". `*` and `?` stay inside one
+    /// folder, `**` crosses folders. Patterns without '/' match the file name only.
+    Glob(String),
+    /// Regular expression over the full path
+    Regex(Regex),
 }
 
-impl Folder {
-    pub fn new() -> Self {
+impl PathMatcher {
+    pub fn words(text: &str) -> Self {
+        PathMatcher::Words(text.split_whitespace().map(|w| w.to_lowercase()).collect())
+    }
+
+    pub fn prefix(prefix: &str) -> Self {
+        PathMatcher::Prefix(prefix.trim_start_matches('/').to_lowercase())
+    }
+
+    pub fn glob(pattern: &str) -> Self {
+        PathMatcher::Glob(pattern.trim_start_matches('/').to_lowercase())
+    }
+
+    /// Case-insensitive regex matcher
+    pub fn regex(pattern: &str) -> Result<Self, regex::Error> {
+        let regex = RegexBuilder::new(pattern).case_insensitive(true).build()?;
+        Ok(PathMatcher::Regex(regex))
+    }
+
+    pub fn matches(&self, full_path: &str, file_name: &str) -> bool {
+        match self {
+            PathMatcher::Words(words) => {
+                let name = file_name.to_lowercase();
+                words.iter().all(|word| name.contains(word))
+            }
+            PathMatcher::Prefix(prefix) => full_path.to_lowercase().starts_with(prefix),
+            PathMatcher::Glob(pattern) if pattern.contains('/') => {
+                glob_match(pattern, &full_path.to_lowercase())
+            }
+            PathMatcher::Glob(pattern) => glob_match(pattern, &file_name.to_lowercase()),
+            PathMatcher::Regex(regex) => regex.is_match(full_path),
+        }
+    }
+
+    /// Whether files below `folder_path` can match at all, used to skip whole subtrees
+    fn may_match_below(&self, folder_path: &str) -> bool {
+        let literal = match self {
+            PathMatcher::Prefix(prefix) => prefix.as_str(),
+            PathMatcher::Glob(pattern) if pattern.contains('/') => {
+                let end = pattern.find(['*', '?']).unwrap_or(pattern.len());
+                &pattern[..end]
+            }
+            _ => return true,
+        };
+        if folder_path.is_empty() {
+            return true;
+        }
+
+        let folder = format!("{}/", folder_path.to_lowercase());
+        folder.starts_with(literal) || literal.starts_with(&folder)
+    }
+}
+
+/// Path matcher plus extension and uncompressed size filters
+#[derive(Debug, Clone)]
+pub struct FileFilter {
+    pub matcher: PathMatcher,
+    /// Lowercase extensions without the dot, empty accepts every extension
+    pub extensions: Vec<String>,
+    pub min_size: Option<u32>,
+    pub max_size: Option<u32>,
+}
+
+impl FileFilter {
+    pub fn new(matcher: PathMatcher) -> Self {
         Self {
-            files: Vec::new(),
-            subfolders: BTreeMap::new(),
+            matcher,
+            extensions: Vec::new(),
+            min_size: None,
+            max_size: None,
         }
     }
 
-    /// Recursively insert a file into the tree based on path parts
-    pub fn insert(&mut self, path: &str, file: IPFFileTable) {
-        let mut parts = path.split('/').peekable();
+    /// Parse a comma separated extension list such as "xac,.xsm"
+    pub fn with_extensions(mut self, extensions: &str) -> Self {
+        self.extensions = extensions
+            .split(',')
+            .map(|e| e.trim().trim_start_matches('.').to_lowercase())
+            .filter(|e| !e.is_empty())
+            .collect();
+        self
+    }
 
-        if let Some(part) = parts.next() {
-            if parts.peek().is_none() {
-                // Leaf node: insert file here
-                self.files.push(file);
-            } else {
-                // Intermediate folder
-                let folder = self
-                    .subfolders
-                    .entry(part.to_string())
-                    .or_insert_with(Folder::new);
-                let rest = parts.collect::<Vec<_>>().join("/");
-                folder.insert(&rest, file);
-            }
+    pub fn with_size(mut self, min_size: Option<u32>, max_size: Option<u32>) -> Self {
+        self.min_size = min_size;
+        self.max_size = max_size;
+        self
+    }
+
+    fn accepts(&self, full_path: &str, file: &FileEntry) -> bool {
+        if !self.extensions.is_empty()
+            && !self.extensions.contains(&ipf::file_extension(&file.name))
+        {
+            return false;
+        }
+
+        let size = file.file_size_uncompressed;
+        if self.min_size.is_some_and(|min| size < min)
+            || self.max_size.is_some_and(|max| size > max)
+        {
+            return false;
         }
+
+        self.matcher.matches(full_path, &file.name)
     }
+}
 
-    /// Optional: print tree for debugging
-    pub fn print(&self, prefix: &str) {
-        for (name, folder) in &self.subfolders {
-            println!("{}Folder: {}", prefix, name);
-            folder.print(&format!("{}  ", prefix));
+/// Match `text` against a lowercase glob pattern.
+///
+/// `?` and `*` never match '/', `**` matches anything and `**/` also matches
+/// no folder at all, so "ies/**/*.ies" matches "ies/item.ies".
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    enum Token {
+        Literal(char),
+        AnyChar,
+        Star,
+        DoubleStar,
+        // "**/": zero or more whole folders
+        Folders,
+    }
+
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    tokens.push(Token::Folders);
+                    i += 3;
+                } else {
+                    tokens.push(Token::DoubleStar);
+                    i += 2;
+                }
+                continue;
+            }
+            '*' => tokens.push(Token::Star),
+            '?' => tokens.push(Token::AnyChar),
+            c => tokens.push(Token::Literal(c)),
         }
-        for file in &self.files {
-            println!("{}File: {:?}", prefix, file.file_path.as_ref().unwrap());
+        i += 1;
+    }
+
+    // next[j]: tokens[t + 1..] match text[j..]
+    let text: Vec<char> = text.chars().collect();
+    let n = text.len();
+    let mut next = vec![false; n + 1];
+    next[n] = true;
+
+    for token in tokens.iter().rev() {
+        let mut cur = vec![false; n + 1];
+        // Folders: whether text[j..] starts with "<anything>/" followed by a match of this token
+        let mut folder_match = false;
+        for j in (0..=n).rev() {
+            let c = text.get(j).copied();
+            cur[j] = match token {
+                Token::Literal(l) => c == Some(*l) && next[j + 1],
+                Token::AnyChar => c.is_some_and(|c| c != '/') && next[j + 1],
+                Token::Star => next[j] || (c.is_some_and(|c| c != '/') && cur[j + 1]),
+                Token::DoubleStar => next[j] || (c.is_some() && cur[j + 1]),
+                Token::Folders => {
+                    if c == Some('/') {
+                        folder_match = cur[j + 1];
+                    }
+                    next[j] || (c.is_some() && folder_match)
+                }
+            };
         }
+        next = cur;
     }
 
-    /// Print tree with optional limit for folders and files
-    pub fn print_limited(&self, prefix: &str, folder_limit: usize, file_limit: usize) {
-        // Print subfolders
-        for (i, (name, folder)) in self.subfolders.iter().enumerate() {
-            if i >= folder_limit {
-                println!(
-                    "{}... ({} more folders)",
-                    prefix,
-                    self.subfolders.len() - folder_limit
-                );
-                break;
+    next[0]
+}
+
+/// Whether `name` is the archive file name, ignoring case, with or without
+/// its ".ipf" extension
+pub fn archive_name_matches(archive_name: &str, name: &str) -> bool {
+    archive_name.eq_ignore_ascii_case(name)
+        || archive_name
+            .rsplit_once('.')
+            .is_some_and(|(stem, _)| stem.eq_ignore_ascii_case(name))
+}
+
+/// Lowercase a path and normalize its separators, e.g. "\\IES//Item.ies/" -> "ies/item.ies"
+pub fn normalize_path(path: &str) -> String {
+    path.split(['/', '\\'])
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+        .to_lowercase()
+}
+
+/// Characters escaped in a path used as a URL query value; '/' is kept readable
+const QUERY_VALUE: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'&')
+    .add(b'+')
+    .add(b'<')
+    .add(b'=')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`');
+
+/// Percent-encode a tree path for the `path=` query of an API URL
+pub fn encode_query_path(path: &str) -> String {
+    utf8_percent_encode(path, QUERY_VALUE).to_string()
+}
+
+/// Case-insensitive ordering without allocating lowercase copies
+fn cmp_ignore_case(a: &str, b: &str) -> Ordering {
+    a.chars()
+        .flat_map(char::to_lowercase)
+        .cmp(b.chars().flat_map(char::to_lowercase))
+}
+
+/// Natural, case-insensitive ordering: "file2" sorts before "file10"
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let take_number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                        digits.push(c);
+                    }
+                    digits
+                };
+                let x = take_number(&mut a);
+                let y = take_number(&mut b);
+                let (x_trim, y_trim) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let ord = x_trim
+                    .len()
+                    .cmp(&y_trim.len())
+                    .then_with(|| x_trim.cmp(y_trim))
+                    .then_with(|| x.len().cmp(&y.len()));
+                if ord.is_ne() {
+                    return ord;
+                }
             }
-            println!("{}Folder: {}", prefix, name);
-            folder.print_limited(&format!("{}  ", prefix), folder_limit, file_limit);
-        }
-
-        // Print files
-        for (i, file) in self.files.iter().enumerate() {
-            if i >= file_limit {
-                println!(
-                    "{}... ({} more files)",
-                    prefix,
-                    self.files.len() - file_limit
-                );
-                break;
+            (Some(x), Some(y)) => {
+                let ord = x.to_lowercase().cmp(y.to_lowercase());
+                if ord.is_ne() {
+                    return ord;
+                }
+                a.next();
+                b.next();
             }
-            println!("{}File: {:?}", prefix, file.file_path.as_ref().unwrap());
         }
     }
+}
 
-    /// Shallow search for a folder: returns subfolder names and files directly inside it
-    pub fn search_folder_shallow(&self, folder_path: &str) -> Option<(Vec<String>, Vec<String>)> {
-        // Normalize path: remove trailing slash
-        let path = folder_path.trim_end_matches('/');
-
-        if path.is_empty() {
-            // Return root folder content
-            let subfolders: Vec<String> = self.subfolders.keys().cloned().collect();
-            let files: Vec<String> = self
-                .files
-                .iter()
-                .map(|f| f.directory_name.clone())
-                .collect();
-            return Some((subfolders, files));
+/// (first file id, version count) for every run of equally named files in a folder
+fn version_runs(
+    files: &[FileEntry],
+    files_start: u32,
+    files_len: u32,
+) -> impl Iterator<Item = (u32, u32)> + '_ {
+    let folder_files = &files[files_start as usize..][..files_len as usize];
+    let mut start = 0;
+    std::iter::from_fn(move || {
+        if start >= folder_files.len() {
+            return None;
         }
+        let len = folder_files[start..]
+            .iter()
+            .take_while(|f| cmp_ignore_case(&f.name, &folder_files[start].name).is_eq())
+            .count();
+        let run = (files_start + start as u32, len as u32);
+        start += len;
+        Some(run)
+    })
+}
 
-        // Traverse the path parts
-        let mut current = self;
-        for part in path.split('/') {
-            if let Some(subfolder) = current.subfolders.get(part) {
-                current = subfolder;
-            } else {
-                return None; // folder not found
-            }
+fn hash_path(normalized: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    normalized.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// One IPF archive on disk
+#[derive(Debug, Clone, Serialize)]
+pub struct Archive {
+    pub path: PathBuf,
+    /// Archive file name, e.g. "ui.ipf"
+    pub name: String,
+    /// Set once the parsed archives are registered with `Folder::register_archives`
+    pub header: Option<IPFHeader>,
+}
+
+impl Archive {
+    /// Whether the archive lives in the game's `patch` folder
+    pub fn is_patch(&self) -> bool {
+        self.path
+            .parent()
+            .and_then(Path::file_name)
+            .is_some_and(|dir| dir.eq_ignore_ascii_case("patch"))
+    }
+}
+
+/// Every archive referenced by the tree, addressed by a u16 id
+#[derive(Debug, Default)]
+pub struct ArchiveTable {
+    archives: Vec<Archive>,
+    ids: HashMap<PathBuf, u16>,
+}
+
+impl ArchiveTable {
+    pub fn intern(&mut self, path: &Path) -> u16 {
+        if let Some(&id) = self.ids.get(path) {
+            return id;
         }
 
-        // Collect shallow content of the target folder
-        let subfolders: Vec<String> = current.subfolders.keys().cloned().collect();
-        let files: Vec<String> = current
-            .files
+        let id = u16::try_from(self.archives.len()).expect("more than 65535 IPF archives");
+        self.archives.push(Archive {
+            path: path.to_path_buf(),
+            name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            header: None,
+        });
+        self.ids.insert(path.to_path_buf(), id);
+        id
+    }
+
+    pub fn get(&self, id: u16) -> &Archive {
+        &self.archives[id as usize]
+    }
+
+    /// Case-insensitive lookup by file name, with or without the ".ipf" extension
+    pub fn find(&self, name: &str) -> Option<u16> {
+        self.archives
             .iter()
-            .map(|f| f.directory_name.clone())
-            .collect();
+            .position(|a| archive_name_matches(&a.name, name))
+            .map(|id| id as u16)
+    }
 
-        Some((subfolders, files))
+    pub fn iter(&self) -> impl Iterator<Item = &Archive> {
+        self.archives.iter()
     }
 
-    /// Recursive search for files matching `file_name`, returns full path and reference
-    pub fn search_file_recursive<'a>(
-        &'a self,
-        file_name: &str, // could be "barrack dds"
-        current_path: &str,
-    ) -> Vec<(String, &'a IPFFileTable)> {
-        let mut results = Vec::new();
+    /// Load order of every archive, indexed by id: folder first, then file name,
+    /// as in `ipf::sort_file_tables_by_folder_then_name`
+    fn ranks(&self) -> Vec<u16> {
+        let mut order: Vec<u16> = (0..self.archives.len() as u16).collect();
+        order.sort_by(|&a, &b| {
+            let (a, b) = (&self.get(a).path, &self.get(b).path);
+            a.parent()
+                .cmp(&b.parent())
+                .then_with(|| a.file_name().cmp(&b.file_name()))
+        });
+        let mut ranks = vec![0; order.len()];
+        for (rank, &id) in order.iter().enumerate() {
+            ranks[id as usize] = rank as u16;
+        }
+        ranks
+    }
 
-        // Split the search string into words (ignoring extra spaces)
-        let search_words: Vec<String> = file_name
-            .split_whitespace()
-            .map(|w| w.to_lowercase())
-            .collect();
+    pub fn len(&self) -> usize {
+        self.archives.len()
+    }
 
-        // Check files in current folder
-        for f in &self.files {
-            let file_lower = f.directory_name.to_lowercase();
-            // Only match if all words are contained
-            if search_words.iter().all(|word| file_lower.contains(word)) {
-                let full_path = if current_path.is_empty() {
-                    f.directory_name.clone()
-                } else {
-                    format!("{}/{}", current_path, f.directory_name)
-                };
-                results.push((full_path, f));
+    pub fn is_empty(&self) -> bool {
+        self.archives.is_empty()
+    }
+}
+
+/// Deduplicated strings addressed by a u32 id
+#[derive(Debug, Default)]
+pub struct StringInterner {
+    strings: Vec<Box<str>>,
+    // Only needed while building, dropped by `Folder::shrink`
+    ids: HashMap<Box<str>, u32>,
+}
+
+impl StringInterner {
+    pub fn intern(&mut self, value: &str) -> u32 {
+        if let Some(&id) = self.ids.get(value) {
+            return id;
+        }
+
+        let id = self.strings.len() as u32;
+        self.strings.push(value.into());
+        self.ids.insert(value.into(), id);
+        id
+    }
+
+    pub fn get(&self, id: u32) -> &str {
+        &self.strings[id as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    fn heap_bytes(&self) -> usize {
+        self.strings.iter().map(|s| s.len()).sum::<usize>()
+            + self.strings.capacity() * size_of::<Box<str>>()
+    }
+}
+
+/// Compact record of one archived file version
+#[derive(Debug, Clone)]
+pub struct FileEntry {
+    /// File name without folders
+    pub name: Box<str>,
+    pub crc32: u32,
+    pub file_size_compressed: u32,
+    pub file_size_uncompressed: u32,
+    /// Offset in the IPF archive
+    pub file_pointer: u32,
+    container: u32,
+    folder: u32,
+    archive: u16,
+}
+
+#[derive(Debug)]
+struct FolderNode {
+    name: Box<str>,
+    parent: u32,
+    /// Child folder ids, sorted case-insensitively by name
+    children: Vec<u32>,
+    /// Files are stored contiguously, sorted by name with versions in archive order
+    files_start: u32,
+    files_len: u32,
+    /// Recursive totals: distinct file paths, file versions and latest uncompressed size
+    file_count: u32,
+    version_count: u32,
+    total_size: u64,
+}
+
+/// Estimated heap usage of the tree
+#[derive(Debug, Clone, Serialize)]
+pub struct TreeMemoryUsage {
+    pub folders: usize,
+    pub files: usize,
+    pub archives: usize,
+    pub container_names: usize,
+    pub folder_bytes: usize,
+    pub file_bytes: usize,
+    pub string_bytes: usize,
+    pub path_index_bytes: usize,
+    pub total_bytes: usize,
+}
+
+/// File tree of every IPF archive, stored as an arena of folder nodes and a flat
+/// file table. Archive paths and container names are interned.
+#[derive(Debug)]
+pub struct Folder {
+    archives: ArchiveTable,
+    containers: StringInterner,
+    // nodes[0] is the root
+    nodes: Vec<FolderNode>,
+    files: Vec<FileEntry>,
+    // hash of the normalized full path -> (first file id, version count)
+    path_index: HashMap<u64, (u32, u32)>,
+}
+
+/// A file version borrowed from the tree
+#[derive(Debug, Clone, Copy)]
+pub struct FileRef<'a> {
+    tree: &'a Folder,
+    id: u32,
+}
+
+impl<'a> FileRef<'a> {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn entry(&self) -> &'a FileEntry {
+        &self.tree.files[self.id as usize]
+    }
+
+    pub fn name(&self) -> &'a str {
+        &self.entry().name
+    }
+
+    /// Name of the archive's internal container, e.g. "ies.ipf"
+    pub fn container_name(&self) -> &'a str {
+        self.tree.containers.get(self.entry().container)
+    }
+
+    pub fn archive_id(&self) -> u16 {
+        self.entry().archive
+    }
+
+    pub fn archive(&self) -> &'a Archive {
+        self.tree.archives.get(self.entry().archive)
+    }
+
+    /// Path of the IPF archive holding this file
+    pub fn file_path(&self) -> &'a Path {
+        &self.archive().path
+    }
+
+    /// Full path of the file inside the tree, e.g. "ies/item.ies"
+    pub fn full_path(&self) -> String {
+        let folder = self.tree.folder_path(self.entry().folder);
+        if folder.is_empty() {
+            self.name().to_string()
+        } else {
+            format!("{}/{}", folder, self.name())
+        }
+    }
+
+    /// First `len` bytes of the file, cheaper than `extract_data` for large files
+    pub fn extract_head(&self, len: usize) -> io::Result<Vec<u8>> {
+        let entry = self.entry();
+        ipf::extract_file_head(
+            self.file_path(),
+            &entry.name,
+            entry.file_pointer,
+            entry.file_size_compressed,
+            len,
+        )
+    }
+
+    /// Format sniffed from the file's first bytes, `Binary` when it cannot be read
+    pub fn format(&self) -> FileFormat {
+        match self.extract_head(sniff::SNIFF_LEN) {
+            Ok(head) => sniff::detect(self.name(), &head, self.file_size_uncompressed as u64),
+            Err(_) => FileFormat::Binary,
+        }
+    }
+
+    /// Version index of this file among every version of its path, oldest first
+    pub fn version(&self) -> usize {
+        let entry = self.entry();
+        self.tree.files[..self.id as usize]
+            .iter()
+            .rev()
+            .take_while(|f| {
+                f.folder == entry.folder && cmp_ignore_case(&f.name, &entry.name).is_eq()
+            })
+            .count()
+    }
+
+    pub fn extract_data(&self) -> io::Result<Vec<u8>> {
+        let entry = self.entry();
+        ipf::extract_file_data(
+            self.file_path(),
+            &entry.name,
+            entry.file_pointer,
+            entry.file_size_compressed,
+            entry.file_size_uncompressed,
+        )
+    }
+}
+
+impl Deref for FileRef<'_> {
+    type Target = FileEntry;
+
+    fn deref(&self) -> &FileEntry {
+        self.entry()
+    }
+}
+
+/// A folder borrowed from the tree
+#[derive(Debug, Clone, Copy)]
+pub struct FolderRef<'a> {
+    tree: &'a Folder,
+    id: u32,
+}
+
+impl<'a> FolderRef<'a> {
+    fn node(&self) -> &'a FolderNode {
+        &self.tree.nodes[self.id as usize]
+    }
+
+    pub fn name(&self) -> &'a str {
+        &self.node().name
+    }
+
+    /// Full path of the folder, "" for the root
+    pub fn path(&self) -> String {
+        self.tree.folder_path(self.id)
+    }
+
+    pub fn subfolders(&self) -> impl Iterator<Item = FolderRef<'a>> + 'a {
+        let tree = self.tree;
+        self.node()
+            .children
+            .iter()
+            .map(move |&id| FolderRef { tree, id })
+    }
+
+    /// Every file version directly inside this folder
+    pub fn files(&self) -> impl Iterator<Item = FileRef<'a>> + 'a {
+        let tree = self.tree;
+        let node = self.node();
+        (node.files_start..node.files_start + node.files_len).map(move |id| FileRef { tree, id })
+    }
+
+    /// Every distinct file directly inside this folder with all of its versions
+    pub fn file_versions(&self) -> impl Iterator<Item = FileVersions<'a>> + 'a {
+        let tree = self.tree;
+        let node = self.node();
+        version_runs(&tree.files, node.files_start, node.files_len)
+            .map(move |(start, len)| FileVersions { tree, start, len })
+    }
+
+    /// Distinct file paths in this folder and below
+    pub fn file_count(&self) -> u32 {
+        self.node().file_count
+    }
+
+    /// File versions in this folder and below
+    pub fn version_count(&self) -> u32 {
+        self.node().version_count
+    }
+
+    /// Uncompressed size of the latest version of every file in this folder and below
+    pub fn total_size(&self) -> u64 {
+        self.node().total_size
+    }
+
+    /// Aggregate sizes and extension counts of this folder and below, keeping
+    /// child folders down to `depth` levels
+    pub fn stats(&self, depth: usize) -> FolderStats {
+        let mut stats = FolderStats {
+            name: self.name().to_string(),
+            path: self.path(),
+            file_count: self.file_versions().count() as u32,
+            ..FolderStats::default()
+        };
+
+        for file in self.files() {
+            stats.version_count += 1;
+            stats.compressed_bytes += file.file_size_compressed as u64;
+            stats.uncompressed_bytes += file.file_size_uncompressed as u64;
+            stats
+                .extensions
+                .entry(ipf::file_extension(file.name()))
+                .or_default()
+                .add(file.file_size_compressed, file.file_size_uncompressed);
+        }
+
+        for subfolder in self.subfolders() {
+            let child = subfolder.stats(depth.saturating_sub(1));
+            stats.file_count += child.file_count;
+            stats.version_count += child.version_count;
+            stats.compressed_bytes += child.compressed_bytes;
+            stats.uncompressed_bytes += child.uncompressed_bytes;
+            for (ext, ext_stats) in &child.extensions {
+                stats
+                    .extensions
+                    .entry(ext.clone())
+                    .or_default()
+                    .merge(ext_stats);
+            }
+            if depth > 0 {
+                stats.children.push(child);
             }
         }
 
-        // Recurse into subfolders
-        for (name, folder) in &self.subfolders {
-            let path = if current_path.is_empty() {
-                name.clone()
-            } else {
-                format!("{}/{}", current_path, name)
-            };
-            results.extend(folder.search_file_recursive(file_name, &path));
+        stats
+            .children
+            .sort_by_key(|child| std::cmp::Reverse(child.uncompressed_bytes));
+        stats
+    }
+
+    /// Case-insensitive lookup of a direct subfolder
+    pub fn subfolder(&self, name: &str) -> Option<FolderRef<'a>> {
+        let tree = self.tree;
+        let children = &self.node().children;
+        children
+            .binary_search_by(|&id| cmp_ignore_case(&tree.nodes[id as usize].name, name))
+            .ok()
+            .map(|i| FolderRef {
+                tree,
+                id: children[i],
+            })
+    }
+
+    /// Latest version of every file in this folder and below, with its full path
+    pub fn latest_files(&self) -> Vec<(String, FileRef<'a>)> {
+        let mut files = Vec::with_capacity(self.file_count() as usize);
+        self.collect_latest(&mut files);
+        files
+    }
+
+    fn collect_latest(&self, files: &mut Vec<(String, FileRef<'a>)>) {
+        for versions in self.file_versions() {
+            let latest = versions.latest();
+            files.push((latest.full_path(), latest));
+        }
+        for subfolder in self.subfolders() {
+            subfolder.collect_latest(files);
         }
+    }
+}
 
-        results
+/// Recursive size totals of a folder, see `FolderRef::stats`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FolderStats {
+    pub name: String,
+    pub path: String,
+    /// Distinct file paths
+    pub file_count: u32,
+    pub version_count: u32,
+    /// Summed over every version, i.e. what the archives occupy
+    pub compressed_bytes: u64,
+    pub uncompressed_bytes: u64,
+    pub extensions: BTreeMap<String, ExtensionStats>,
+    /// Largest first; empty once the depth limit is reached, totals still cover the subtree
+    pub children: Vec<FolderStats>,
+}
+
+/// Every version of one file path, oldest first
+#[derive(Debug, Clone, Copy)]
+pub struct FileVersions<'a> {
+    tree: &'a Folder,
+    start: u32,
+    len: u32,
+}
+
+impl<'a> FileVersions<'a> {
+    pub fn name(&self) -> &'a str {
+        self.latest().name()
     }
 
-    /// Search file by full path, e.g., "ui/brush/spraycursor_1.tga"
-    pub fn search_file_by_full_path<'a>(
-        &'a self,
-        full_path: &str,
-    ) -> Vec<(String, &'a IPFFileTable)> {
-        let mut results = Vec::new();
-        let parts: Vec<&str> = full_path.split('/').collect();
-        self.search_file_by_parts(&parts, "", &mut results);
-        results
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn latest(&self) -> FileRef<'a> {
+        FileRef {
+            tree: self.tree,
+            id: self.start + self.len - 1,
+        }
+    }
+
+    pub fn versions(&self) -> impl Iterator<Item = FileRef<'a>> + 'a {
+        let tree = self.tree;
+        (self.start..self.start + self.len).map(move |id| FileRef { tree, id })
+    }
+}
+
+impl Default for Folder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Folder {
+    /// Empty tree holding only the root folder
+    pub fn new() -> Self {
+        Self {
+            archives: ArchiveTable::default(),
+            containers: StringInterner::default(),
+            nodes: vec![FolderNode {
+                name: "".into(),
+                parent: 0,
+                children: Vec::new(),
+                files_start: 0,
+                files_len: 0,
+                file_count: 0,
+                version_count: 0,
+                total_size: 0,
+            }],
+            files: Vec::new(),
+            path_index: HashMap::new(),
+        }
+    }
+
+    pub fn root(&self) -> FolderRef<'_> {
+        FolderRef { tree: self, id: 0 }
+    }
+
+    /// Case-insensitive lookup of a nested folder, "" being the root
+    pub fn folder(&self, folder_path: &str) -> Option<FolderRef<'_>> {
+        let mut current = self.root();
+        for part in folder_path.split(['/', '\\']).filter(|p| !p.is_empty()) {
+            current = current.subfolder(part)?;
+        }
+        Some(current)
+    }
+
+    pub fn file(&self, id: u32) -> Option<FileRef<'_>> {
+        ((id as usize) < self.files.len()).then_some(FileRef { tree: self, id })
+    }
+
+    pub fn archives(&self) -> &ArchiveTable {
+        &self.archives
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Attach the headers of the parsed archives, registering archives that
+    /// contributed no files to the tree as well
+    pub fn register_archives(&mut self, roots: &[IPFRoot]) {
+        for root in roots {
+            if let Some(path) = &root.file_path {
+                let id = self.archives.intern(path);
+                self.archives.archives[id as usize].header = Some(root.header.clone());
+            }
+        }
     }
 
-    fn search_file_by_parts<'a>(
+    /// Number of file versions each archive holds, indexed by archive id
+    pub fn archive_file_counts(&self) -> Vec<u32> {
+        let mut counts = vec![0; self.archives.len()];
+        for file in &self.files {
+            counts[file.archive as usize] += 1;
+        }
+        counts
+    }
+
+    /// Every file version stored in one archive, in tree order
+    pub fn archive_files(&self, archive: u16) -> Vec<(String, FileRef<'_>)> {
+        let mut found = Vec::new();
+        self.for_each_file(|full_path, file| {
+            if file.archive_id() == archive {
+                found.push((full_path.to_string(), file));
+            }
+        });
+        found
+    }
+
+    fn folder_path(&self, mut id: u32) -> String {
+        let mut parts = Vec::new();
+        while id != 0 {
+            let node = &self.nodes[id as usize];
+            parts.push(&*node.name);
+            id = node.parent;
+        }
+        parts.reverse();
+        parts.join("/")
+    }
+
+    /// Visit every file version in tree order with its full path
+    pub fn for_each_file<'a>(&'a self, mut visit: impl FnMut(&str, FileRef<'a>)) {
+        self.walk(self.root(), "", &mut |_| true, &mut visit);
+    }
+
+    fn walk<'a>(
         &'a self,
-        parts: &[&str],
+        folder: FolderRef<'a>,
         current_path: &str,
-        results: &mut Vec<(String, &'a IPFFileTable)>,
+        enter: &mut dyn FnMut(&str) -> bool,
+        visit: &mut dyn FnMut(&str, FileRef<'a>),
     ) {
-        if parts.is_empty() {
+        if !enter(current_path) {
             return;
         }
 
-        if parts.len() == 1 {
-            // Last part = filename
-            let filename = parts[0];
-            for file in &self.files {
-                if file.directory_name.to_lowercase() == filename.to_lowercase() {
-                    let full_path = if current_path.is_empty() {
-                        filename.to_string()
-                    } else {
-                        format!("{}/{}", current_path, filename)
-                    };
-                    results.push((full_path, file));
-                }
-            }
-        } else {
-            // Intermediate folder
-            let folder_name = parts[0];
-            if let Some(subfolder) = self.subfolders.get(folder_name) {
-                let new_path = if current_path.is_empty() {
-                    folder_name.to_string()
+        for file in folder.files() {
+            let full_path = if current_path.is_empty() {
+                file.name().to_string()
+            } else {
+                format!("{}/{}", current_path, file.name())
+            };
+            visit(&full_path, file);
+        }
+
+        for subfolder in folder.subfolders() {
+            let path = if current_path.is_empty() {
+                subfolder.name().to_string()
+            } else {
+                format!("{}/{}", current_path, subfolder.name())
+            };
+            self.walk(subfolder, &path, enter, visit);
+        }
+    }
+
+    /// Index every file name run (all versions of one path) by its normalized full path
+    fn build_path_index(&mut self) {
+        let mut index = HashMap::new();
+        for (node_id, node) in self.nodes.iter().enumerate() {
+            let folder = self.folder_path(node_id as u32).to_lowercase();
+            for (start, len) in version_runs(&self.files, node.files_start, node.files_len) {
+                let name = self.files[start as usize].name.to_lowercase();
+                let normalized = if folder.is_empty() {
+                    name
                 } else {
-                    format!("{}/{}", current_path, folder_name)
+                    format!("{}/{}", folder, name)
                 };
-                subfolder.search_file_by_parts(&parts[1..], &new_path, results);
+                index.insert(hash_path(&normalized), (start, len));
             }
         }
+        self.path_index = index;
+    }
+
+    /// Fill the recursive per-folder totals, children always have higher ids than parents
+    fn compute_folder_totals(&mut self) {
+        for id in (0..self.nodes.len()).rev() {
+            let node = &self.nodes[id];
+            let runs: Vec<(u32, u32)> =
+                version_runs(&self.files, node.files_start, node.files_len).collect();
+
+            let mut file_count = runs.len() as u32;
+            let mut version_count = node.files_len;
+            let mut total_size: u64 = runs
+                .iter()
+                .map(|&(start, len)| {
+                    self.files[(start + len - 1) as usize].file_size_uncompressed as u64
+                })
+                .sum();
+            for &child in &node.children {
+                let child = &self.nodes[child as usize];
+                file_count += child.file_count;
+                version_count += child.version_count;
+                total_size += child.total_size;
+            }
+
+            let node = &mut self.nodes[id];
+            node.file_count = file_count;
+            node.version_count = version_count;
+            node.total_size = total_size;
+        }
+    }
+
+    /// Release memory only needed while building
+    fn shrink(&mut self) {
+        self.containers.ids = HashMap::new();
+        self.nodes.shrink_to_fit();
+        self.files.shrink_to_fit();
+        self.path_index.shrink_to_fit();
+    }
+
+    /// Estimated heap usage, reported by `/api/info`
+    pub fn memory_usage(&self) -> TreeMemoryUsage {
+        let folder_bytes = self.nodes.capacity() * size_of::<FolderNode>()
+            + self
+                .nodes
+                .iter()
+                .map(|n| n.name.len() + n.children.capacity() * size_of::<u32>())
+                .sum::<usize>();
+        let file_bytes = self.files.capacity() * size_of::<FileEntry>()
+            + self.files.iter().map(|f| f.name.len()).sum::<usize>();
+        let string_bytes = self.containers.heap_bytes()
+            + self
+                .archives
+                .iter()
+                .map(|a| a.path.as_os_str().len() + a.name.len() + size_of::<Archive>())
+                .sum::<usize>();
+        // hashbrown stores one control byte per bucket next to each (key, value) slot
+        let path_index_bytes = self.path_index.capacity() * (size_of::<(u64, (u32, u32))>() + 1);
+
+        TreeMemoryUsage {
+            folders: self.nodes.len(),
+            files: self.files.len(),
+            archives: self.archives.len(),
+            container_names: self.containers.len(),
+            folder_bytes,
+            file_bytes,
+            string_bytes,
+            path_index_bytes,
+            total_bytes: folder_bytes + file_bytes + string_bytes + path_index_bytes,
+        }
+    }
+
+    /// Print tree with optional limit for folders and files
+    pub fn print_limited(&self, prefix: &str, folder_limit: usize, file_limit: usize) {
+        print_folder_limited(self.root(), prefix, folder_limit, file_limit);
+    }
+
+    /// Shallow search for a folder: returns subfolder names and files directly inside it
+    pub fn search_folder_shallow(&self, folder_path: &str) -> Option<(Vec<String>, Vec<String>)> {
+        let folder = self.folder(folder_path)?;
+
+        let subfolders = folder.subfolders().map(|f| f.name().to_string()).collect();
+        let files = folder.files().map(|f| f.name().to_string()).collect();
+
+        Some((subfolders, files))
+    }
+
+    /// Recursive search for files matching `file_name`, returns full path and reference
+    pub fn search_file_recursive(&self, file_name: &str) -> Vec<(String, FileRef<'_>)> {
+        self.search_files(&FileFilter::new(PathMatcher::words(file_name)))
+    }
+
+    /// Search file by full path, e.g., "ui/brush/spraycursor_1.tga" (case-insensitive)
+    pub fn search_file_by_full_path(&self, full_path: &str) -> Vec<(String, FileRef<'_>)> {
+        let normalized = normalize_path(full_path);
+        let Some(&(start, len)) = self.path_index.get(&hash_path(&normalized)) else {
+            return Vec::new();
+        };
+
+        let Some(first) = self.file(start) else {
+            return Vec::new();
+        };
+        let canonical = first.full_path();
+        if canonical.to_lowercase() != normalized {
+            return Vec::new(); // hash collision
+        }
+
+        (start..start + len)
+            .map(|id| (canonical.clone(), FileRef { tree: self, id }))
+            .collect()
+    }
+
+    /// Files directly inside `folder_path` whose name starts with `prefix` (case-insensitive),
+    /// e.g. ("ies_drop", "anchor_") for every per-map anchor table
+    pub fn search_files_by_prefix(
+        &self,
+        folder_path: &str,
+        prefix: &str,
+    ) -> Vec<(String, FileRef<'_>)> {
+        let Some(folder) = self.folder(folder_path) else {
+            return Vec::new();
+        };
+        let path = folder.path();
+
+        let prefix_lower = prefix.to_lowercase();
+        folder
+            .files()
+            .filter(|f| f.name().to_lowercase().starts_with(&prefix_lower))
+            .map(|f| {
+                let full_path = if path.is_empty() {
+                    f.name().to_string()
+                } else {
+                    format!("{}/{}", path, f.name())
+                };
+                (full_path, f)
+            })
+            .collect()
+    }
+
+    /// Recursive search with a `FileFilter`, returns full path and reference
+    pub fn search_files(&self, filter: &FileFilter) -> Vec<(String, FileRef<'_>)> {
+        let mut results = Vec::new();
+        self.walk(
+            self.root(),
+            "",
+            &mut |path| filter.matcher.may_match_below(path),
+            &mut |full_path, file| {
+                if filter.accepts(full_path, &file) {
+                    results.push((full_path.to_string(), file));
+                }
+            },
+        );
+        results
+    }
+
+    /// Glob search over the whole tree, e.g. "ies_drop/zonedropitemlist_*.ies"
+    pub fn search_files_glob(&self, pattern: &str) -> Vec<(String, FileRef<'_>)> {
+        self.search_files(&FileFilter::new(PathMatcher::glob(pattern)))
+    }
+}
+
+fn print_folder_limited(folder: FolderRef, prefix: &str, folder_limit: usize, file_limit: usize) {
+    let node = folder.node();
+
+    // Print subfolders
+    for (i, subfolder) in folder.subfolders().enumerate() {
+        if i >= folder_limit {
+            println!(
+                "{}... ({} more folders)",
+                prefix,
+                node.children.len() - folder_limit
+            );
+            break;
+        }
+        println!("{}Folder: {}", prefix, subfolder.name());
+        print_folder_limited(
+            subfolder,
+            &format!("{}  ", prefix),
+            folder_limit,
+            file_limit,
+        );
+    }
+
+    // Print files
+    for (i, file) in folder.files().enumerate() {
+        if i >= file_limit {
+            println!(
+                "{}... ({} more files)",
+                prefix,
+                node.files_len as usize - file_limit
+            );
+            break;
+        }
+        println!("{}File: {:?}", prefix, file.file_path());
     }
 }
 
@@ -212,48 +1129,292 @@ pub fn print_shallow_tree(root: &Folder, max_subfolders: usize, max_files: usize
     println!("Root folder:");
 
     // Show subfolders (shallow)
-    for (i, (folder_name, folder_node)) in root.subfolders.iter().enumerate() {
+    for (i, folder) in root.root().subfolders().enumerate() {
         if i >= max_subfolders {
             break;
         }
-        println!("  Folder: {}", folder_name);
+        println!("  Folder: {}", folder.name());
 
         // Show first few files in this subfolder
-        for (j, file) in folder_node.files.iter().enumerate() {
-            if j >= max_files {
-                break;
-            }
-            println!("    File: {:?}", file.directory_name);
+        for file in folder.files().take(max_files) {
+            println!("    File: {:?}", file.name());
         }
     }
 
     // Also show root-level files
     println!("  Root files:");
-    for (i, file) in root.files.iter().enumerate() {
-        if i >= max_files {
-            break;
-        }
-        println!("    File: {:?}", file.directory_name);
+    for file in root.root().files().take(max_files) {
+        println!("    File: {:?}", file.name());
     }
 }
 
-// Usage
+struct BuildNode {
+    name: String,
+    parent: u32,
+    // lowercased name -> node id
+    children: HashMap<String, u32>,
+    files: Vec<FileEntry>,
+}
+
+/// Build the tree from file tables grouped by full path ("<container stem>/<dir>/<file>").
+///
+/// Folder names differing only in case are merged into the first one seen.
 pub fn build_tree(grouped: BTreeMap<String, Vec<IPFFileTable>>) -> Folder {
-    let mut root = Folder::new();
+    let mut tree = Folder::new();
+    let mut nodes = vec![BuildNode {
+        name: String::new(),
+        parent: 0,
+        children: HashMap::new(),
+        files: Vec::new(),
+    }];
+
+    for (dir, files) in grouped {
+        let mut parts: Vec<&str> = dir.split('/').collect();
+        parts.pop(); // file name
+
+        let mut node = 0u32;
+        for part in parts {
+            let next = nodes.len() as u32;
+            let child = *nodes[node as usize]
+                .children
+                .entry(part.to_lowercase())
+                .or_insert(next);
+            if child == next {
+                nodes.push(BuildNode {
+                    name: part.to_string(),
+                    parent: node,
+                    children: HashMap::new(),
+                    files: Vec::new(),
+                });
+            }
+            node = child;
+        }
 
-    for (dir, mut files) in grouped.into_iter() {
-        for mut file in files.drain(..) {
-            // Keep only the filename in directory_name
-            if let Some(name) = std::path::Path::new(&file.directory_name)
+        for file in files {
+            // Keep only the filename
+            let name = Path::new(&file.directory_name)
                 .file_name()
                 .and_then(|s| s.to_str())
-            {
-                file.directory_name = name.to_string();
-            }
+                .unwrap_or(&file.directory_name);
+
+            let archive = tree
+                .archives
+                .intern(file.file_path.as_deref().unwrap_or(Path::new("")));
+            let container = tree.containers.intern(&file.container_name);
 
-            root.insert(&dir, file);
+            nodes[node as usize].files.push(FileEntry {
+                name: name.into(),
+                crc32: file.crc32,
+                file_size_compressed: file.file_size_compressed,
+                file_size_uncompressed: file.file_size_uncompressed,
+                file_pointer: file.file_pointer,
+                container,
+                folder: node,
+                archive,
+            });
         }
     }
 
-    root
+    // Flatten into the arena
+    let ranks = tree.archives.ranks();
+    tree.nodes.clear();
+    for build in &mut nodes {
+        // Versions of one name may come from several case variants of its path,
+        // so order them by archive rather than by the path key they were grouped under
+        build.files.sort_by(|a, b| {
+            cmp_ignore_case(&a.name, &b.name)
+                .then_with(|| ranks[a.archive as usize].cmp(&ranks[b.archive as usize]))
+        });
+
+        let files_start = tree.files.len() as u32;
+        tree.files.append(&mut build.files);
+
+        tree.nodes.push(FolderNode {
+            name: std::mem::take(&mut build.name).into(),
+            parent: build.parent,
+            children: build.children.values().copied().collect(),
+            files_start,
+            files_len: tree.files.len() as u32 - files_start,
+            file_count: 0,
+            version_count: 0,
+            total_size: 0,
+        });
+    }
+    for i in 0..tree.nodes.len() {
+        let mut children = std::mem::take(&mut tree.nodes[i].children);
+        children.sort_by(|&a, &b| {
+            cmp_ignore_case(&tree.nodes[a as usize].name, &tree.nodes[b as usize].name)
+        });
+        tree.nodes[i].children = children;
+    }
+
+    tree.compute_folder_totals();
+    tree.build_path_index();
+    tree.shrink();
+    tree
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(
+            "ies_drop/zonedropitemlist_*.ies",
+            "ies_drop/zonedropitemlist_f_siauliai_west.ies"
+        ));
+        assert!(!glob_match("ies_drop/*.ies", "ies_drop/sub/anchor_x.ies"));
+        assert!(glob_match("ies_drop/**.ies", "ies_drop/sub/anchor_x.ies"));
+        assert!(glob_match("ies/**/*.ies", "ies/item.ies"));
+        assert!(glob_match("ies/**/*.ies", "ies/a/b/item.ies"));
+        assert!(glob_match("bg/?.tok", "bg/a.tok"));
+        assert!(!glob_match("bg/?.tok", "bg/ab.tok"));
+    }
+
+    fn file(name: &str) -> IPFFileTable {
+        IPFFileTable {
+            directory_name_length: name.len() as u16,
+            crc32: 0,
+            file_size_compressed: 0,
+            file_size_uncompressed: 0,
+            file_pointer: 0,
+            container_name_length: 0,
+            container_name: String::new(),
+            directory_name: name.to_string(),
+            file_path: None,
+        }
+    }
+
+    #[test]
+    fn test_full_path_lookup_is_case_insensitive() {
+        let mut grouped = BTreeMap::new();
+        grouped.insert("ies/Item.ies".to_string(), vec![file("Item.ies")]);
+        grouped.insert("IES/item.ies".to_string(), vec![file("item.ies")]);
+        let tree = build_tree(grouped);
+
+        // "IES" and "ies" share one folder and both versions are indexed under one path
+        assert_eq!(tree.root().subfolders().count(), 1);
+        assert_eq!(tree.search_file_by_full_path("ies/ITEM.IES").len(), 2);
+        assert_eq!(tree.search_file_by_full_path("\\Ies\\item.ies").len(), 2);
+        assert!(tree.search_folder_shallow("Ies").is_some());
+    }
+
+    #[test]
+    fn test_folder_totals_and_versions() {
+        let mut grouped = BTreeMap::new();
+        let sized = |name: &str, size: u32| {
+            let mut f = file(name);
+            f.file_size_uncompressed = size;
+            f
+        };
+        grouped.insert(
+            "ies/item.ies".to_string(),
+            vec![sized("item.ies", 10), sized("item.ies", 30)],
+        );
+        grouped.insert("ies/sub/job.ies".to_string(), vec![sized("job.ies", 5)]);
+        let tree = build_tree(grouped);
+
+        let ies = tree.folder("ies").unwrap();
+        assert_eq!(ies.file_count(), 2);
+        assert_eq!(ies.version_count(), 3);
+        assert_eq!(ies.total_size(), 35);
+
+        let item = ies.file_versions().next().unwrap();
+        assert_eq!(item.len(), 2);
+        assert_eq!(item.latest().file_size_uncompressed, 30);
+    }
+
+    #[test]
+    fn test_folder_stats_depth() {
+        let mut grouped = BTreeMap::new();
+        grouped.insert(
+            "ies/item.ies".to_string(),
+            vec![file("item.ies"), file("item.ies")],
+        );
+        grouped.insert("ies/sub/a.xml".to_string(), vec![file("a.xml")]);
+        let tree = build_tree(grouped);
+
+        let shallow = tree.root().stats(1);
+        assert_eq!(shallow.file_count, 2);
+        assert_eq!(shallow.version_count, 3);
+        assert_eq!(shallow.extensions["ies"].count, 2);
+        assert_eq!(shallow.children.len(), 1);
+        assert!(shallow.children[0].children.is_empty());
+        assert_eq!(shallow.children[0].extensions["xml"].count, 1);
+    }
+
+    #[test]
+    fn test_case_variant_versions_follow_archive_order() {
+        let in_archive = |name: &str, archive: &str| IPFFileTable {
+            file_path: Some(PathBuf::from(archive)),
+            ..file(name)
+        };
+        let mut grouped = BTreeMap::new();
+        // The patch's spelling sorts first among the grouped keys
+        grouped.insert(
+            "IES/item.ies".to_string(),
+            vec![in_archive("item.ies", "patch/001001.ipf")],
+        );
+        grouped.insert(
+            "ies/Item.ies".to_string(),
+            vec![in_archive("Item.ies", "data/ies.ipf")],
+        );
+        let tree = build_tree(grouped);
+
+        let versions = tree.search_file_by_full_path("ies/item.ies");
+        let archives: Vec<&str> = versions
+            .iter()
+            .map(|(_, file)| file.archive().name.as_str())
+            .collect();
+        assert_eq!(archives, vec!["ies.ipf", "001001.ipf"]);
+        assert_eq!(versions[1].1.version(), 1);
+    }
+
+    #[test]
+    fn test_archive_files_and_versions() {
+        let in_archive = |name: &str, archive: &str| IPFFileTable {
+            file_path: Some(PathBuf::from(archive)),
+            ..file(name)
+        };
+        let mut grouped = BTreeMap::new();
+        grouped.insert(
+            "ies/item.ies".to_string(),
+            vec![
+                in_archive("item.ies", "data/ies.ipf"),
+                in_archive("item.ies", "patch/001001.ipf"),
+            ],
+        );
+        let tree = build_tree(grouped);
+
+        let patch = tree.archives().find("001001").unwrap();
+        assert!(tree.archives().get(patch).is_patch());
+        let files = tree.archive_files(patch);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].0, "ies/item.ies");
+        assert_eq!(files[0].1.version(), 1);
+        assert_eq!(tree.archive_file_counts(), vec![1, 1]);
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        assert_eq!(natural_cmp("file2.ies", "File10.ies"), Ordering::Less);
+        assert_eq!(natural_cmp("a", "A"), Ordering::Equal);
+        assert_eq!(natural_cmp("x1", "x01"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_prefix_prunes_folders() {
+        let matcher = PathMatcher::prefix("ies_drop/anchor_");
+        assert!(matcher.may_match_below(""));
+        assert!(matcher.may_match_below("ies_drop"));
+        assert!(!matcher.may_match_below("ies_mongem"));
+        assert!(matcher.matches("ies_drop/Anchor_f_farm.ies", "Anchor_f_farm.ies"));
+    }
+
+    #[test]
+    fn test_encode_query_path() {
+        assert_eq!(encode_query_path("ies/item.ies"), "ies/item.ies");
+        assert_eq!(encode_query_path("bg/a b&c#d.dds"), "bg/a%20b%26c%23d.dds");
+    }
 }
@@ -0,0 +1,100 @@
+//! ClassName lookup over one or more IES tables.
+//!
+//! Game tables reference each other by `ClassName` (e.g. a recipe names its
+//! ingredients). A `ClassIndex` merges the rows of related tables (all the
+//! item tables, all the monster tables, ...) and resolves display names
+//! through the language dictionary.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::ies::IESRoot;
+use crate::tsv;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClassEntry {
+    pub class_id: i32,
+    pub class_name: String,
+    pub name: String,
+    pub group: String,
+    pub icon: String,
+    /// Source table, e.g. "item_equip"
+    pub table: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ClassIndex {
+    entries: HashMap<String, ClassEntry>,
+}
+
+impl ClassIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add every row of `ies`, keyed by lowercased ClassName.
+    ///
+    /// Later tables override earlier ones for duplicate class names.
+    pub fn add_ies(&mut self, table: &str, ies: &IESRoot, dictionary: &HashMap<String, String>) {
+        let lookup = ies.column_lookup();
+
+        for row in &ies.data {
+            let class_name = lookup
+                .text(row, "ClassName")
+                .unwrap_or(&row.row_text.text_data)
+                .to_string();
+            if class_name.is_empty() {
+                continue;
+            }
+
+            let name = lookup
+                .text(row, "Name")
+                .map(|n| tsv::resolve_dic_ids(n, dictionary))
+                .unwrap_or_default();
+
+            self.entries.insert(
+                class_name.to_lowercase(),
+                ClassEntry {
+                    class_id: row.index_data,
+                    class_name,
+                    name,
+                    group: lookup
+                        .text(row, "GroupName")
+                        .unwrap_or_default()
+                        .to_string(),
+                    icon: lookup.text(row, "Icon").unwrap_or_default().to_string(),
+                    table: table.to_string(),
+                },
+            );
+        }
+    }
+
+    pub fn insert(&mut self, entry: ClassEntry) {
+        self.entries.insert(entry.class_name.to_lowercase(), entry);
+    }
+
+    pub fn get(&self, class_name: &str) -> Option<&ClassEntry> {
+        self.entries.get(&class_name.to_lowercase())
+    }
+
+    /// Display name for `class_name`, falling back to the class name itself
+    pub fn display_name(&self, class_name: &str) -> String {
+        match self.get(class_name) {
+            Some(entry) if !entry.name.is_empty() => entry.name.clone(),
+            _ => class_name.to_string(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ClassEntry> {
+        self.entries.values()
+    }
+}
@@ -0,0 +1,241 @@
+//! Collections (`ies/collection.ies`) and the card album (`ies/cardbattle.ies`).
+//!
+//! Collections list the items required to complete them and the stat bonuses
+//! granted on completion. Cards carry battle stats, the monster they depict
+//! and the illustration registered in `ui/baseskinset/monillust.xml`.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::class_index::ClassIndex;
+use crate::ies::IESRoot;
+use crate::tsv;
+use crate::xml::SkinImage;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkedClass {
+    pub class_name: String,
+    pub name: String,
+    /// Whether the class name was found in the linked table
+    pub resolved: bool,
+}
+
+impl LinkedClass {
+    pub fn new(class_name: &str, index: &ClassIndex) -> Self {
+        LinkedClass {
+            class_name: class_name.to_string(),
+            name: index.display_name(class_name),
+            resolved: index.get(class_name).is_some(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StatBonus {
+    pub property: String,
+    pub value: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Collection {
+    pub class_id: i32,
+    pub class_name: String,
+    pub name: String,
+    pub required_items: Vec<LinkedClass>,
+    /// Bonus applied to the character on completion
+    pub bonuses: Vec<StatBonus>,
+    /// Bonus applied to the whole account on completion
+    pub account_bonuses: Vec<StatBonus>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Card {
+    pub class_id: i32,
+    pub class_name: String,
+    pub name: String,
+    pub monster: Option<LinkedClass>,
+    /// Every numeric column of the row, keyed by column name
+    pub stats: BTreeMap<String, f32>,
+    pub illustration: Option<SkinImage>,
+}
+
+#[derive(Debug, Default)]
+pub struct CollectionData {
+    pub collections: Vec<Collection>,
+    pub cards: Vec<Card>,
+}
+
+impl CollectionData {
+    pub fn collection(&self, class_name: &str) -> Option<&Collection> {
+        self.collections
+            .iter()
+            .find(|c| c.class_name.eq_ignore_ascii_case(class_name))
+    }
+
+    pub fn card(&self, class_name: &str) -> Option<&Card> {
+        self.cards
+            .iter()
+            .find(|c| c.class_name.eq_ignore_ascii_case(class_name))
+    }
+}
+
+/// Parse `collection.ies` rows into collections linked to the item index.
+///
+/// Requirements come from the `ItemName_<n>` columns, bonuses from
+/// `PropList_Clent` (falling back to `PropList`) and `AccPropList_Clent`.
+pub fn parse_collections(
+    ies: &IESRoot,
+    items: &ClassIndex,
+    dictionary: &HashMap<String, String>,
+) -> Vec<Collection> {
+    let lookup = ies.column_lookup();
+
+    let mut item_columns: Vec<(u32, &String)> = lookup
+        .names()
+        .iter()
+        .filter_map(|name| {
+            let n = name.strip_prefix("ItemName_")?.parse().ok()?;
+            Some((n, name))
+        })
+        .collect();
+    item_columns.sort();
+
+    let prop_list = |row, columns: &[&str]| {
+        columns
+            .iter()
+            .find_map(|c| lookup.text(row, c).filter(|t| !t.is_empty()))
+            .map(parse_prop_list)
+            .unwrap_or_default()
+    };
+
+    ies.data
+        .iter()
+        .map(|row| Collection {
+            class_id: row.index_data,
+            class_name: lookup
+                .text(row, "ClassName")
+                .unwrap_or(&row.row_text.text_data)
+                .to_string(),
+            name: lookup
+                .text(row, "Name")
+                .map(|n| tsv::resolve_dic_ids(n, dictionary))
+                .unwrap_or_default(),
+            required_items: item_columns
+                .iter()
+                .filter_map(|(_, column)| lookup.text(row, column))
+                .filter(|item| !item.is_empty() && *item != "None")
+                .map(|item| LinkedClass::new(item, items))
+                .collect(),
+            bonuses: prop_list(row, &["PropList_Clent", "PropList"]),
+            account_bonuses: prop_list(row, &["AccPropList_Clent", "AccPropList"]),
+        })
+        .collect()
+}
+
+/// Parse `cardbattle.ies` rows into cards linked to monsters and illustrations.
+///
+/// The depicted monster is read from `MonsterName`, `MonName` or `TargetMonster`,
+/// falling back to the card's own class name when it is a monster class.
+pub fn parse_cards(
+    ies: &IESRoot,
+    monsters: &ClassIndex,
+    illustrations: &[SkinImage],
+    dictionary: &HashMap<String, String>,
+) -> Vec<Card> {
+    let lookup = ies.column_lookup();
+
+    let illust_by_name: HashMap<String, &SkinImage> = illustrations
+        .iter()
+        .map(|img| (img.name.to_lowercase(), img))
+        .collect();
+
+    ies.data
+        .iter()
+        .map(|row| {
+            let class_name = lookup
+                .text(row, "ClassName")
+                .unwrap_or(&row.row_text.text_data)
+                .to_string();
+
+            let monster_class = ["MonsterName", "MonName", "TargetMonster"]
+                .iter()
+                .find_map(|c| {
+                    lookup
+                        .text(row, c)
+                        .filter(|t| !t.is_empty() && *t != "None")
+                })
+                .map(str::to_string)
+                .or_else(|| monsters.get(&class_name).map(|_| class_name.clone()));
+
+            let stats = lookup
+                .names()
+                .iter()
+                .filter_map(|column| Some((column.clone(), lookup.number(row, column)?)))
+                .collect();
+
+            // Illustrations are registered under the monster class or an explicit Illust column
+            let illustration = ["Illust", "Icon"]
+                .iter()
+                .filter_map(|c| lookup.text(row, c))
+                .chain(monster_class.as_deref())
+                .chain(std::iter::once(class_name.as_str()))
+                .find_map(|key| illust_by_name.get(&key.to_lowercase()))
+                .map(|img| (*img).clone());
+
+            Card {
+                class_id: row.index_data,
+                name: lookup
+                    .text(row, "Name")
+                    .map(|n| tsv::resolve_dic_ids(n, dictionary))
+                    .unwrap_or_default(),
+                monster: monster_class.map(|m| LinkedClass::new(&m, monsters)),
+                class_name,
+                stats,
+                illustration,
+            }
+        })
+        .collect()
+}
+
+/// Parse a property list such as `STR/3;MHP/150` or `STR/3/CON/2`
+pub fn parse_prop_list(text: &str) -> Vec<StatBonus> {
+    let tokens: Vec<&str> = text
+        .split(['/', ';'])
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    tokens
+        .chunks_exact(2)
+        .filter_map(|pair| {
+            Some(StatBonus {
+                property: pair[0].to_string(),
+                value: pair[1].parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_prop_list() {
+        let expected = vec![
+            StatBonus {
+                property: "STR".to_string(),
+                value: 3.0,
+            },
+            StatBonus {
+                property: "MHP".to_string(),
+                value: 150.0,
+            },
+        ];
+
+        assert_eq!(parse_prop_list("STR/3;MHP/150"), expected);
+        assert_eq!(parse_prop_list("STR/3/MHP/150"), expected);
+        assert!(parse_prop_list("").is_empty());
+    }
+}
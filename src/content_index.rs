@@ -0,0 +1,313 @@
+//! Full-text search over the text formats stored in the IPF archives.
+//!
+//! Every version of every xml, lua, skn, 3d*, effect and IES file is a
+//! document. The index maps each lowercased word to the documents containing
+//! it; identifiers such as `Boss_Wastrel` are indexed whole and by their `_`
+//! separated parts. Match context is produced at query time by extracting the
+//! candidate files again, which keeps the index small enough to cache on disk.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use serde::{Deserialize, Serialize};
+
+use crate::category::{FileRef, Folder};
+use crate::ies::IESRoot;
+use crate::ipf;
+
+/// Cache file written next to `paths.json` unless `content_index_cache` says otherwise
+pub const CONTENT_INDEX_CACHE: &str = "content_index.cache";
+
+/// `None` until the background build finishes
+pub type SharedContentIndex = Arc<RwLock<Option<ContentIndex>>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedDoc {
+    pub path: String,
+    /// Version index as returned by `Folder::search_file_by_full_path`
+    pub version: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextLine {
+    /// 1-based line number
+    pub line: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ContentIndex {
+    /// Hash of the indexed file tables, used to invalidate the cache
+    fingerprint: u64,
+    docs: Vec<IndexedDoc>,
+    postings: HashMap<String, Vec<u32>>,
+}
+
+impl ContentIndex {
+    /// Load the cached index if it still matches `tree`, otherwise build and cache a new one
+    pub fn load_or_build(tree: &Folder, cache_path: &Path) -> Self {
+        let mut files = Vec::new();
+        collect_documents(tree, &mut files);
+        let fingerprint = fingerprint(&files);
+
+        match Self::load(cache_path) {
+            Ok(index) if index.fingerprint == fingerprint => return index,
+            Ok(_) => println!("Content index cache is outdated, rebuilding"),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => eprintln!("Failed to load content index cache: {}", e),
+        }
+
+        let index = Self::build(files, fingerprint);
+        if let Err(e) = index.save(cache_path) {
+            eprintln!("Failed to save content index cache: {}", e);
+        }
+        index
+    }
+
+    fn build(files: Vec<(IndexedDoc, FileRef)>, fingerprint: u64) -> Self {
+        let threads = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(4);
+        let chunk_size = files.len().div_ceil(threads).max(1);
+
+        // Chunks are merged in order, so every posting list stays sorted
+        let partials: Vec<HashMap<String, Vec<u32>>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = files
+                .chunks(chunk_size)
+                .enumerate()
+                .map(|(chunk_index, chunk)| {
+                    scope.spawn(move || {
+                        let mut postings: HashMap<String, Vec<u32>> = HashMap::new();
+                        for (i, (doc, file_table)) in chunk.iter().enumerate() {
+                            let doc_id = (chunk_index * chunk_size + i) as u32;
+                            let Ok(data) = file_table.extract_data() else {
+                                continue;
+                            };
+                            let Some(text) = extract_text(&doc.path, &data) else {
+                                continue;
+                            };
+                            let words: HashSet<String> = tokenize(&text).collect();
+                            for word in words {
+                                postings.entry(word).or_default().push(doc_id);
+                            }
+                        }
+                        postings
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap_or_default())
+                .collect()
+        });
+
+        let mut postings: HashMap<String, Vec<u32>> = HashMap::new();
+        for partial in partials {
+            for (word, ids) in partial {
+                postings.entry(word).or_default().extend(ids);
+            }
+        }
+
+        ContentIndex {
+            fingerprint,
+            docs: files.into_iter().map(|(doc, _)| doc).collect(),
+            postings,
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let reader = ZlibDecoder::new(BufReader::new(File::open(path)?));
+        serde_json::from_reader(reader).map_err(io::Error::other)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let writer = BufWriter::new(File::create(path)?);
+        let mut encoder = ZlibEncoder::new(writer, Compression::fast());
+        serde_json::to_writer(&mut encoder, self).map_err(io::Error::other)?;
+        encoder.finish()?;
+        Ok(())
+    }
+
+    pub fn doc_count(&self) -> usize {
+        self.docs.len()
+    }
+
+    pub fn word_count(&self) -> usize {
+        self.postings.len()
+    }
+
+    /// Documents containing every word of `query`
+    pub fn search(&self, query: &str) -> Vec<&IndexedDoc> {
+        let words: HashSet<String> = tokenize(query).collect();
+        if words.is_empty() {
+            return Vec::new();
+        }
+
+        let mut lists = Vec::with_capacity(words.len());
+        for word in &words {
+            match self.postings.get(word) {
+                Some(ids) => lists.push(ids),
+                None => return Vec::new(),
+            }
+        }
+        lists.sort_by_key(|ids| ids.len());
+
+        let mut ids = lists[0].clone();
+        for other in &lists[1..] {
+            ids.retain(|id| other.binary_search(id).is_ok());
+        }
+
+        ids.iter().map(|&id| &self.docs[id as usize]).collect()
+    }
+}
+
+/// Whether `file_name` is one of the indexed text formats
+pub fn is_indexed_file(file_name: &str) -> bool {
+    let ext = ipf::file_extension(file_name);
+    matches!(ext.as_str(), "xml" | "lua" | "skn" | "effect" | "ies") || ext.starts_with("3d")
+}
+
+/// Searchable text of a file: IES tables become one tab separated line per row
+pub fn extract_text(file_name: &str, data: &[u8]) -> Option<String> {
+    if file_name.to_lowercase().ends_with(".ies") {
+        let ies = IESRoot::from_bytes(data).ok()?;
+        let lines: Vec<String> = ies
+            .data
+            .iter()
+            .map(|row| {
+                std::iter::once(row.row_text.text_data.as_str())
+                    .chain(row.texts.iter().map(|t| t.text_data.as_str()))
+                    .collect::<Vec<_>>()
+                    .join("\t")
+            })
+            .collect();
+        return Some(lines.join("\n"));
+    }
+
+    Some(String::from_utf8_lossy(data).into_owned())
+}
+
+/// Lowercased words of 2 to 64 characters, plus the parts of `_` separated identifiers
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| (2..=64).contains(&word.chars().count()))
+        .flat_map(|word| {
+            let parts: Vec<&str> = if word.contains('_') {
+                word.split('_').filter(|p| p.chars().count() >= 2).collect()
+            } else {
+                Vec::new()
+            };
+            std::iter::once(word).chain(parts)
+        })
+        .map(|word| word.to_lowercase())
+}
+
+/// Lines of `text` containing `query` (or, failing that, all of its words)
+pub fn match_context(text: &str, query: &str, max_lines: usize) -> Vec<ContextLine> {
+    const MAX_LINE_CHARS: usize = 200;
+
+    let phrase = query.trim().to_lowercase();
+    let words: Vec<String> = query.split_whitespace().map(|w| w.to_lowercase()).collect();
+
+    let mut phrase_matches = Vec::new();
+    let mut word_matches = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let lower = line.to_lowercase();
+        let target = if lower.contains(&phrase) {
+            &mut phrase_matches
+        } else if words.iter().all(|w| lower.contains(w)) {
+            &mut word_matches
+        } else {
+            continue;
+        };
+        if target.len() < max_lines {
+            target.push(ContextLine {
+                line: i + 1,
+                text: line.trim().chars().take(MAX_LINE_CHARS).collect(),
+            });
+        }
+    }
+
+    if phrase_matches.is_empty() {
+        word_matches
+    } else {
+        phrase_matches
+    }
+}
+
+/// Every indexed document of the tree, versions counted per case-insensitive path
+fn collect_documents<'a>(tree: &'a Folder, out: &mut Vec<(IndexedDoc, FileRef<'a>)>) {
+    let mut versions: HashMap<String, usize> = HashMap::new();
+    tree.for_each_file(|full_path, file| {
+        if !is_indexed_file(file.name()) {
+            return;
+        }
+        let version = versions.entry(full_path.to_lowercase()).or_insert(0);
+        out.push((
+            IndexedDoc {
+                path: full_path.to_string(),
+                version: *version,
+            },
+            file,
+        ));
+        *version += 1;
+    });
+}
+
+/// FNV-1a hash over the identity of every indexed file
+fn fingerprint(files: &[(IndexedDoc, FileRef)]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut feed = |bytes: &[u8]| {
+        for &b in bytes {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    };
+
+    for (doc, file_table) in files {
+        feed(doc.path.as_bytes());
+        feed(file_table.container_name().as_bytes());
+        feed(&file_table.crc32.to_le_bytes());
+        feed(&file_table.file_size_uncompressed.to_le_bytes());
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_splits_identifiers() {
+        let words: Vec<String> = tokenize("SCR_Boss_Wastrel(self, 1)").collect();
+        assert_eq!(
+            words,
+            vec!["scr_boss_wastrel", "scr", "boss", "wastrel", "self"]
+        );
+    }
+
+    #[test]
+    fn test_match_context_prefers_phrase() {
+        let text = "local a = 1\nCreateMonster('Boss_Wastrel')\nboss wastrel";
+        let lines = match_context(text, "Boss_Wastrel", 5);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].line, 2);
+    }
+
+    #[test]
+    fn test_is_indexed_file() {
+        assert!(is_indexed_file("barrack.3dworld"));
+        assert!(is_indexed_file("item.IES"));
+        assert!(!is_indexed_file("enclass.tga"));
+    }
+}
@@ -0,0 +1,585 @@
+//! DDS (DirectDraw Surface) texture decoding.
+//!
+//! Decodes the block compressed formats the game's textures use (BC1 to BC5,
+//! as DXT/ATI FourCCs or DX10 headers) and mask described uncompressed layouts
+//! into RGBA8 `stb::Image`s. Surfaces are stored face by face, each face with
+//! its full mip chain.
+
+use binrw::{BinReaderExt, binread};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Cursor};
+
+use crate::stb::Image;
+
+const HEADER_LEN: usize = 128;
+const DX10_HEADER_LEN: usize = 20;
+
+const DDSD_MIPMAPCOUNT: u32 = 0x2_0000;
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_LUMINANCE: u32 = 0x2_0000;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_CUBEMAP_FACES: u32 = 0xFC00;
+const RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+const FOURCC_DX10: u32 = u32::from_le_bytes(*b"DX10");
+
+#[binread]
+#[derive(Default, Debug, Serialize, Deserialize)]
+#[br(little)]
+pub struct DdsPixelFormat {
+    pub size: u32,
+    pub flags: u32,
+    pub four_cc: u32,
+    pub rgb_bit_count: u32,
+    pub r_mask: u32,
+    pub g_mask: u32,
+    pub b_mask: u32,
+    pub a_mask: u32,
+}
+
+#[binread]
+#[derive(Default, Debug, Serialize, Deserialize)]
+#[br(little)]
+pub struct DdsHeaderDx10 {
+    pub dxgi_format: u32,
+    pub resource_dimension: u32,
+    pub misc_flag: u32,
+    pub array_size: u32,
+    pub misc_flags2: u32,
+}
+
+#[binread]
+#[derive(Default, Debug, Serialize, Deserialize)]
+#[br(little, magic = b"DDS ")]
+pub struct DdsHeader {
+    pub size: u32,
+    pub flags: u32,
+    pub height: u32,
+    pub width: u32,
+    pub pitch_or_linear_size: u32,
+    pub depth: u32,
+    pub mip_map_count: u32,
+    pub reserved1: [u32; 11],
+    pub pixel_format: DdsPixelFormat,
+    pub caps: u32,
+    pub caps2: u32,
+    pub caps3: u32,
+    pub caps4: u32,
+    pub reserved2: u32,
+    #[br(if(pixel_format.flags & DDPF_FOURCC != 0 && pixel_format.four_cc == FOURCC_DX10))]
+    pub dx10: Option<DdsHeaderDx10>,
+}
+
+/// Pixel layout of the surfaces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum DdsFormat {
+    /// DXT1, 1-bit alpha
+    Bc1,
+    /// DXT2/DXT3, explicit 4-bit alpha
+    Bc2,
+    /// DXT4/DXT5, interpolated alpha
+    Bc3,
+    /// ATI1, single channel
+    Bc4,
+    /// ATI2, two channels (normal maps)
+    Bc5,
+    /// Channels picked out of 8 to 32-bit pixels by mask, `[r, g, b, a]`
+    Uncompressed { bit_count: u32, masks: [u32; 4] },
+}
+
+impl DdsFormat {
+    /// Bytes per 4x4 block, `None` for uncompressed formats
+    fn block_size(&self) -> Option<usize> {
+        match self {
+            DdsFormat::Bc1 | DdsFormat::Bc4 => Some(8),
+            DdsFormat::Bc2 | DdsFormat::Bc3 | DdsFormat::Bc5 => Some(16),
+            DdsFormat::Uncompressed { .. } => None,
+        }
+    }
+
+    /// Bytes in one surface, `None` if it does not fit in `usize`
+    fn surface_size(&self, width: usize, height: usize) -> Option<usize> {
+        match (self.block_size(), self) {
+            (Some(block), _) => width
+                .div_ceil(4)
+                .checked_mul(height.div_ceil(4))?
+                .checked_mul(block),
+            (None, DdsFormat::Uncompressed { bit_count, .. }) => width
+                .checked_mul(height)?
+                .checked_mul(*bit_count as usize / 8),
+            (None, _) => Some(0),
+        }
+    }
+}
+
+impl DdsHeader {
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        Cursor::new(bytes)
+            .read_le()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("binrw error: {}", e)))
+    }
+
+    pub fn format(&self) -> io::Result<DdsFormat> {
+        let unsupported = |what: String| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("Unsupported DDS format: {}", what),
+            )
+        };
+
+        if let Some(dx10) = &self.dx10 {
+            const BGRA: [u32; 4] = [0xFF_0000, 0xFF00, 0xFF, 0xFF00_0000];
+            return match dx10.dxgi_format {
+                70..=72 => Ok(DdsFormat::Bc1),
+                73..=75 => Ok(DdsFormat::Bc2),
+                76..=78 => Ok(DdsFormat::Bc3),
+                79..=81 => Ok(DdsFormat::Bc4),
+                82..=84 => Ok(DdsFormat::Bc5),
+                27..=32 => Ok(DdsFormat::Uncompressed {
+                    bit_count: 32,
+                    masks: [0xFF, 0xFF00, 0xFF_0000, 0xFF00_0000],
+                }),
+                87 | 90 | 91 => Ok(DdsFormat::Uncompressed {
+                    bit_count: 32,
+                    masks: BGRA,
+                }),
+                88 | 92 | 93 => Ok(DdsFormat::Uncompressed {
+                    bit_count: 32,
+                    masks: [BGRA[0], BGRA[1], BGRA[2], 0],
+                }),
+                other => Err(unsupported(format!("DXGI format {}", other))),
+            };
+        }
+
+        let pf = &self.pixel_format;
+        if pf.flags & DDPF_FOURCC != 0 {
+            return match &pf.four_cc.to_le_bytes() {
+                b"DXT1" => Ok(DdsFormat::Bc1),
+                b"DXT2" | b"DXT3" => Ok(DdsFormat::Bc2),
+                b"DXT4" | b"DXT5" => Ok(DdsFormat::Bc3),
+                b"ATI1" | b"BC4U" => Ok(DdsFormat::Bc4),
+                b"ATI2" | b"BC5U" => Ok(DdsFormat::Bc5),
+                other => Err(unsupported(format!(
+                    "FourCC {}",
+                    String::from_utf8_lossy(other)
+                ))),
+            };
+        }
+
+        if !matches!(pf.rgb_bit_count, 8 | 16 | 24 | 32) {
+            return Err(unsupported(format!("{} bits per pixel", pf.rgb_bit_count)));
+        }
+        let a_mask = if pf.flags & DDPF_ALPHAPIXELS != 0 || pf.r_mask == 0 {
+            pf.a_mask
+        } else {
+            0
+        };
+        // Luminance spreads its single channel over red, green and blue
+        let masks = if pf.flags & DDPF_LUMINANCE != 0 {
+            [pf.r_mask, pf.r_mask, pf.r_mask, a_mask]
+        } else {
+            [pf.r_mask, pf.g_mask, pf.b_mask, a_mask]
+        };
+        Ok(DdsFormat::Uncompressed {
+            bit_count: pf.rgb_bit_count,
+            masks,
+        })
+    }
+
+    /// Capped at the 32 levels a 32-bit dimension can have
+    pub fn mip_count(&self) -> usize {
+        if self.flags & DDSD_MIPMAPCOUNT != 0 {
+            self.mip_map_count.clamp(1, 32) as usize
+        } else {
+            1
+        }
+    }
+
+    /// Cubemaps store six faces (fewer when some are left out), arrays one per layer
+    pub fn face_count(&self) -> usize {
+        if let Some(dx10) = &self.dx10 {
+            let layers = dx10.array_size.max(1) as usize;
+            return if dx10.misc_flag & RESOURCE_MISC_TEXTURECUBE != 0 {
+                layers * 6
+            } else {
+                layers
+            };
+        }
+        if self.caps2 & DDSCAPS2_CUBEMAP != 0 {
+            match (self.caps2 & DDSCAPS2_CUBEMAP_FACES).count_ones() {
+                0 => 6,
+                faces => faces as usize,
+            }
+        } else {
+            1
+        }
+    }
+
+    pub fn is_cubemap(&self) -> bool {
+        match &self.dx10 {
+            Some(dx10) => dx10.misc_flag & RESOURCE_MISC_TEXTURECUBE != 0,
+            None => self.caps2 & DDSCAPS2_CUBEMAP != 0,
+        }
+    }
+
+    fn data_offset(&self) -> usize {
+        match self.dx10 {
+            Some(_) => HEADER_LEN + DX10_HEADER_LEN,
+            None => HEADER_LEN,
+        }
+    }
+
+    fn mip_dimensions(&self, mip: usize) -> (usize, usize) {
+        (
+            (self.width as usize >> mip).max(1),
+            (self.height as usize >> mip).max(1),
+        )
+    }
+}
+
+/// Decode one mip level of one face to RGBA
+pub fn decode(data: &[u8], mip: usize, face: usize) -> io::Result<Image> {
+    let header = DdsHeader::from_bytes(data)?;
+    let format = header.format()?;
+    let (mips, faces) = (header.mip_count(), header.face_count());
+    if mip >= mips || face >= faces {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "DDS has {} mips and {} faces, asked for mip {} of face {}",
+                mips, faces, mip, face
+            ),
+        ));
+    }
+
+    let overflow = || io::Error::new(io::ErrorKind::InvalidData, "DDS dimensions overflow");
+    let mip_size = |level| {
+        let (width, height) = header.mip_dimensions(level);
+        format.surface_size(width, height).ok_or_else(overflow)
+    };
+    let sum_sizes = |levels: std::ops::Range<usize>| {
+        levels.map(mip_size).try_fold(0usize, |total, size| {
+            total.checked_add(size?).ok_or_else(overflow)
+        })
+    };
+    let (chain, before) = (sum_sizes(0..mips)?, sum_sizes(0..mip)?);
+    let start = face
+        .checked_mul(chain)
+        .and_then(|faces| faces.checked_add(header.data_offset() + before))
+        .ok_or_else(overflow)?;
+    let end = start.checked_add(mip_size(mip)?).ok_or_else(overflow)?;
+    let surface = data
+        .get(start..end)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "DDS surface truncated"))?;
+
+    let (width, height) = header.mip_dimensions(mip);
+    let pixels = match format {
+        DdsFormat::Uncompressed { bit_count, masks } => {
+            decode_uncompressed(surface, width, height, bit_count, masks)?
+        }
+        _ => decode_blocks(surface, width, height, format)?,
+    };
+    Ok(Image {
+        width: width as i32,
+        height: height as i32,
+        channels: 4,
+        data: pixels,
+    })
+}
+
+/// Top mip for display, cubemap faces side by side in file order (+X -X +Y -Y +Z -Z)
+pub fn decode_preview(data: &[u8]) -> io::Result<Image> {
+    let header = DdsHeader::from_bytes(data)?;
+    if !header.is_cubemap() {
+        return decode(data, 0, 0);
+    }
+
+    let faces = (0..header.face_count())
+        .map(|face| decode(data, 0, face))
+        .collect::<io::Result<Vec<_>>>()?;
+    let (face_width, height) = (faces[0].width as usize, faces[0].height as usize);
+    let width = face_width * faces.len();
+    let mut strip = vec![0u8; rgba_len(width, height)?];
+    for (i, face) in faces.iter().enumerate() {
+        for y in 0..height {
+            let src = &face.data[y * face_width * 4..(y + 1) * face_width * 4];
+            let dst = (y * width + i * face_width) * 4;
+            strip[dst..dst + face_width * 4].copy_from_slice(src);
+        }
+    }
+    Ok(Image {
+        width: width as i32,
+        height: height as i32,
+        channels: 4,
+        data: strip,
+    })
+}
+
+/// Bytes in a `width` x `height` RGBA8 image
+fn rgba_len(width: usize, height: usize) -> io::Result<usize> {
+    width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(4))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "DDS dimensions overflow"))
+}
+
+fn decode_uncompressed(
+    surface: &[u8],
+    width: usize,
+    height: usize,
+    bit_count: u32,
+    masks: [u32; 4],
+) -> io::Result<Vec<u8>> {
+    let bytes = bit_count as usize / 8;
+    let mut pixels = Vec::with_capacity(rgba_len(width, height)?);
+    for pixel in surface.chunks_exact(bytes).take(width * height) {
+        let mut raw = [0u8; 4];
+        raw[..bytes].copy_from_slice(pixel);
+        let value = u32::from_le_bytes(raw);
+        for (channel, &mask) in masks.iter().enumerate() {
+            pixels.push(match mask {
+                // Missing alpha is opaque, missing colors black
+                0 if channel == 3 => 255,
+                0 => 0,
+                mask => {
+                    let max = mask >> mask.trailing_zeros();
+                    let v = (value & mask) >> mask.trailing_zeros();
+                    (v as u64 * 255 / max as u64) as u8
+                }
+            });
+        }
+    }
+    Ok(pixels)
+}
+
+fn decode_blocks(
+    surface: &[u8],
+    width: usize,
+    height: usize,
+    format: DdsFormat,
+) -> io::Result<Vec<u8>> {
+    let block_size = format.block_size().unwrap_or(16);
+    let blocks_wide = width.div_ceil(4);
+    let mut pixels = vec![0u8; rgba_len(width, height)?];
+
+    for (i, block) in surface.chunks_exact(block_size).enumerate() {
+        let texels = match format {
+            DdsFormat::Bc1 => bc1_block(block, true),
+            DdsFormat::Bc2 => {
+                let mut texels = bc1_block(&block[8..], false);
+                for (j, texel) in texels.iter_mut().enumerate() {
+                    let nibble = (block[j / 2] >> ((j % 2) * 4)) & 0xF;
+                    texel[3] = nibble * 17;
+                }
+                texels
+            }
+            DdsFormat::Bc3 => {
+                let mut texels = bc1_block(&block[8..], false);
+                for (texel, alpha) in texels.iter_mut().zip(bc4_block(&block[..8])) {
+                    texel[3] = alpha;
+                }
+                texels
+            }
+            DdsFormat::Bc4 => bc4_block(block).map(|v| [v, v, v, 255]),
+            DdsFormat::Bc5 => {
+                let red = bc4_block(&block[..8]);
+                let green = bc4_block(&block[8..]);
+                // Rebuild Z so tangent space normal maps look like normal maps
+                std::array::from_fn(|j| {
+                    let x = red[j] as f32 / 127.5 - 1.0;
+                    let y = green[j] as f32 / 127.5 - 1.0;
+                    let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+                    [
+                        red[j],
+                        green[j],
+                        ((z * 0.5 + 0.5) * 255.0).round() as u8,
+                        255,
+                    ]
+                })
+            }
+            DdsFormat::Uncompressed { .. } => unreachable!("not block compressed"),
+        };
+
+        let (bx, by) = ((i % blocks_wide) * 4, (i / blocks_wide) * 4);
+        for (j, texel) in texels.iter().enumerate() {
+            let (x, y) = (bx + j % 4, by + j / 4);
+            // Blocks overhang the edges of images not a multiple of 4
+            if x < width && y < height {
+                let at = (y * width + x) * 4;
+                pixels[at..at + 4].copy_from_slice(texel);
+            }
+        }
+    }
+    Ok(pixels)
+}
+
+fn rgb565(color: u16) -> [u8; 3] {
+    let r = ((color >> 11) & 0x1F) as u8;
+    let g = ((color >> 5) & 0x3F) as u8;
+    let b = (color & 0x1F) as u8;
+    [
+        (r << 3) | (r >> 2),
+        (g << 2) | (g >> 4),
+        (b << 3) | (b >> 2),
+    ]
+}
+
+/// Color block, shared by BC1 to BC3. Only BC1 switches to three colors plus
+/// transparent black when the first endpoint is not the larger one.
+fn bc1_block(block: &[u8], punch_through: bool) -> [[u8; 4]; 16] {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let (a, b) = (rgb565(c0), rgb565(c1));
+    let mix = |wa: u16, wb: u16| -> [u8; 4] {
+        let total = wa + wb;
+        let channel = |i: usize| ((a[i] as u16 * wa + b[i] as u16 * wb) / total) as u8;
+        [channel(0), channel(1), channel(2), 255]
+    };
+    let palette = if c0 > c1 || !punch_through {
+        [mix(1, 0), mix(0, 1), mix(2, 1), mix(1, 2)]
+    } else {
+        [mix(1, 0), mix(0, 1), mix(1, 1), [0, 0, 0, 0]]
+    };
+
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    std::array::from_fn(|j| palette[((indices >> (j * 2)) & 0x3) as usize])
+}
+
+/// Single channel block, used for BC3 alpha and the BC4/BC5 channels
+fn bc4_block(block: &[u8]) -> [u8; 16] {
+    let (v0, v1) = (block[0] as u32, block[1] as u32);
+    let palette: [u8; 8] = std::array::from_fn(|i| {
+        let i = i as u32;
+        match i {
+            0 => v0 as u8,
+            1 => v1 as u8,
+            _ if v0 > v1 => ((v0 * (8 - i) + v1 * (i - 1)) / 7) as u8,
+            2..=5 => ((v0 * (6 - i) + v1 * (i - 1)) / 5) as u8,
+            6 => 0,
+            _ => 255,
+        }
+    });
+
+    let mut bits = [0u8; 8];
+    bits[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(bits);
+    std::array::from_fn(|j| palette[((indices >> (j * 3)) & 0x7) as usize])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal DDS header for `four_cc` or, when `None`, 32-bit BGRA pixels
+    fn header(
+        width: u32,
+        height: u32,
+        mips: u32,
+        four_cc: Option<&[u8; 4]>,
+        caps2: u32,
+    ) -> Vec<u8> {
+        let mut out = b"DDS ".to_vec();
+        let mut push = |v: u32| out.extend_from_slice(&v.to_le_bytes());
+        push(124);
+        push(0x1007 | if mips > 1 { DDSD_MIPMAPCOUNT } else { 0 });
+        push(height);
+        push(width);
+        push(0);
+        push(0);
+        push(mips);
+        for _ in 0..11 {
+            push(0);
+        }
+        push(32);
+        match four_cc {
+            Some(four_cc) => {
+                push(DDPF_FOURCC);
+                push(u32::from_le_bytes(*four_cc));
+                for _ in 0..5 {
+                    push(0);
+                }
+            }
+            None => {
+                push(0x40 | DDPF_ALPHAPIXELS);
+                push(0);
+                push(32);
+                push(0xFF_0000);
+                push(0xFF00);
+                push(0xFF);
+                push(0xFF00_0000);
+            }
+        }
+        push(0x1000);
+        push(caps2);
+        push(0);
+        push(0);
+        push(0);
+        out
+    }
+
+    #[test]
+    fn test_decode_bc1() {
+        let mut dds = header(4, 4, 1, Some(b"DXT1"), 0);
+        // Pure red and pure blue endpoints, rows use index 0, 1, 2 and 3
+        dds.extend_from_slice(&0xF800u16.to_le_bytes());
+        dds.extend_from_slice(&0x001Fu16.to_le_bytes());
+        dds.extend_from_slice(&[0x00, 0x55, 0xAA, 0xFF]);
+
+        let img = decode(&dds, 0, 0).unwrap();
+        assert_eq!((img.width, img.height, img.channels), (4, 4, 4));
+        let pixel = |x: usize, y: usize| &img.data[(y * 4 + x) * 4..(y * 4 + x) * 4 + 4];
+        assert_eq!(pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(pixel(3, 1), [0, 0, 255, 255]);
+        assert_eq!(pixel(1, 2), [170, 0, 85, 255]);
+        assert_eq!(pixel(2, 3), [85, 0, 170, 255]);
+    }
+
+    #[test]
+    fn test_decode_bc3_alpha() {
+        let mut dds = header(4, 4, 1, Some(b"DXT5"), 0);
+        // Alpha endpoints 255 and 0, first texel index 0, second index 1
+        dds.extend_from_slice(&[255, 0, 0b0000_1000, 0, 0, 0, 0, 0]);
+        dds.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0]);
+
+        let img = decode(&dds, 0, 0).unwrap();
+        assert_eq!(img.data[0..4], [255, 255, 255, 255]);
+        assert_eq!(img.data[4..8], [255, 255, 255, 0]);
+    }
+
+    #[test]
+    fn test_mips_and_cubemap_faces() {
+        // 2x2 BGRA cubemap with two mips per face, each face a flat color
+        let mut dds = header(2, 2, 2, None, DDSCAPS2_CUBEMAP | DDSCAPS2_CUBEMAP_FACES);
+        for face in 0..6u8 {
+            for _ in 0..4 + 1 {
+                dds.extend_from_slice(&[face, 0, 10, 255]);
+            }
+        }
+        let header = DdsHeader::from_bytes(&dds).unwrap();
+        assert_eq!((header.mip_count(), header.face_count()), (2, 6));
+
+        let mip = decode(&dds, 1, 3).unwrap();
+        assert_eq!((mip.width, mip.height), (1, 1));
+        assert_eq!(mip.data, [10, 0, 3, 255]);
+        assert!(decode(&dds, 2, 0).is_err());
+
+        let strip = decode_preview(&dds).unwrap();
+        assert_eq!((strip.width, strip.height), (12, 2));
+        assert_eq!(strip.data[5 * 2 * 4..5 * 2 * 4 + 4], [10, 0, 5, 255]);
+    }
+
+    #[test]
+    fn test_truncated_surface() {
+        let dds = header(8, 8, 1, Some(b"DXT1"), 0);
+        assert!(decode(&dds, 0, 0).is_err());
+    }
+
+    #[test]
+    fn test_overflowing_dimensions() {
+        let dds = header(u32::MAX, u32::MAX, 1, None, 0);
+        let kind = |mip| decode(&dds, mip, 0).err().map(|e| e.kind());
+        assert_eq!(kind(0), Some(io::ErrorKind::InvalidData));
+        assert_eq!(kind(1), Some(io::ErrorKind::InvalidInput));
+    }
+}
@@ -0,0 +1,982 @@
+//! Binary glTF 2.0 (.glb) export of a `mesh::Scene`.
+//!
+//! Scenes already use glTF's right-handed, Y-up axes, so vertex data is copied
+//! almost verbatim: only UVs are flipped back to glTF's top-left origin and
+//! tangents mirrored to match the mirrored positions. Every submesh becomes a
+//! primitive, and every scene material it uses a glTF material with the
+//! diffuse map embedded as PNG when the caller can supply one, and the full
+//! XAC material kept in its extras. Skinned submeshes share a
+//! single skin spanning the skeleton nodes, morph targets are written dense
+//! with their names in the mesh extras, as Blender and three.js expect.
+//! Scene animations become glTF animations driving the skeleton nodes.
+
+use std::collections::HashMap;
+
+use serde_json::{Value, json};
+
+use crate::animation::{AnimationClip, Keyframe};
+use crate::mesh::{
+    IDENTITY_MATRIX, MapType, Material, Matrix4, Model, MorphTarget, Scene, SceneNode, SubMesh,
+    invert_matrix,
+};
+
+const GLB_MAGIC: u32 = 0x4654_6C67; // "glTF"
+const GLB_VERSION: u32 = 2;
+const CHUNK_JSON: u32 = 0x4E4F_534A; // "JSON"
+const CHUNK_BIN: u32 = 0x004E_4942; // "BIN\0"
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const UNSIGNED_BYTE: u32 = 5121;
+const UNSIGNED_SHORT: u32 = 5123;
+const UNSIGNED_INT: u32 = 5125;
+const FLOAT: u32 = 5126;
+const TRIANGLES: u32 = 4;
+const LINEAR: u32 = 9729;
+const LINEAR_MIPMAP_LINEAR: u32 = 9987;
+const REPEAT: u32 = 10497;
+
+/// Accumulates the binary chunk and the glTF JSON arrays that index into it
+#[derive(Default)]
+struct GlbBuilder<'a> {
+    scene_materials: &'a [Material],
+    bin: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+    meshes: Vec<Value>,
+    nodes: Vec<Value>,
+    materials: Vec<Value>,
+    textures: Vec<Value>,
+    images: Vec<Value>,
+    // scene material index -> material index
+    material_lookup: HashMap<usize, usize>,
+    // texture path -> texture index, `None` when it could not be loaded
+    texture_lookup: HashMap<String, Option<usize>>,
+    // skeleton node index -> position in the skin's joint list, which skips
+    // the indices the skeleton has no node for
+    joint_slots: HashMap<u32, u16>,
+    // skeleton node index -> (glTF node, bind pose world matrix)
+    joint_nodes: HashMap<u32, (usize, Matrix4)>,
+    // glTF nodes whose mesh carries JOINTS_0/WEIGHTS_0
+    skinned_nodes: Vec<usize>,
+}
+
+impl GlbBuilder<'_> {
+    /// Append 4-byte aligned data as a new buffer view
+    fn push_view(&mut self, data: &[u8], target: Option<u32>) -> usize {
+        while !self.bin.len().is_multiple_of(4) {
+            self.bin.push(0);
+        }
+        let mut view = json!({
+            "buffer": 0,
+            "byteOffset": self.bin.len(),
+            "byteLength": data.len(),
+        });
+        if let Some(target) = target {
+            view["target"] = json!(target);
+        }
+        self.bin.extend_from_slice(data);
+        self.buffer_views.push(view);
+        self.buffer_views.len() - 1
+    }
+
+    fn push_accessor(&mut self, accessor: Value) -> usize {
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    /// Float vertex attribute with `N` components per vertex
+    fn push_floats<const N: usize>(&mut self, values: &[[f32; N]], with_bounds: bool) -> usize {
+        self.push_float_accessor(values, Some(ARRAY_BUFFER), with_bounds)
+    }
+
+    /// Animation sampler data. Inputs are keyframe times, which require min
+    /// and max; views used by animations take no target.
+    fn push_keyframes<const N: usize>(&mut self, values: &[[f32; N]]) -> usize {
+        self.push_float_accessor(values, None, N == 1)
+    }
+
+    fn push_float_accessor<const N: usize>(
+        &mut self,
+        values: &[[f32; N]],
+        target: Option<u32>,
+        with_bounds: bool,
+    ) -> usize {
+        let bytes: Vec<u8> = values
+            .iter()
+            .flatten()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let view = self.push_view(&bytes, target);
+        let kind = match N {
+            1 => "SCALAR",
+            2 => "VEC2",
+            3 => "VEC3",
+            _ => "VEC4",
+        };
+        let mut accessor = json!({
+            "bufferView": view,
+            "componentType": FLOAT,
+            "count": values.len(),
+            "type": kind,
+        });
+        // POSITION and animation inputs require min and max
+        if with_bounds {
+            let mut min = [f32::MAX; N];
+            let mut max = [f32::MIN; N];
+            for value in values {
+                for i in 0..N {
+                    min[i] = min[i].min(value[i]);
+                    max[i] = max[i].max(value[i]);
+                }
+            }
+            accessor["min"] = json!(min.to_vec());
+            accessor["max"] = json!(max.to_vec());
+        }
+        self.push_accessor(accessor)
+    }
+
+    /// Normalized RGBA8 vertex colors
+    fn push_colors32(&mut self, colors: &[u32]) -> usize {
+        let bytes: Vec<u8> = colors.iter().flat_map(|c| c.to_le_bytes()).collect();
+        let view = self.push_view(&bytes, Some(ARRAY_BUFFER));
+        self.push_accessor(json!({
+            "bufferView": view,
+            "componentType": UNSIGNED_BYTE,
+            "normalized": true,
+            "count": colors.len(),
+            "type": "VEC4",
+        }))
+    }
+
+    /// Skeleton node indices remapped to skin joint slots
+    fn push_joints(&mut self, joints: &[[u16; 4]]) -> usize {
+        let bytes: Vec<u8> = joints
+            .iter()
+            .flatten()
+            .map(|&j| self.joint_slots.get(&(j as u32)).copied().unwrap_or(0))
+            .flat_map(|j| j.to_le_bytes())
+            .collect();
+        let view = self.push_view(&bytes, Some(ARRAY_BUFFER));
+        self.push_accessor(json!({
+            "bufferView": view,
+            "componentType": UNSIGNED_SHORT,
+            "count": joints.len(),
+            "type": "VEC4",
+        }))
+    }
+
+    fn push_indices(&mut self, indices: &[u32]) -> usize {
+        let bytes: Vec<u8> = indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+        let view = self.push_view(&bytes, Some(ELEMENT_ARRAY_BUFFER));
+        self.push_accessor(json!({
+            "bufferView": view,
+            "componentType": UNSIGNED_INT,
+            "count": indices.len(),
+            "type": "SCALAR",
+        }))
+    }
+
+    /// Embedded PNG of a texture path, shared by every material using it
+    fn texture(
+        &mut self,
+        texture_path: &str,
+        load_texture: &mut dyn FnMut(&str) -> Option<Vec<u8>>,
+    ) -> Option<usize> {
+        if let Some(&index) = self.texture_lookup.get(texture_path) {
+            return index;
+        }
+
+        let index = load_texture(texture_path).map(|png| {
+            let name = texture_path.rsplit('/').next().unwrap_or(texture_path);
+            let view = self.push_view(&png, None);
+            self.images.push(json!({
+                "name": name,
+                "bufferView": view,
+                "mimeType": "image/png",
+            }));
+            self.textures.push(json!({
+                "sampler": 0,
+                "source": self.images.len() - 1,
+            }));
+            self.textures.len() - 1
+        });
+        self.texture_lookup.insert(texture_path.to_string(), index);
+        index
+    }
+
+    /// glTF material for a scene material, shared by every submesh using it
+    fn material(
+        &mut self,
+        scene_index: usize,
+        load_texture: &mut dyn FnMut(&str) -> Option<Vec<u8>>,
+    ) -> Option<usize> {
+        if let Some(&index) = self.material_lookup.get(&scene_index) {
+            return Some(index);
+        }
+        let material = self.scene_materials.get(scene_index)?;
+
+        let texture = material
+            .map(MapType::Diffuse)
+            .map(|map| map.resolved.as_ref().map_or(&map.texture, |r| &r.path))
+            .and_then(|path| self.texture(path, load_texture));
+        // A diffuse map replaces the diffuse color
+        let d = &material.diffuse;
+        let base = match texture {
+            Some(_) => [1.0, 1.0, 1.0, material.opacity],
+            None => [d.r, d.g, d.b, material.opacity],
+        };
+        let mut pbr = json!({
+            "baseColorFactor": base,
+            "metallicFactor": 0.0,
+            "roughnessFactor": 1.0,
+        });
+        if let Some(texture) = texture {
+            pbr["baseColorTexture"] = json!({ "index": texture });
+        }
+
+        let mut gltf_material = json!({
+            "name": material.name,
+            "pbrMetallicRoughness": pbr,
+            "extras": material,
+        });
+        let e = &material.emissive;
+        if e.r > 0.0 || e.g > 0.0 || e.b > 0.0 {
+            gltf_material["emissiveFactor"] = json!([e.r, e.g, e.b]);
+        }
+        if material.double_sided {
+            gltf_material["doubleSided"] = json!(true);
+        }
+        if let Some(cutoff) = material.alpha_cutoff {
+            gltf_material["alphaMode"] = json!("MASK");
+            gltf_material["alphaCutoff"] = json!(cutoff);
+        } else if material.alpha_blend {
+            gltf_material["alphaMode"] = json!("BLEND");
+        }
+
+        self.materials.push(gltf_material);
+        let index = self.materials.len() - 1;
+        self.material_lookup.insert(scene_index, index);
+        Some(index)
+    }
+
+    fn primitive(
+        &mut self,
+        submesh: &SubMesh,
+        load_texture: &mut dyn FnMut(&str) -> Option<Vec<u8>>,
+    ) -> Option<Value> {
+        let count = submesh.positions.len();
+        if count == 0 || submesh.indices.is_empty() {
+            return None;
+        }
+
+        let positions: Vec<[f32; 3]> = submesh.positions.iter().map(|p| [p.x, p.y, p.z]).collect();
+        let mut attributes = json!({ "POSITION": self.push_floats(&positions, true) });
+
+        if submesh.normals.len() == count {
+            let normals: Vec<[f32; 3]> = submesh.normals.iter().map(|n| [n.x, n.y, n.z]).collect();
+            attributes["NORMAL"] = json!(self.push_floats(&normals, false));
+        }
+        if submesh.tangents.len() == count {
+            // Mirror like the positions, which also flips the handedness
+            let tangents: Vec<[f32; 4]> = submesh
+                .tangents
+                .iter()
+                .map(|t| [-t.x, t.y, t.z, if t.w < 0.0 { 1.0 } else { -1.0 }])
+                .collect();
+            attributes["TANGENT"] = json!(self.push_floats(&tangents, false));
+        }
+        if submesh.uvcoords.len() == count {
+            // SubMesh stores OpenGL style V, glTF has its origin top-left
+            let uvs: Vec<[f32; 2]> = submesh
+                .uvcoords
+                .iter()
+                .map(|uv| [uv.x, 1.0 - uv.y])
+                .collect();
+            attributes["TEXCOORD_0"] = json!(self.push_floats(&uvs, false));
+        }
+        if submesh.colors128.len() == count {
+            let colors: Vec<[f32; 4]> = submesh
+                .colors128
+                .iter()
+                .map(|c| [c.r, c.g, c.b, c.a])
+                .collect();
+            attributes["COLOR_0"] = json!(self.push_floats(&colors, false));
+        } else if submesh.colors32.len() == count {
+            attributes["COLOR_0"] = json!(self.push_colors32(&submesh.colors32));
+        }
+
+        // Without a skeleton there is no skin for the joints to refer to
+        if submesh.joints.len() == count
+            && submesh.weights.len() == count
+            && !self.joint_slots.is_empty()
+        {
+            attributes["JOINTS_0"] = json!(self.push_joints(&submesh.joints));
+            attributes["WEIGHTS_0"] = json!(self.push_floats(&submesh.weights, false));
+        }
+
+        let mut primitive = json!({
+            "attributes": attributes,
+            "indices": self.push_indices(&submesh.indices),
+            "mode": TRIANGLES,
+        });
+        if !submesh.morph_targets.is_empty() {
+            let targets: Vec<Value> = submesh
+                .morph_targets
+                .iter()
+                .map(|morph| self.morph_target(morph, count))
+                .collect();
+            primitive["targets"] = json!(targets);
+        }
+        if let Some(material) = submesh
+            .material_index
+            .and_then(|index| self.material(index, load_texture))
+        {
+            primitive["material"] = json!(material);
+        }
+        Some(primitive)
+    }
+
+    fn morph_target(&mut self, morph: &MorphTarget, count: usize) -> Value {
+        let mut target = json!({});
+        if morph.position_deltas.len() == count {
+            let positions: Vec<[f32; 3]> = morph
+                .position_deltas
+                .iter()
+                .map(|d| [d.x, d.y, d.z])
+                .collect();
+            target["POSITION"] = json!(self.push_floats(&positions, true));
+        }
+        if morph.normal_deltas.len() == count {
+            let normals: Vec<[f32; 3]> = morph
+                .normal_deltas
+                .iter()
+                .map(|d| [d.x, d.y, d.z])
+                .collect();
+            target["NORMAL"] = json!(self.push_floats(&normals, false));
+        }
+        if morph.tangent_deltas.len() == count {
+            // Mirrored like the tangents themselves
+            let tangents: Vec<[f32; 3]> = morph
+                .tangent_deltas
+                .iter()
+                .map(|d| [-d.x, d.y, d.z])
+                .collect();
+            target["TANGENT"] = json!(self.push_floats(&tangents, false));
+        }
+        target
+    }
+
+    fn mesh(
+        &mut self,
+        model: &Model,
+        load_texture: &mut dyn FnMut(&str) -> Option<Vec<u8>>,
+    ) -> Option<usize> {
+        let primitives: Vec<Value> = model
+            .submeshes
+            .iter()
+            .filter_map(|submesh| self.primitive(submesh, load_texture))
+            .collect();
+        if primitives.is_empty() {
+            return None;
+        }
+        let mut mesh = json!({
+            "name": model.name,
+            "primitives": primitives,
+        });
+        // Every submesh carries the mesh's full target list, name it once
+        if let Some(submesh) = model
+            .submeshes
+            .iter()
+            .find(|submesh| !submesh.morph_targets.is_empty())
+        {
+            let names: Vec<&str> = submesh
+                .morph_targets
+                .iter()
+                .map(|morph| morph.name.as_str())
+                .collect();
+            mesh["weights"] = json!(vec![0.0; names.len()]);
+            mesh["extras"] = json!({ "targetNames": names });
+        }
+        self.meshes.push(mesh);
+        Some(self.meshes.len() - 1)
+    }
+
+    fn node(
+        &mut self,
+        scene_node: &SceneNode,
+        load_texture: &mut dyn FnMut(&str) -> Option<Vec<u8>>,
+    ) -> usize {
+        let children: Vec<usize> = scene_node
+            .children
+            .iter()
+            .map(|child| self.node(child, load_texture))
+            .collect();
+
+        let mut node = json!({ "name": scene_node.name });
+        if let Some(p) = &scene_node.position {
+            node["translation"] = json!([p.x, p.y, p.z]);
+        }
+        if let Some(q) = &scene_node.rotation {
+            node["rotation"] = json!([q.x, q.y, q.z, q.w]);
+        }
+        if let Some(s) = &scene_node.scale {
+            node["scale"] = json!([s.x, s.y, s.z]);
+        }
+        if let Some(mesh) = scene_node
+            .model
+            .as_ref()
+            .and_then(|model| self.mesh(model, load_texture))
+        {
+            node["mesh"] = json!(mesh);
+            let skinned = scene_node.model.iter().any(|model| {
+                model
+                    .submeshes
+                    .iter()
+                    .any(|submesh| !submesh.joints.is_empty())
+            });
+            if skinned {
+                self.skinned_nodes.push(self.nodes.len());
+            }
+        }
+        if !children.is_empty() {
+            node["children"] = json!(children);
+        }
+
+        let index = self.nodes.len();
+        if let (Some(joint), Some(world)) = (scene_node.node_index, scene_node.world_matrix) {
+            self.joint_nodes.insert(joint, (index, world));
+        }
+        self.nodes.push(node);
+        index
+    }
+
+    /// One skin over the whole skeleton, its joints ordered like `joint_slots`
+    fn skin(&mut self) -> Option<Value> {
+        if self.skinned_nodes.is_empty() || self.joint_nodes.is_empty() {
+            return None;
+        }
+        let mut skeleton: Vec<(&u32, &(usize, Matrix4))> = self.joint_nodes.iter().collect();
+        skeleton.sort_by_key(|&(&index, _)| index);
+        let mut joints = Vec::with_capacity(skeleton.len());
+        let mut inverse_binds: Vec<u8> = Vec::with_capacity(skeleton.len() * 64);
+        for (_, &(node, world)) in skeleton {
+            let inverse = invert_matrix(&world).unwrap_or(IDENTITY_MATRIX);
+            joints.push(node);
+            inverse_binds.extend(inverse.iter().flat_map(|v| v.to_le_bytes()));
+        }
+
+        let view = self.push_view(&inverse_binds, None);
+        let accessor = self.push_accessor(json!({
+            "bufferView": view,
+            "componentType": FLOAT,
+            "count": joints.len(),
+            "type": "MAT4",
+        }));
+        for &node in &self.skinned_nodes {
+            self.nodes[node]["skin"] = json!(0);
+        }
+        Some(json!({
+            "joints": joints,
+            "inverseBindMatrices": accessor,
+        }))
+    }
+
+    /// Linear sampler over keyframe times and values with `N` components
+    fn sampler<const N: usize>(&mut self, times: Vec<[f32; 1]>, values: &[[f32; N]]) -> Value {
+        json!({
+            "input": self.push_keyframes(&times),
+            "output": self.push_keyframes(values),
+            "interpolation": "LINEAR",
+        })
+    }
+
+    /// Translation, rotation and scale channels for every bound track
+    fn animation(&mut self, animation: &AnimationClip) -> Option<Value> {
+        let mut samplers = Vec::new();
+        let mut channels = Vec::new();
+        for track in &animation.tracks {
+            let Some(&(node, _)) = track
+                .node_index
+                .and_then(|index| self.joint_nodes.get(&index))
+            else {
+                continue;
+            };
+
+            let translations: Vec<[f32; 3]> = track
+                .translations
+                .iter()
+                .map(|k| [k.value.x, k.value.y, k.value.z])
+                .collect();
+            let rotations: Vec<[f32; 4]> = track
+                .rotations
+                .iter()
+                .map(|k| [k.value.x, k.value.y, k.value.z, k.value.w])
+                .collect();
+            let scales: Vec<[f32; 3]> = track
+                .scales
+                .iter()
+                .map(|k| [k.value.x, k.value.y, k.value.z])
+                .collect();
+            let track_samplers = [
+                (
+                    "translation",
+                    self.sampler(key_times(&track.translations), &translations),
+                ),
+                (
+                    "rotation",
+                    self.sampler(key_times(&track.rotations), &rotations),
+                ),
+                ("scale", self.sampler(key_times(&track.scales), &scales)),
+            ];
+            for (path, sampler) in track_samplers {
+                channels.push(json!({
+                    "sampler": samplers.len(),
+                    "target": { "node": node, "path": path },
+                }));
+                samplers.push(sampler);
+            }
+        }
+        if channels.is_empty() {
+            return None;
+        }
+        Some(json!({
+            "name": animation.name,
+            "samplers": samplers,
+            "channels": channels,
+        }))
+    }
+}
+
+/// Skin joint slot of every skeleton node, in skeleton order without gaps.
+/// Must pick the same nodes `GlbBuilder::node` registers as joints.
+fn joint_slots(roots: &[SceneNode]) -> HashMap<u32, u16> {
+    fn collect(node: &SceneNode, indices: &mut Vec<u32>) {
+        if let (Some(index), Some(_)) = (node.node_index, node.world_matrix) {
+            indices.push(index);
+        }
+        for child in &node.children {
+            collect(child, indices);
+        }
+    }
+
+    let mut indices = Vec::new();
+    for root in roots {
+        collect(root, &mut indices);
+    }
+    indices.sort_unstable();
+    indices.dedup();
+    indices
+        .into_iter()
+        .enumerate()
+        .map(|(slot, index)| (index, slot as u16))
+        .collect()
+}
+
+fn key_times<T>(keys: &[Keyframe<T>]) -> Vec<[f32; 1]> {
+    keys.iter().map(|k| [k.time]).collect()
+}
+
+/// Export `scene` as a binary glTF file.
+///
+/// `load_texture` receives the diffuse map path of a material, resolved to its
+/// archive entry when `Scene::resolve_textures` found one, and returns the
+/// texture as PNG, or `None` to leave the material untextured.
+pub fn scene_to_glb(
+    scene: &Scene,
+    load_texture: &mut dyn FnMut(&str) -> Option<Vec<u8>>,
+) -> Vec<u8> {
+    let mut builder = GlbBuilder {
+        scene_materials: &scene.materials,
+        joint_slots: joint_slots(&scene.root_nodes),
+        ..GlbBuilder::default()
+    };
+    let mut roots: Vec<usize> = scene
+        .root_nodes
+        .iter()
+        .map(|node| builder.node(node, load_texture))
+        .collect();
+
+    // The scene level transform becomes a wrapping root node
+    if scene.position.is_some() || scene.rotation.is_some() || scene.scale.is_some() {
+        let wrapper = SceneNode {
+            name: "scene".to_string(),
+            position: scene.position.clone(),
+            rotation: scene.rotation.clone(),
+            scale: scene.scale.clone(),
+            ..SceneNode::default()
+        };
+        let wrapper = builder.node(&wrapper, load_texture);
+        builder.nodes[wrapper]["children"] = json!(roots);
+        roots = vec![wrapper];
+    }
+
+    let skin = builder.skin();
+    let animations: Vec<Value> = scene
+        .animations
+        .iter()
+        .filter_map(|animation| builder.animation(animation))
+        .collect();
+    while !builder.bin.len().is_multiple_of(4) {
+        builder.bin.push(0);
+    }
+
+    let mut gltf = json!({
+        "asset": { "version": "2.0", "generator": env!("CARGO_PKG_NAME") },
+        "scene": 0,
+        "scenes": [{ "nodes": roots }],
+        "nodes": builder.nodes,
+    });
+    let arrays = [
+        ("meshes", builder.meshes),
+        ("materials", builder.materials),
+        ("textures", builder.textures),
+        ("images", builder.images),
+        ("accessors", builder.accessors),
+        ("bufferViews", builder.buffer_views),
+        ("animations", animations),
+    ];
+    for (key, values) in arrays {
+        if !values.is_empty() {
+            gltf[key] = Value::Array(values);
+        }
+    }
+    if let Some(skin) = skin {
+        gltf["skins"] = json!([skin]);
+    }
+    if gltf.get("textures").is_some() {
+        gltf["samplers"] = json!([{
+            "magFilter": LINEAR,
+            "minFilter": LINEAR_MIPMAP_LINEAR,
+            "wrapS": REPEAT,
+            "wrapT": REPEAT,
+        }]);
+    }
+    if !builder.bin.is_empty() {
+        gltf["buffers"] = json!([{ "byteLength": builder.bin.len() }]);
+    }
+
+    let mut json_chunk = serde_json::to_vec(&gltf).unwrap_or_default();
+    while !json_chunk.len().is_multiple_of(4) {
+        json_chunk.push(b' ');
+    }
+
+    let mut total_len = 12 + 8 + json_chunk.len();
+    if !builder.bin.is_empty() {
+        total_len += 8 + builder.bin.len();
+    }
+
+    let mut glb = Vec::with_capacity(total_len);
+    glb.extend_from_slice(&GLB_MAGIC.to_le_bytes());
+    glb.extend_from_slice(&GLB_VERSION.to_le_bytes());
+    glb.extend_from_slice(&(total_len as u32).to_le_bytes());
+    glb.extend_from_slice(&(json_chunk.len() as u32).to_le_bytes());
+    glb.extend_from_slice(&CHUNK_JSON.to_le_bytes());
+    glb.extend_from_slice(&json_chunk);
+    if !builder.bin.is_empty() {
+        glb.extend_from_slice(&(builder.bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&CHUNK_BIN.to_le_bytes());
+        glb.extend_from_slice(&builder.bin);
+    }
+    glb
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    /// Split a GLB into its JSON document and binary chunk
+    fn read_glb(glb: &[u8]) -> (Value, &[u8]) {
+        assert_eq!(u32_at(glb, 0), GLB_MAGIC);
+        assert_eq!(u32_at(glb, 8) as usize, glb.len());
+        let json_len = u32_at(glb, 12) as usize;
+        assert_eq!(u32_at(glb, 16), CHUNK_JSON);
+        let gltf: Value = serde_json::from_slice(&glb[20..20 + json_len]).unwrap();
+        let bin_start = 20 + json_len;
+        assert_eq!(u32_at(glb, bin_start + 4), CHUNK_BIN);
+        (gltf, &glb[bin_start + 8..])
+    }
+
+    #[test]
+    fn test_scene_to_glb() -> io::Result<()> {
+        let xac = crate::xac::XACRoot::from_file("tests/npc_lecifer_set.xac")?;
+        let scene = Scene::from_xac_root(&xac, String::new());
+
+        let mut requested = Vec::new();
+        let glb = scene_to_glb(&scene, &mut |path| {
+            requested.push(path.to_string());
+            None
+        });
+        let (gltf, bin) = read_glb(&glb);
+
+        assert_eq!(gltf["buffers"][0]["byteLength"], bin.len());
+        let primitive = &gltf["meshes"][0]["primitives"][0];
+        let position =
+            &gltf["accessors"][primitive["attributes"]["POSITION"].as_u64().unwrap() as usize];
+        assert_eq!(position["type"], "VEC3");
+        assert!(position["min"].is_array());
+
+        // Submesh indices are local to the submesh's own vertices
+        let indices = &gltf["accessors"][primitive["indices"].as_u64().unwrap() as usize];
+        let view = &gltf["bufferViews"][indices["bufferView"].as_u64().unwrap() as usize];
+        let start = view["byteOffset"].as_u64().unwrap() as usize;
+        let max_index = (0..indices["count"].as_u64().unwrap() as usize)
+            .map(|i| u32_at(bin, start + i * 4))
+            .max()
+            .unwrap();
+        assert!((max_index as u64) < position["count"].as_u64().unwrap());
+
+        // Every view stays inside the binary chunk
+        for view in gltf["bufferViews"].as_array().unwrap() {
+            let end = view["byteOffset"].as_u64().unwrap() + view["byteLength"].as_u64().unwrap();
+            assert!(end as usize <= bin.len());
+        }
+        // The skin spans every skeleton node
+        assert_eq!(gltf["skins"][0]["joints"].as_array().unwrap().len(), 134);
+        let skinned = gltf["meshes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .flat_map(|mesh| mesh["primitives"].as_array().unwrap())
+            .any(|p| p["attributes"]["JOINTS_0"].is_u64());
+        assert!(skinned);
+
+        // One material per scene material in use, each texture loaded once
+        let mut used: Vec<usize> = Vec::new();
+        fn collect(node: &SceneNode, used: &mut Vec<usize>) {
+            let submeshes = node.model.iter().flat_map(|model| &model.submeshes);
+            used.extend(submeshes.filter_map(|submesh| submesh.material_index));
+            for child in &node.children {
+                collect(child, used);
+            }
+        }
+        for node in &scene.root_nodes {
+            collect(node, &mut used);
+        }
+        used.sort();
+        used.dedup();
+        let materials = gltf["materials"].as_array().map_or(0, |m| m.len());
+        assert_eq!(materials, used.len());
+        let mut distinct = requested.clone();
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct, requested);
+        assert!(!requested.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_morph_targets_to_glb() {
+        use crate::mesh::{Vector3, Vector4};
+
+        let vertex = |x: f32| Vector3 { x, y: 0.0, z: 0.0 };
+        let submesh = SubMesh {
+            positions: vec![vertex(0.0), vertex(1.0), vertex(2.0)],
+            tangents: vec![Vector4::default(); 3],
+            indices: vec![0, 1, 2],
+            morph_targets: vec![MorphTarget {
+                name: "blink".to_string(),
+                range_max: 1.0,
+                position_deltas: vec![vertex(0.0), vertex(0.5), vertex(0.0)],
+                tangent_deltas: vec![vertex(1.0); 3],
+                ..MorphTarget::default()
+            }],
+            ..SubMesh::default()
+        };
+        let scene = Scene {
+            root_nodes: vec![SceneNode {
+                name: "face".to_string(),
+                model: Some(Model {
+                    name: "face".to_string(),
+                    submeshes: vec![submesh],
+                }),
+                ..SceneNode::default()
+            }],
+            ..Scene::default()
+        };
+
+        let glb = scene_to_glb(&scene, &mut |_| None);
+        let (gltf, _) = read_glb(&glb);
+        let mesh = &gltf["meshes"][0];
+        assert_eq!(mesh["extras"]["targetNames"], json!(["blink"]));
+        assert_eq!(mesh["weights"], json!([0.0]));
+
+        let target = &mesh["primitives"][0]["targets"][0];
+        let position = &gltf["accessors"][target["POSITION"].as_u64().unwrap() as usize];
+        assert_eq!(position["max"], json!([0.5, 0.0, 0.0]));
+        assert!(target["TANGENT"].is_u64());
+        // Normal deltas were left empty and are skipped
+        assert!(target.get("NORMAL").is_none());
+    }
+
+    #[test]
+    fn test_skin_skips_skeleton_gaps() {
+        use crate::mesh::Vector3;
+
+        let vertex = |x: f32| Vector3 { x, y: 0.0, z: 0.0 };
+        let joint = |name: &str, index: u32, children: Vec<SceneNode>| SceneNode {
+            name: name.to_string(),
+            node_index: Some(index),
+            world_matrix: Some(IDENTITY_MATRIX),
+            children,
+            ..SceneNode::default()
+        };
+        let submesh = SubMesh {
+            positions: vec![vertex(0.0), vertex(1.0), vertex(2.0)],
+            indices: vec![0, 1, 2],
+            joints: vec![[0, 0, 0, 0], [5, 0, 0, 0], [5, 0, 0, 0]],
+            weights: vec![[1.0, 0.0, 0.0, 0.0]; 3],
+            ..SubMesh::default()
+        };
+        // Skeleton nodes 1 to 4 were never built
+        let scene = Scene {
+            root_nodes: vec![joint(
+                "root",
+                0,
+                vec![
+                    joint("hand", 5, Vec::new()),
+                    SceneNode {
+                        name: "body".to_string(),
+                        model: Some(Model {
+                            name: "body".to_string(),
+                            submeshes: vec![submesh],
+                        }),
+                        ..SceneNode::default()
+                    },
+                ],
+            )],
+            ..Scene::default()
+        };
+
+        let glb = scene_to_glb(&scene, &mut |_| None);
+        let (gltf, bin) = read_glb(&glb);
+        let nodes = gltf["nodes"].as_array().unwrap();
+        let node_named = |name: &str| nodes.iter().position(|node| node["name"] == name);
+        assert_eq!(
+            gltf["skins"][0]["joints"],
+            json!([node_named("root"), node_named("hand")])
+        );
+        assert_eq!(nodes[node_named("body").unwrap()]["skin"], 0);
+
+        // Skeleton node 5 is the second joint of the skin
+        let primitive = &gltf["meshes"][0]["primitives"][0];
+        let accessor =
+            &gltf["accessors"][primitive["attributes"]["JOINTS_0"].as_u64().unwrap() as usize];
+        let view = &gltf["bufferViews"][accessor["bufferView"].as_u64().unwrap() as usize];
+        let start = view["byteOffset"].as_u64().unwrap() as usize;
+        let first_joints: Vec<u16> = (0..3)
+            .map(|vertex| {
+                let at = start + vertex * 8;
+                u16::from_le_bytes([bin[at], bin[at + 1]])
+            })
+            .collect();
+        assert_eq!(first_joints, vec![0, 1, 1]);
+    }
+
+    #[test]
+    fn test_material_to_glb() {
+        use crate::mesh::{RGBAColor, TextureMap, Vector3};
+
+        let vertex = |x: f32| Vector3 { x, y: 0.0, z: 0.0 };
+        let submesh = |material_index| SubMesh {
+            positions: vec![vertex(0.0), vertex(1.0), vertex(2.0)],
+            indices: vec![0, 1, 2],
+            material_index,
+            ..SubMesh::default()
+        };
+        let scene = Scene {
+            root_nodes: vec![SceneNode {
+                model: Some(Model {
+                    name: "box".to_string(),
+                    submeshes: vec![submesh(Some(1)), submesh(Some(1)), submesh(None)],
+                }),
+                ..SceneNode::default()
+            }],
+            materials: vec![
+                Material::default(),
+                Material {
+                    name: "glass".to_string(),
+                    emissive: RGBAColor {
+                        r: 0.5,
+                        g: 0.0,
+                        b: 0.0,
+                        a: 1.0,
+                    },
+                    double_sided: true,
+                    alpha_cutoff: Some(0.5),
+                    maps: vec![TextureMap {
+                        map_type: MapType::Diffuse,
+                        texture: "glass.dds".to_string(),
+                        amount: 1.0,
+                        offset: Default::default(),
+                        tiling: Default::default(),
+                        rotation: 0.0,
+                        resolved: None,
+                    }],
+                    ..Material::default()
+                },
+            ],
+            ..Scene::default()
+        };
+
+        let glb = scene_to_glb(&scene, &mut |_| Some(vec![0; 8]));
+        let (gltf, _) = read_glb(&glb);
+        // Only the material in use is written, once
+        let materials = gltf["materials"].as_array().unwrap();
+        assert_eq!(materials.len(), 1);
+        let material = &materials[0];
+        assert_eq!(material["name"], "glass");
+        assert_eq!(material["alphaMode"], "MASK");
+        assert_eq!(material["doubleSided"], true);
+        assert_eq!(material["emissiveFactor"], json!([0.5, 0.0, 0.0]));
+        assert_eq!(
+            material["pbrMetallicRoughness"]["baseColorTexture"]["index"],
+            0
+        );
+        assert_eq!(material["extras"]["maps"][0]["map_type"], "diffuse");
+
+        let primitives = gltf["meshes"][0]["primitives"].as_array().unwrap();
+        assert_eq!(primitives[0]["material"], 0);
+        assert_eq!(primitives[1]["material"], 0);
+        assert!(primitives[2].get("material").is_none());
+    }
+
+    #[test]
+    fn test_animation_to_glb() -> io::Result<()> {
+        let xac = crate::xac::XACRoot::from_file("tests/npc_lecifer_set.xac")?;
+        let mut scene = Scene::from_xac_root(&xac, String::new());
+        let xsm = crate::xsm::XSMRoot::from_file("tests/npc_lecifer_run.xsm")?;
+        scene.add_animation(AnimationClip::from_xsm_root(&xsm)?);
+
+        let glb = scene_to_glb(&scene, &mut |_| None);
+        let (gltf, _) = read_glb(&glb);
+        let animation = &gltf["animations"][0];
+        let channels = animation["channels"].as_array().unwrap();
+        assert_eq!(channels.len(), scene.animations[0].tracks.len() * 3);
+
+        let joints = gltf["skins"][0]["joints"].as_array().unwrap();
+        for channel in channels {
+            assert!(joints.contains(&channel["target"]["node"]));
+            let sampler = &animation["samplers"][channel["sampler"].as_u64().unwrap() as usize];
+            let input = &gltf["accessors"][sampler["input"].as_u64().unwrap() as usize];
+            let output = &gltf["accessors"][sampler["output"].as_u64().unwrap() as usize];
+            assert_eq!(input["type"], "SCALAR");
+            assert!(input["min"].is_array() && input["max"].is_array());
+            assert_eq!(input["count"], output["count"]);
+            let expected = match channel["target"]["path"].as_str().unwrap() {
+                "rotation" => "VEC4",
+                _ => "VEC3",
+            };
+            assert_eq!(output["type"], expected);
+            // Animation data is not vertex data
+            let view = &gltf["bufferViews"][input["bufferView"].as_u64().unwrap() as usize];
+            assert!(view.get("target").is_none());
+        }
+        Ok(())
+    }
+}
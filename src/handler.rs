@@ -0,0 +1,710 @@
+//! Format handlers behind the parse, preview and export endpoints.
+//!
+//! A handler claims files by their sniffed `FileFormat` (plus the extension
+//! where one format covers several dialects, such as the XML based 3dworld),
+//! turns them into JSON, renders a browser friendly preview and converts them
+//! to the export targets it lists. The API only talks to `FormatRegistry`, so
+//! supporting a new format means registering one more handler here.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Cursor;
+
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::animation::AnimationClip;
+use crate::category::Folder;
+use crate::dds::DdsHeader;
+use crate::fsb::FSB5File;
+use crate::gltf;
+use crate::ies::IESRoot;
+use crate::mesh::{Scene, dx_to_gl_position, dx_to_gl_quat, dx_to_gl_scale, to_quat, to_vec3};
+use crate::sniff::FileFormat;
+use crate::texture::{TextureIndex, TextureResolver};
+use crate::threedworld::World;
+use crate::tok::{self, TokParser};
+use crate::xac::XACRoot;
+use crate::xpm::XPMRoot;
+use crate::xsm::XSMRoot;
+
+/// What a handler may look at besides the file bytes
+pub struct HandlerContext<'a> {
+    pub tree: &'a Folder,
+    /// Full path of the file inside the tree, e.g. "ies/item.ies"
+    pub full_path: &'a str,
+    /// Lowercased XAC path -> texture folder, from `IESRoot::extract_mesh_path_map`
+    pub mesh_map: &'a HashMap<String, String>,
+    /// Texture duplicates and file names, shared by every request
+    pub textures: &'a TextureIndex,
+    /// Full paths of XSM motions to play on an exported model
+    pub animations: &'a [String],
+    /// DDS mip level and cubemap face to render, the whole top level when both are `None`
+    pub mip: Option<usize>,
+    pub face: Option<usize>,
+}
+
+impl<'a> HandlerContext<'a> {
+    fn extension(&self) -> String {
+        self.full_path
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_lowercase())
+            .unwrap_or_default()
+    }
+
+    /// Texture lookup for the model at `full_path`
+    fn texture_resolver(&self) -> TextureResolver<'a> {
+        TextureResolver::for_model(self.tree, self.textures, self.full_path, self.mesh_map)
+    }
+}
+
+#[derive(Debug)]
+pub enum Output {
+    Json(Value),
+    Bytes {
+        content_type: &'static str,
+        data: Vec<u8>,
+    },
+}
+
+#[derive(Debug)]
+pub enum HandlerError {
+    /// The handler has no such operation or target
+    Unsupported(String),
+    /// The file content could not be decoded
+    Parse(String),
+    /// A file referenced by this one is not in the tree
+    Missing(String),
+    /// The request asked for something the file does not have
+    BadRequest(String),
+}
+
+impl fmt::Display for HandlerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandlerError::Unsupported(msg)
+            | HandlerError::Parse(msg)
+            | HandlerError::Missing(msg)
+            | HandlerError::BadRequest(msg) => f.write_str(msg),
+        }
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<Value, HandlerError> {
+    serde_json::to_value(value).map_err(|e| HandlerError::Parse(e.to_string()))
+}
+
+fn parse_error(format: &str) -> impl FnOnce(std::io::Error) -> HandlerError + '_ {
+    move |e| HandlerError::Parse(format!("Failed to parse {} file: {}", format, e))
+}
+
+pub trait FormatHandler: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Whether this handler understands a file of the given format and name
+    fn detect(&self, format: FileFormat, file_name: &str) -> bool;
+
+    fn parse(&self, data: &[u8], ctx: &HandlerContext) -> Result<Value, HandlerError>;
+
+    /// Browser friendly rendering, the parsed JSON unless overridden
+    fn preview(&self, data: Vec<u8>, ctx: &HandlerContext) -> Result<Output, HandlerError> {
+        self.parse(&data, ctx).map(Output::Json)
+    }
+
+    /// Extensions accepted by `export`
+    fn export_targets(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn export(
+        &self,
+        _data: Vec<u8>,
+        target: &str,
+        _ctx: &HandlerContext,
+    ) -> Result<Output, HandlerError> {
+        Err(HandlerError::Unsupported(format!(
+            "{} files cannot be exported to {}",
+            self.name(),
+            target
+        )))
+    }
+}
+
+pub struct FormatRegistry {
+    handlers: Vec<Box<dyn FormatHandler>>,
+}
+
+impl FormatRegistry {
+    pub fn new() -> Self {
+        Self {
+            handlers: Vec::new(),
+        }
+    }
+
+    /// Registry with every built-in handler
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register(IesHandler);
+        registry.register(XacHandler);
+        registry.register(XsmHandler);
+        registry.register(XpmHandler);
+        registry.register(TokHandler);
+        registry.register(WorldHandler);
+        registry.register(FsbHandler);
+        registry.register(ImageHandler);
+        registry.register(TextHandler);
+        registry.register(MediaHandler);
+        registry
+    }
+
+    /// Handlers are tried in registration order
+    pub fn register(&mut self, handler: impl FormatHandler + 'static) {
+        self.handlers.push(Box::new(handler));
+    }
+
+    pub fn find(&self, format: FileFormat, file_name: &str) -> Option<&dyn FormatHandler> {
+        self.handlers
+            .iter()
+            .find(|h| h.detect(format, file_name))
+            .map(|h| h.as_ref())
+    }
+
+    pub fn handlers(&self) -> impl Iterator<Item = &dyn FormatHandler> {
+        self.handlers.iter().map(|h| h.as_ref())
+    }
+}
+
+impl Default for FormatRegistry {
+    fn default() -> Self {
+        Self::with_defaults()
+    }
+}
+
+pub struct IesHandler;
+
+impl FormatHandler for IesHandler {
+    fn name(&self) -> &'static str {
+        "ies"
+    }
+
+    fn detect(&self, format: FileFormat, _file_name: &str) -> bool {
+        format == FileFormat::Ies
+    }
+
+    fn parse(&self, data: &[u8], _ctx: &HandlerContext) -> Result<Value, HandlerError> {
+        let ies = IESRoot::from_bytes(data).map_err(parse_error("IES"))?;
+        to_json(&ies)
+    }
+
+    fn export_targets(&self) -> &'static [&'static str] {
+        &["csv"]
+    }
+
+    fn export(
+        &self,
+        data: Vec<u8>,
+        target: &str,
+        _ctx: &HandlerContext,
+    ) -> Result<Output, HandlerError> {
+        if target != "csv" {
+            return Err(HandlerError::Unsupported(format!(
+                "ies files cannot be exported to {}",
+                target
+            )));
+        }
+        let ies = IESRoot::from_bytes(&data).map_err(parse_error("IES"))?;
+        Ok(Output::Bytes {
+            content_type: "text/csv",
+            data: ies_to_csv(&ies).into_bytes(),
+        })
+    }
+}
+
+/// One header row of column names, then one row per IES row
+pub fn ies_to_csv(ies: &IESRoot) -> String {
+    fn escape(value: &str) -> String {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+
+    let lookup = ies.column_lookup();
+    let mut csv = String::new();
+    let header: Vec<String> = lookup.names().iter().map(|n| escape(n)).collect();
+    csv.push_str(&header.join(","));
+    csv.push('\n');
+
+    for row in &ies.data {
+        let cells: Vec<String> = lookup
+            .names()
+            .iter()
+            .map(|column| escape(&lookup.value(row, column).unwrap_or_default()))
+            .collect();
+        csv.push_str(&cells.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+pub struct XacHandler;
+
+impl FormatHandler for XacHandler {
+    fn name(&self) -> &'static str {
+        "xac"
+    }
+
+    fn detect(&self, format: FileFormat, _file_name: &str) -> bool {
+        format == FileFormat::Xac
+    }
+
+    fn parse(&self, data: &[u8], _ctx: &HandlerContext) -> Result<Value, HandlerError> {
+        let xac = XACRoot::from_bytes(data).map_err(parse_error("XAC"))?;
+        to_json(&xac)
+    }
+
+    fn preview(&self, data: Vec<u8>, ctx: &HandlerContext) -> Result<Output, HandlerError> {
+        let xac = XACRoot::from_bytes(&data).map_err(parse_error("XAC"))?;
+        let mut scene = Scene::from_xac_root(&xac, String::new());
+        scene.resolve_textures(&mut ctx.texture_resolver());
+        to_json(&scene).map(Output::Json)
+    }
+
+    fn export_targets(&self) -> &'static [&'static str] {
+        &["glb"]
+    }
+
+    fn export(
+        &self,
+        data: Vec<u8>,
+        target: &str,
+        ctx: &HandlerContext,
+    ) -> Result<Output, HandlerError> {
+        if target != "glb" {
+            return Err(HandlerError::Unsupported(format!(
+                "xac files cannot be exported to {}",
+                target
+            )));
+        }
+        let xac = XACRoot::from_bytes(&data).map_err(parse_error("XAC"))?;
+        let mut scene = Scene::from_xac_root(&xac, String::new());
+        scene.resolve_textures(&mut ctx.texture_resolver());
+        for path in ctx.animations {
+            scene.add_animation(Self::load_animation(ctx.tree, path)?);
+        }
+        let glb = gltf::scene_to_glb(&scene, &mut |path| Self::load_texture(ctx.tree, path));
+        Ok(Output::Bytes {
+            content_type: "model/gltf-binary",
+            data: glb,
+        })
+    }
+}
+
+impl XacHandler {
+    /// Motion at `path`, named after the file when the XSM carries no name
+    fn load_animation(tree: &Folder, path: &str) -> Result<AnimationClip, HandlerError> {
+        let results = tree.search_file_by_full_path(path);
+        let Some((full_path, file)) = results.last() else {
+            return Err(HandlerError::Missing(format!(
+                "Animation not found: {}",
+                path
+            )));
+        };
+        let data = file
+            .extract_data()
+            .map_err(|e| HandlerError::Parse(format!("Failed to extract {}: {}", path, e)))?;
+        let xsm = XSMRoot::from_bytes(&data).map_err(parse_error("XSM"))?;
+
+        let mut animation = AnimationClip::from_xsm_root(&xsm).map_err(parse_error("XSM"))?;
+        if animation.name.is_empty() {
+            let name = full_path.rsplit('/').next().unwrap_or(full_path);
+            animation.name = name
+                .rsplit_once('.')
+                .map_or(name, |(stem, _)| stem)
+                .to_string();
+        }
+        Ok(animation)
+    }
+
+    /// Texture as PNG; materials name .tga files that ship as .dds
+    fn load_texture(tree: &Folder, path: &str) -> Option<Vec<u8>> {
+        let mut candidates = vec![path.to_string()];
+        if let Some((stem, _)) = path.rsplit_once('.') {
+            candidates.push(format!("{}.dds", stem));
+        }
+        candidates.iter().find_map(|candidate| {
+            let results = tree.search_file_by_full_path(candidate);
+            let (_, file) = results.last()?;
+            let data = file.extract_data().ok()?;
+            ImageHandler::to_png(&data, None, None).ok()
+        })
+    }
+}
+
+pub struct XsmHandler;
+
+impl FormatHandler for XsmHandler {
+    fn name(&self) -> &'static str {
+        "xsm"
+    }
+
+    fn detect(&self, format: FileFormat, _file_name: &str) -> bool {
+        format == FileFormat::Xsm
+    }
+
+    fn parse(&self, data: &[u8], _ctx: &HandlerContext) -> Result<Value, HandlerError> {
+        let xsm = XSMRoot::from_bytes(data).map_err(parse_error("XSM"))?;
+        to_json(&xsm)
+    }
+
+    /// The motion as an `AnimationClip`, whatever chunk versions it uses
+    fn preview(&self, data: Vec<u8>, _ctx: &HandlerContext) -> Result<Output, HandlerError> {
+        let xsm = XSMRoot::from_bytes(&data).map_err(parse_error("XSM"))?;
+        let clip = AnimationClip::from_xsm_root(&xsm).map_err(parse_error("XSM"))?;
+        to_json(&clip).map(Output::Json)
+    }
+}
+
+pub struct XpmHandler;
+
+impl FormatHandler for XpmHandler {
+    fn name(&self) -> &'static str {
+        "xpm"
+    }
+
+    fn detect(&self, format: FileFormat, _file_name: &str) -> bool {
+        format == FileFormat::Xpm
+    }
+
+    fn parse(&self, data: &[u8], _ctx: &HandlerContext) -> Result<Value, HandlerError> {
+        let xpm = XPMRoot::from_bytes(data).map_err(parse_error("XPM"))?;
+        to_json(&xpm)
+    }
+}
+
+pub struct TokHandler;
+
+impl TokHandler {
+    fn render_svg(data: &[u8]) -> Result<Vec<u8>, HandlerError> {
+        let root = TokParser::new(Cursor::new(data))
+            .and_then(|parser| parser.parse())
+            .map_err(parse_error("TOK"))?;
+        let mut svg = Vec::new();
+        tok::export_to_svg(&root, &mut Cursor::new(&mut svg), 512.0, 512.0)
+            .map_err(|e| HandlerError::Parse(format!("Failed to export SVG: {}", e)))?;
+        Ok(svg)
+    }
+}
+
+impl FormatHandler for TokHandler {
+    fn name(&self) -> &'static str {
+        "tok"
+    }
+
+    fn detect(&self, format: FileFormat, _file_name: &str) -> bool {
+        format == FileFormat::Tok
+    }
+
+    fn parse(&self, data: &[u8], _ctx: &HandlerContext) -> Result<Value, HandlerError> {
+        let root = TokParser::new(Cursor::new(data))
+            .and_then(|parser| parser.parse())
+            .map_err(parse_error("TOK"))?;
+        to_json(&root)
+    }
+
+    fn preview(&self, data: Vec<u8>, _ctx: &HandlerContext) -> Result<Output, HandlerError> {
+        Ok(Output::Bytes {
+            content_type: "image/svg+xml",
+            data: Self::render_svg(&data)?,
+        })
+    }
+
+    fn export_targets(&self) -> &'static [&'static str] {
+        &["svg"]
+    }
+
+    fn export(
+        &self,
+        data: Vec<u8>,
+        target: &str,
+        ctx: &HandlerContext,
+    ) -> Result<Output, HandlerError> {
+        match target {
+            "svg" => self.preview(data, ctx),
+            _ => Err(HandlerError::Unsupported(format!(
+                "tok files cannot be exported to {}",
+                target
+            ))),
+        }
+    }
+}
+
+pub struct WorldHandler;
+
+impl FormatHandler for WorldHandler {
+    fn name(&self) -> &'static str {
+        "3dworld"
+    }
+
+    fn detect(&self, format: FileFormat, file_name: &str) -> bool {
+        format == FileFormat::Xml && file_name.to_lowercase().ends_with(".3dworld")
+    }
+
+    fn parse(&self, data: &[u8], _ctx: &HandlerContext) -> Result<Value, HandlerError> {
+        let world = World::from_bytes(data).map_err(parse_error("3dworld"))?;
+        to_json(&world)
+    }
+
+    /// Every model of the world as a scene placed at its world transform
+    fn preview(&self, data: Vec<u8>, ctx: &HandlerContext) -> Result<Output, HandlerError> {
+        fn normalize_path(p: &str) -> String {
+            p.replace('\\', "/").trim_matches('/').to_string()
+        }
+
+        let world = World::from_bytes(&data).map_err(parse_error("3dworld"))?;
+        let Some(model_dir) = world.model_dirs.first() else {
+            return Ok(Output::Json(json!([])));
+        };
+        let ipf_name = normalize_path(&model_dir.ipf_name);
+        let base_path = normalize_path(&model_dir.path);
+        // One resolver for the whole world, models share most of their textures
+        let mut textures = TextureResolver::new(ctx.tree, ctx.textures);
+        textures.add_world_dirs(&world);
+
+        let mut scenes = Vec::new();
+        for model in &world.models {
+            let model_path = format!("{}/{}/{}", ipf_name, base_path, normalize_path(&model.file));
+            let results = ctx.tree.search_file_by_full_path(&model_path);
+            let Some((_, file)) = results.last() else {
+                return Err(HandlerError::Missing(format!(
+                    "Model not found: {}",
+                    model_path
+                )));
+            };
+
+            let data = file.extract_data().map_err(|e| {
+                HandlerError::Parse(format!("Failed to extract {}: {}", model_path, e))
+            })?;
+            let xac = XACRoot::from_bytes(&data).map_err(parse_error("XAC"))?;
+
+            let mut scene = Scene::from_xac_root(&xac, String::new());
+            scene.resolve_textures(&mut textures);
+            if let Some(pos) = &model.pos {
+                scene.position = Some(dx_to_gl_position(to_vec3(pos)));
+            }
+            if let Some(rot) = &model.rot {
+                scene.rotation = Some(dx_to_gl_quat(to_quat(rot)));
+            }
+            if let Some(scale) = &model.scale {
+                scene.scale = Some(dx_to_gl_scale(to_vec3(scale)));
+            }
+            scenes.push(scene);
+        }
+
+        to_json(&scenes).map(Output::Json)
+    }
+}
+
+pub struct FsbHandler;
+
+impl FormatHandler for FsbHandler {
+    fn name(&self) -> &'static str {
+        "fsb"
+    }
+
+    fn detect(&self, format: FileFormat, _file_name: &str) -> bool {
+        format == FileFormat::Fsb5
+    }
+
+    /// Header, sample headers and names; the sample data itself is left out
+    fn parse(&self, data: &[u8], _ctx: &HandlerContext) -> Result<Value, HandlerError> {
+        let fsb = FSB5File::read(&mut Cursor::new(data))
+            .map_err(|e| HandlerError::Parse(format!("Failed to parse FSB5 file: {}", e)))?;
+        Ok(json!({
+            "header": to_json(&fsb.header)?,
+            "sample_headers": to_json(&fsb.sample_headers)?,
+            "name_table": to_json(&fsb.name_table)?,
+        }))
+    }
+}
+
+pub struct ImageHandler;
+
+impl ImageHandler {
+    /// Decode DDS or any image stb understands (TGA, PNG, JPEG, BMP) and encode it as PNG.
+    /// `mip` and `face` pick one DDS surface instead of the whole top level.
+    fn to_png(
+        data: &[u8],
+        mip: Option<usize>,
+        face: Option<usize>,
+    ) -> Result<Vec<u8>, HandlerError> {
+        let img = if data.starts_with(b"DDS ") {
+            match (mip, face) {
+                (None, None) => crate::dds::decode_preview(data),
+                (mip, face) => crate::dds::decode(data, mip.unwrap_or(0), face.unwrap_or(0)),
+            }
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::InvalidInput => HandlerError::BadRequest(e.to_string()),
+                _ => parse_error("DDS")(e),
+            })?
+        } else {
+            crate::stb::load_tga_from_memory(data)
+                .ok_or_else(|| HandlerError::Parse("Failed to decode image".to_string()))?
+        };
+        crate::stb::encode_png_to_memory(&img)
+            .ok_or_else(|| HandlerError::Parse("Failed to encode PNG".to_string()))
+    }
+}
+
+impl FormatHandler for ImageHandler {
+    fn name(&self) -> &'static str {
+        "image"
+    }
+
+    fn detect(&self, format: FileFormat, _file_name: &str) -> bool {
+        format.is_image()
+    }
+
+    fn parse(&self, data: &[u8], _ctx: &HandlerContext) -> Result<Value, HandlerError> {
+        let mut info = json!({ "size": data.len() });
+        if let Ok(header) = DdsHeader::from_bytes(data) {
+            info["width"] = json!(header.width);
+            info["height"] = json!(header.height);
+            info["format"] = json!(header.format().ok());
+            info["mip_count"] = json!(header.mip_count());
+            info["face_count"] = json!(header.face_count());
+            info["cubemap"] = json!(header.is_cubemap());
+        } else if let Some(img) = crate::stb::load_tga_from_memory(data) {
+            info["width"] = json!(img.width);
+            info["height"] = json!(img.height);
+            info["channels"] = json!(img.channels);
+        }
+        Ok(info)
+    }
+
+    /// TGA and DDS are converted to PNG, everything else is served as-is.
+    /// Cubemaps show their faces side by side unless `ctx` picks a mip or face.
+    fn preview(&self, data: Vec<u8>, ctx: &HandlerContext) -> Result<Output, HandlerError> {
+        let format = crate::sniff::detect(ctx.full_path, &data, data.len() as u64);
+        if matches!(format, FileFormat::Tga | FileFormat::Dds) {
+            return Ok(Output::Bytes {
+                content_type: "image/png",
+                data: Self::to_png(&data, ctx.mip, ctx.face)?,
+            });
+        }
+        Ok(Output::Bytes {
+            content_type: format.mime_type(),
+            data,
+        })
+    }
+
+    fn export_targets(&self) -> &'static [&'static str] {
+        &["png"]
+    }
+
+    fn export(
+        &self,
+        data: Vec<u8>,
+        target: &str,
+        ctx: &HandlerContext,
+    ) -> Result<Output, HandlerError> {
+        let format = crate::sniff::detect(ctx.full_path, &data, data.len() as u64);
+        match (target, format) {
+            ("png", FileFormat::Tga | FileFormat::Dds) => self.preview(data, ctx),
+            ("png", FileFormat::Png) => Ok(Output::Bytes {
+                content_type: "image/png",
+                data,
+            }),
+            _ => Err(HandlerError::Unsupported(format!(
+                "{} images cannot be exported to {}",
+                format.name(),
+                target
+            ))),
+        }
+    }
+}
+
+/// XML, Lua and other plain text
+pub struct TextHandler;
+
+impl FormatHandler for TextHandler {
+    fn name(&self) -> &'static str {
+        "text"
+    }
+
+    fn detect(&self, format: FileFormat, _file_name: &str) -> bool {
+        format.is_text()
+    }
+
+    fn parse(&self, data: &[u8], ctx: &HandlerContext) -> Result<Value, HandlerError> {
+        Ok(json!({
+            "extension": ctx.extension(),
+            "text": String::from_utf8_lossy(data),
+        }))
+    }
+
+    fn preview(&self, data: Vec<u8>, _ctx: &HandlerContext) -> Result<Output, HandlerError> {
+        Ok(Output::Bytes {
+            content_type: "text/plain",
+            data: String::from_utf8_lossy(&data).into_owned().into_bytes(),
+        })
+    }
+}
+
+/// Audio and fonts the browser plays or renders natively
+pub struct MediaHandler;
+
+impl FormatHandler for MediaHandler {
+    fn name(&self) -> &'static str {
+        "media"
+    }
+
+    fn detect(&self, format: FileFormat, _file_name: &str) -> bool {
+        matches!(format, FileFormat::Mp3 | FileFormat::Ttf)
+    }
+
+    fn parse(&self, _data: &[u8], _ctx: &HandlerContext) -> Result<Value, HandlerError> {
+        Err(HandlerError::Unsupported(
+            "media files have no parsed form".to_string(),
+        ))
+    }
+
+    fn preview(&self, data: Vec<u8>, ctx: &HandlerContext) -> Result<Output, HandlerError> {
+        let format = crate::sniff::detect(ctx.full_path, &data, data.len() as u64);
+        Ok(Output::Bytes {
+            content_type: format.mime_type(),
+            data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_dispatch() {
+        let registry = FormatRegistry::with_defaults();
+        let name = |format, file: &str| registry.find(format, file).map(|h| h.name());
+
+        assert_eq!(name(FileFormat::Ies, "item.ies"), Some("ies"));
+        assert_eq!(name(FileFormat::Xml, "barrack.3dworld"), Some("3dworld"));
+        assert_eq!(name(FileFormat::Xml, "skin.xml"), Some("text"));
+        assert_eq!(name(FileFormat::Tga, "enclass.tga"), Some("image"));
+        assert_eq!(name(FileFormat::Binary, "x.bin"), None);
+    }
+
+    #[test]
+    fn test_ies_csv_export() -> std::io::Result<()> {
+        let data = std::fs::read("tests/cell.ies")?;
+        let ies = IESRoot::from_bytes(&data)?;
+        let csv = ies_to_csv(&ies);
+
+        let mut lines = csv.lines();
+        let header = lines.next().unwrap();
+        assert_eq!(header.split(',').count(), ies.column_lookup().names().len());
+        assert_eq!(lines.count(), ies.data.len());
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+//! HTTP caching helpers for the download and preview endpoints.
+//!
+//! Archive entries never change while the server runs, so an entry's crc32,
+//! archive and version make a stable ETag. Decoded previews (PNG conversions,
+//! scene JSON) are kept in a byte-budgeted LRU so repeated views skip the
+//! extract and decode step entirely.
+
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::sync::Mutex;
+
+use actix_web::http::header;
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse, HttpResponseBuilder};
+
+use crate::category::FileRef;
+
+pub const CACHE_CONTROL: &str = "public, max-age=3600";
+
+/// Default preview cache budget when `paths.json` sets none
+pub const DEFAULT_PREVIEW_CACHE_MB: usize = 256;
+
+/// Strong ETag of one file version, `variant` tells representations of it apart
+pub fn entry_etag(file: &FileRef, variant: &str) -> String {
+    format!(
+        "\"{:08x}-{}-{}-{}\"",
+        file.crc32,
+        file.archive_id(),
+        file.version(),
+        variant
+    )
+}
+
+/// Whether the request's If-None-Match already names `etag`
+pub fn not_modified(req: &HttpRequest, etag: &str) -> bool {
+    let Some(value) = req
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return false;
+    };
+    value
+        .split(',')
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+pub fn not_modified_response(etag: &str) -> HttpResponse {
+    HttpResponse::NotModified()
+        .insert_header((header::ETAG, etag.to_string()))
+        .insert_header((header::CACHE_CONTROL, CACHE_CONTROL))
+        .finish()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RangeRequest {
+    /// No Range header, or one we ignore (multiple ranges, other units)
+    Full,
+    Partial(Range<usize>),
+    Unsatisfiable,
+}
+
+/// Parse a single `bytes=` range against a body of `len` bytes
+pub fn parse_range(value: Option<&str>, len: usize) -> RangeRequest {
+    let Some(spec) = value.and_then(|v| v.trim().strip_prefix("bytes=")) else {
+        return RangeRequest::Full;
+    };
+    if spec.contains(',') {
+        return RangeRequest::Full;
+    }
+    let Some((start, end)) = spec.split_once('-') else {
+        return RangeRequest::Full;
+    };
+
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => match suffix.parse::<usize>() {
+            Ok(0) => return RangeRequest::Unsatisfiable,
+            Ok(n) => (len.saturating_sub(n), len),
+            Err(_) => return RangeRequest::Full,
+        },
+        (start, "") => match start.parse::<usize>() {
+            Ok(start) => (start, len),
+            Err(_) => return RangeRequest::Full,
+        },
+        (start, end) => match (start.parse::<usize>(), end.parse::<usize>()) {
+            (Ok(start), Ok(end)) if start <= end => (start, end.saturating_add(1).min(len)),
+            _ => return RangeRequest::Full,
+        },
+    };
+
+    if start >= len {
+        RangeRequest::Unsatisfiable
+    } else {
+        RangeRequest::Partial(start..end)
+    }
+}
+
+/// Respond with `body`, honoring a Range header. `builder` already carries
+/// the caller's extra headers and is only used for 200 responses.
+pub fn ranged_response(
+    req: &HttpRequest,
+    mut builder: HttpResponseBuilder,
+    content_type: &str,
+    etag: &str,
+    body: Bytes,
+) -> HttpResponse {
+    let range = req
+        .headers()
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok());
+
+    match parse_range(range, body.len()) {
+        RangeRequest::Full => builder
+            .insert_header((header::ETAG, etag.to_string()))
+            .insert_header((header::CACHE_CONTROL, CACHE_CONTROL))
+            .insert_header((header::ACCEPT_RANGES, "bytes"))
+            .content_type(content_type.to_string())
+            .body(body),
+        RangeRequest::Partial(range) => HttpResponse::PartialContent()
+            .insert_header((header::ETAG, etag.to_string()))
+            .insert_header((header::CACHE_CONTROL, CACHE_CONTROL))
+            .insert_header((header::ACCEPT_RANGES, "bytes"))
+            .insert_header((
+                header::CONTENT_RANGE,
+                format!("bytes {}-{}/{}", range.start, range.end - 1, body.len()),
+            ))
+            .content_type(content_type.to_string())
+            .body(body.slice(range)),
+        RangeRequest::Unsatisfiable => HttpResponse::RangeNotSatisfiable()
+            .insert_header((header::CONTENT_RANGE, format!("bytes */{}", body.len())))
+            .finish(),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CachedPreview {
+    pub content_type: &'static str,
+    pub body: Bytes,
+}
+
+#[derive(Debug, Default)]
+struct LruState {
+    entries: HashMap<String, (CachedPreview, u64)>,
+    // last use tick -> key, oldest first
+    order: BTreeMap<u64, String>,
+    tick: u64,
+    used_bytes: usize,
+}
+
+/// Decoded previews keyed by ETag, evicting the least recently used past the budget
+#[derive(Debug)]
+pub struct PreviewCache {
+    budget_bytes: usize,
+    state: Mutex<LruState>,
+}
+
+impl PreviewCache {
+    pub fn new(budget_bytes: usize) -> Self {
+        Self {
+            budget_bytes,
+            state: Mutex::new(LruState::default()),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<CachedPreview> {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        state.tick += 1;
+        let (preview, last_used) = state.entries.get_mut(key)?;
+        let previous = std::mem::replace(last_used, state.tick);
+        state.order.remove(&previous);
+        state.order.insert(state.tick, key.to_string());
+        Some(preview.clone())
+    }
+
+    /// Previews larger than the whole budget are not cached
+    pub fn insert(&self, key: String, preview: CachedPreview) {
+        let size = preview.body.len();
+        if size > self.budget_bytes {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        if let Some((old, last_used)) = state.entries.remove(&key) {
+            state.order.remove(&last_used);
+            state.used_bytes -= old.body.len();
+        }
+
+        while state.used_bytes + size > self.budget_bytes {
+            let Some((_, oldest)) = state.order.pop_first() else {
+                break;
+            };
+            if let Some((evicted, _)) = state.entries.remove(&oldest) {
+                state.used_bytes -= evicted.body.len();
+            }
+        }
+
+        state.used_bytes += size;
+        state.order.insert(tick, key.clone());
+        state.entries.insert(key, (preview, tick));
+    }
+
+    pub fn used_bytes(&self) -> usize {
+        self.state.lock().unwrap().used_bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range(None, 100), RangeRequest::Full);
+        assert_eq!(
+            parse_range(Some("bytes=0-9"), 100),
+            RangeRequest::Partial(0..10)
+        );
+        assert_eq!(
+            parse_range(Some("bytes=90-"), 100),
+            RangeRequest::Partial(90..100)
+        );
+        assert_eq!(
+            parse_range(Some("bytes=-10"), 100),
+            RangeRequest::Partial(90..100)
+        );
+        assert_eq!(
+            parse_range(Some("bytes=50-500"), 100),
+            RangeRequest::Partial(50..100)
+        );
+        assert_eq!(
+            parse_range(Some("bytes=100-"), 100),
+            RangeRequest::Unsatisfiable
+        );
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 100), RangeRequest::Full);
+    }
+
+    #[test]
+    fn test_preview_cache_evicts_least_recently_used() {
+        let preview = |len: usize| CachedPreview {
+            content_type: "image/png",
+            body: Bytes::from(vec![0u8; len]),
+        };
+        let cache = PreviewCache::new(100);
+        cache.insert("a".to_string(), preview(40));
+        cache.insert("b".to_string(), preview(40));
+        assert!(cache.get("a").is_some());
+
+        // "b" is now the oldest entry
+        cache.insert("c".to_string(), preview(40));
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("a").is_some());
+        assert_eq!(cache.used_bytes(), 80);
+
+        cache.insert("huge".to_string(), preview(101));
+        assert_eq!(cache.len(), 2);
+    }
+}
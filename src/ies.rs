@@ -191,6 +191,90 @@ impl IESRoot {
 
         map
     }
+
+    /// Build a name -> slot lookup for reading row cells by column name
+    pub fn column_lookup(&self) -> IESColumnLookup {
+        IESColumnLookup::new(&self.columns)
+    }
+}
+
+/// Where a named column lives inside an `IESColumnData` row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IESColumnSlot {
+    Number(usize),
+    Text(usize),
+}
+
+/// Column name -> row slot mapping.
+///
+/// Rows store number columns (type 0) in `floats` and string columns in `texts`,
+/// each ordered by `decl_idx`.
+#[derive(Debug, Default)]
+pub struct IESColumnLookup {
+    slots: HashMap<String, IESColumnSlot>,
+    names: Vec<String>,
+}
+
+impl IESColumnLookup {
+    pub fn new(columns: &[IESColumn]) -> Self {
+        let mut sorted: Vec<&IESColumn> = columns.iter().collect();
+        sorted.sort_by(|a, b| {
+            (a.type_data != 0)
+                .cmp(&(b.type_data != 0))
+                .then(a.decl_idx.cmp(&b.decl_idx))
+        });
+
+        let mut slots = HashMap::new();
+        let mut names = Vec::new();
+        let (mut number_idx, mut text_idx) = (0, 0);
+
+        for col in sorted {
+            let slot = if col.type_data == 0 {
+                number_idx += 1;
+                IESColumnSlot::Number(number_idx - 1)
+            } else {
+                text_idx += 1;
+                IESColumnSlot::Text(text_idx - 1)
+            };
+            slots.insert(col.column.clone(), slot);
+            names.push(col.column.clone());
+        }
+
+        Self { slots, names }
+    }
+
+    /// Column names in row storage order (numbers first, then strings)
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn slot(&self, column: &str) -> Option<IESColumnSlot> {
+        self.slots.get(column).copied()
+    }
+
+    /// Read a string cell, `None` if the column is missing or numeric
+    pub fn text<'a>(&self, row: &'a IESColumnData, column: &str) -> Option<&'a str> {
+        match self.slot(column)? {
+            IESColumnSlot::Text(i) => row.texts.get(i).map(|t| t.text_data.as_str()),
+            IESColumnSlot::Number(_) => None,
+        }
+    }
+
+    /// Read a number cell, `None` if the column is missing or a string
+    pub fn number(&self, row: &IESColumnData, column: &str) -> Option<f32> {
+        match self.slot(column)? {
+            IESColumnSlot::Number(i) => row.floats.get(i).map(|f| f.float_data),
+            IESColumnSlot::Text(_) => None,
+        }
+    }
+
+    /// Read any cell as a string, formatting numbers without a trailing `.0`
+    pub fn value(&self, row: &IESColumnData, column: &str) -> Option<String> {
+        match self.slot(column)? {
+            IESColumnSlot::Text(i) => row.texts.get(i).map(|t| t.text_data.clone()),
+            IESColumnSlot::Number(i) => row.floats.get(i).map(|f| f.float_data.to_string()),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -52,7 +52,7 @@ const PASSWORD: [u8; 20] = [
 ];
 
 #[binread]
-#[derive(Default, Debug, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 #[br(little)]
 pub struct IPFHeader {
     pub file_count: u16,
@@ -86,94 +86,155 @@ pub struct IPFFileTable {
 }
 
 impl IPFFileTable {
-    /// Check if the file should not be decompressed based on extension
-    fn should_skip_decompression(&self) -> bool {
-        let ignored_exts = [".fsb", ".jpg", ".mp3"];
-        self.directory_name
-            .rsplit('.')
-            .next()
-            .map(|ext| format!(".{}", ext.to_ascii_lowercase()))
-            .map_or(false, |ext| ignored_exts.contains(&ext.as_str()))
-    }
-
     pub fn extract_data(&self) -> io::Result<Vec<u8>> {
         let path = self.file_path.as_ref().ok_or_else(|| {
             io::Error::new(io::ErrorKind::Other, "file_path not set for this IPF entry")
         })?;
 
-        let mut file = File::open(path)?;
+        extract_file_data(
+            path,
+            &self.directory_name,
+            self.file_pointer,
+            self.file_size_compressed,
+            self.file_size_uncompressed,
+        )
+    }
+}
+
+/// Read one file out of the IPF archive at `archive_path`, decrypting and decompressing it
+pub fn extract_file_data(
+    archive_path: &Path,
+    file_name: &str,
+    file_pointer: u32,
+    file_size_compressed: u32,
+    file_size_uncompressed: u32,
+) -> io::Result<Vec<u8>> {
+    let mut file = File::open(archive_path)?;
+
+    // Seek to the file's data
+    file.seek(SeekFrom::Start(file_pointer as u64))?;
+
+    // Read the raw compressed/encrypted bytes
+    let mut buffer = vec![0u8; file_size_compressed as usize];
+    file.read_exact(&mut buffer)?;
+
+    // Decrypt and optionally decompress
+    if !should_skip_decompression(file_name) {
+        decrypt_in_place(&mut buffer);
+        buffer = decompress_data(&buffer, file_size_uncompressed)?;
+    }
 
-        // Seek to the file's data
-        file.seek(SeekFrom::Start(self.file_pointer as u64))?;
+    Ok(buffer)
+}
 
-        // Read the raw compressed/encrypted bytes
-        let mut buffer = vec![0u8; self.file_size_compressed as usize];
+/// Read at most `len` bytes from the start of a file without extracting all of it.
+///
+/// The cipher is a stream cipher and deflate decodes front to back, so only a
+/// prefix of the stored bytes needs to be read, decrypted and inflated. The
+/// prefix grows until it inflates to `len` bytes or covers the whole file.
+pub fn extract_file_head(
+    archive_path: &Path,
+    file_name: &str,
+    file_pointer: u32,
+    file_size_compressed: u32,
+    len: usize,
+) -> io::Result<Vec<u8>> {
+    let mut file = File::open(archive_path)?;
+    let stored_size = file_size_compressed as usize;
+    let mut stored_len = stored_size.min(len.max(64));
+
+    loop {
+        file.seek(SeekFrom::Start(file_pointer as u64))?;
+        let mut buffer = vec![0u8; stored_len];
         file.read_exact(&mut buffer)?;
 
-        // Decrypt and optionally decompress
-        if !self.should_skip_decompression() {
-            self.decrypt_in_place(&mut buffer);
-            buffer = self.decompress_data(&buffer)?;
+        if should_skip_decompression(file_name) {
+            buffer.truncate(len);
+            return Ok(buffer);
         }
 
-        Ok(buffer)
-    }
+        decrypt_in_place(&mut buffer);
+        let mut output = Vec::with_capacity(len);
+        flate2::Decompress::new(false)
+            .decompress_vec(&buffer, &mut output, flate2::FlushDecompress::Sync)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Failed to decompress"))?;
 
-    /// Decrypt buffer in place using IPF decryption algorithm
-    fn decrypt_in_place(&self, buffer: &mut [u8]) {
-        if buffer.is_empty() {
-            return;
+        if output.len() >= len || stored_len == stored_size {
+            return Ok(output);
         }
+        stored_len = stored_size.min(stored_len * 4);
+    }
+}
 
-        let mut keys = self.generate_keys();
-        let steps = (buffer.len() - 1) / 2 + 1;
+/// Check if the file should not be decompressed based on extension
+fn should_skip_decompression(file_name: &str) -> bool {
+    let ignored_exts = [".fsb", ".jpg", ".mp3"];
+    file_name
+        .rsplit('.')
+        .next()
+        .map(|ext| format!(".{}", ext.to_ascii_lowercase()))
+        .map_or(false, |ext| ignored_exts.contains(&ext.as_str()))
+}
 
-        for i in 0..steps {
-            let v = (keys[2] & 0xFFFD) | 2;
-            let idx = i * 2;
-            if idx < buffer.len() {
-                buffer[idx] ^= ((v.wrapping_mul(v ^ 1)) >> 8) as u8;
-                self.update_keys(&mut keys, buffer[idx]);
-            }
-        }
+/// Decrypt buffer in place using IPF decryption algorithm
+fn decrypt_in_place(buffer: &mut [u8]) {
+    if buffer.is_empty() {
+        return;
     }
 
-    /// Decompress zlib/deflate data
-    fn decompress_data(&self, data: &[u8]) -> io::Result<Vec<u8>> {
-        let mut output = Vec::with_capacity(self.file_size_uncompressed as usize);
-        flate2::Decompress::new(false)
-            .decompress_vec(data, &mut output, flate2::FlushDecompress::Finish)
-            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Failed to decompress"))?;
-        Ok(output)
-    }
+    let mut keys = generate_keys();
+    let steps = (buffer.len() - 1) / 2 + 1;
 
-    /// Compute CRC32 for key update
-    fn compute_crc32(&self, crc: u32, b: u8) -> u32 {
-        CRC32_TABLE[((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8)
+    for i in 0..steps {
+        let v = (keys[2] & 0xFFFD) | 2;
+        let idx = i * 2;
+        if idx < buffer.len() {
+            buffer[idx] ^= ((v.wrapping_mul(v ^ 1)) >> 8) as u8;
+            update_keys(&mut keys, buffer[idx]);
+        }
     }
+}
 
-    /// Extract byte at a given position from u32 value
-    fn extract_byte_at(&self, value: u32, byte_index: usize) -> u8 {
-        (value >> (byte_index * 8)) as u8
-    }
+/// Decompress zlib/deflate data
+fn decompress_data(data: &[u8], file_size_uncompressed: u32) -> io::Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(file_size_uncompressed as usize);
+    flate2::Decompress::new(false)
+        .decompress_vec(data, &mut output, flate2::FlushDecompress::Finish)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Failed to decompress"))?;
+    Ok(output)
+}
 
-    /// Update decryption keys using a single byte
-    fn update_keys(&self, keys: &mut [u32; 3], byte: u8) {
-        keys[0] = self.compute_crc32(keys[0], byte);
-        keys[1] = 0x8088405u32
-            .wrapping_mul((keys[0] as u8 as u32).wrapping_add(keys[1]))
-            .wrapping_add(1);
-        keys[2] = self.compute_crc32(keys[2], self.extract_byte_at(keys[1], 3));
-    }
+/// Compute CRC32 for key update
+fn compute_crc32(crc: u32, b: u8) -> u32 {
+    CRC32_TABLE[((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8)
+}
 
-    /// Generate initial decryption keys from PASSWORD
-    fn generate_keys(&self) -> [u32; 3] {
-        let mut keys = [0x12345678, 0x23456789, 0x34567890];
-        for &b in PASSWORD.iter() {
-            self.update_keys(&mut keys, b);
-        }
-        keys
+/// Standard (IEEE) CRC32 of `data`, as stored in IPF and ZIP entries
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, &b| compute_crc32(crc, b))
+}
+
+/// Extract byte at a given position from u32 value
+fn extract_byte_at(value: u32, byte_index: usize) -> u8 {
+    (value >> (byte_index * 8)) as u8
+}
+
+/// Update decryption keys using a single byte
+fn update_keys(keys: &mut [u32; 3], byte: u8) {
+    keys[0] = compute_crc32(keys[0], byte);
+    keys[1] = 0x8088405u32
+        .wrapping_mul((keys[0] as u8 as u32).wrapping_add(keys[1]))
+        .wrapping_add(1);
+    keys[2] = compute_crc32(keys[2], extract_byte_at(keys[1], 3));
+}
+
+/// Generate initial decryption keys from PASSWORD
+fn generate_keys() -> [u32; 3] {
+    let mut keys = [0x12345678, 0x23456789, 0x34567890];
+    for &b in PASSWORD.iter() {
+        update_keys(&mut keys, b);
     }
+    keys
 }
 
 #[binread]
@@ -186,6 +247,9 @@ pub struct IPFRoot {
     #[br(seek_before = SeekFrom::Start(header.file_table_pointer as u64))]
     #[br(count = header.file_count)]
     pub file_table: Vec<IPFFileTable>,
+
+    #[brw(ignore)]
+    pub file_path: Option<PathBuf>,
 }
 
 impl IPFRoot {
@@ -198,6 +262,7 @@ impl IPFRoot {
         let mut root: IPFRoot = reader
             .read_le()
             .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("binrw error: {}", e)))?;
+        root.file_path = Some(path_ref.to_path_buf());
 
         for f in &mut root.file_table {
             f.file_path = Some(path_ref.to_path_buf());
@@ -388,6 +453,56 @@ pub fn print_hex_viewer(data: &[u8]) {
     }
 }
 
+/// Exclusive upper bounds of the uncompressed size buckets in `ExtensionStats::size_histogram`
+pub const SIZE_BUCKETS: [u32; 7] = [
+    1 << 10,
+    4 << 10,
+    16 << 10,
+    64 << 10,
+    256 << 10,
+    1 << 20,
+    4 << 20,
+];
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExtensionStats {
+    pub count: u32,
+    pub compressed_total: u64,
+    pub uncompressed_total: u64,
+    /// File counts per `SIZE_BUCKETS` bucket, the last entry counts everything larger
+    pub size_histogram: [u32; SIZE_BUCKETS.len() + 1],
+}
+
+impl ExtensionStats {
+    pub fn add(&mut self, compressed: u32, uncompressed: u32) {
+        self.count += 1;
+        self.compressed_total += compressed as u64;
+        self.uncompressed_total += uncompressed as u64;
+        let bucket = SIZE_BUCKETS
+            .iter()
+            .position(|&limit| uncompressed < limit)
+            .unwrap_or(SIZE_BUCKETS.len());
+        self.size_histogram[bucket] += 1;
+    }
+
+    pub fn merge(&mut self, other: &ExtensionStats) {
+        self.count += other.count;
+        self.compressed_total += other.compressed_total;
+        self.uncompressed_total += other.uncompressed_total;
+        for (bucket, count) in self.size_histogram.iter_mut().zip(other.size_histogram) {
+            *bucket += count;
+        }
+    }
+}
+
+/// Lowercase extension without the dot, "" when there is none
+pub fn file_extension(file_name: &str) -> String {
+    Path::new(file_name)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct FileSizeStats {
     pub count_duplicated: u32,
@@ -398,6 +513,8 @@ pub struct FileSizeStats {
     pub uncompressed_lowest: u32,
     pub uncompressed_highest: u32,
     pub uncompressed_avg: u32,
+    /// Every file version counted under its extension
+    pub extensions: BTreeMap<String, ExtensionStats>,
 }
 
 pub fn compute_ipf_file_stats(ipfs: &[IPFRoot]) -> FileSizeStats {
@@ -409,10 +526,15 @@ pub fn compute_ipf_file_stats(ipfs: &[IPFRoot]) -> FileSizeStats {
     let mut compressed_highest = 0u32;
     let mut uncompressed_lowest = u32::MAX;
     let mut uncompressed_highest = 0u32;
+    let mut extensions: BTreeMap<String, ExtensionStats> = BTreeMap::new();
 
     for ipf in ipfs {
         for file in &ipf.file_table {
             count_duplicated += 1;
+            extensions
+                .entry(file_extension(&file.directory_name))
+                .or_default()
+                .add(file.file_size_compressed, file.file_size_uncompressed);
             compressed_sum += file.file_size_compressed as u64;
             uncompressed_sum += file.file_size_uncompressed as u64;
 
@@ -434,6 +556,7 @@ pub fn compute_ipf_file_stats(ipfs: &[IPFRoot]) -> FileSizeStats {
             uncompressed_lowest: 0,
             uncompressed_highest: 0,
             uncompressed_avg: 0,
+            extensions,
         };
     }
 
@@ -446,6 +569,7 @@ pub fn compute_ipf_file_stats(ipfs: &[IPFRoot]) -> FileSizeStats {
         uncompressed_lowest,
         uncompressed_highest,
         uncompressed_avg: (uncompressed_sum / count_duplicated as u64) as u32,
+        extensions,
     }
 }
 
@@ -474,6 +598,25 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_extract_file_head_matches_full_extraction() -> io::Result<()> {
+        let root = IPFRoot::from_file("tests/379124_001001.ipf")?;
+
+        for entry in root.file_table.iter().take(8) {
+            let data = entry.extract_data()?;
+            let head = extract_file_head(
+                entry.file_path.as_ref().unwrap(),
+                &entry.directory_name,
+                entry.file_pointer,
+                entry.file_size_compressed,
+                64,
+            )?;
+            assert_eq!(head, data[..data.len().min(64)]);
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_ipf_file_index_37_is_valid_utf8() -> io::Result<()> {
         // Read IPFRoot from file
@@ -8,37 +8,99 @@ use std::{
     collections::{BTreeMap, HashMap},
     fs::File,
     io::{self, BufReader},
-    path::PathBuf,
-    sync::Arc,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
 };
 use tera::Tera;
 
-use category::Folder;
+use category::{FileRef, Folder, normalize_path};
 
+use crate::class_index::ClassIndex;
+use crate::collection::CollectionData;
+use crate::content_index::{CONTENT_INDEX_CACHE, ContentIndex, SharedContentIndex};
 use crate::ies::IESRoot;
+use crate::patch::PatchTimeline;
+use crate::recipe::RecipeGraph;
+use crate::spawn::{MapSpawns, SpawnData};
+use crate::texture::TextureIndex;
 
+mod animation;
 mod api;
 mod category;
+mod class_index;
+mod collection;
+mod content_index;
+mod dds;
 mod fsb;
 mod gltf;
+mod handler;
+mod http_cache;
 mod ies;
 mod ipf;
 mod mesh;
+mod patch;
+mod recipe;
+mod sniff;
+mod spawn;
 mod stb;
+mod texture;
 mod threedworld;
 mod tok;
 mod tsv;
+mod wavelet;
 mod web_data;
 mod xac;
 mod xml;
 mod xpm;
 mod xsm;
+mod zip;
 
 #[derive(Debug, Deserialize)]
 struct PathsConfig {
     game_root: String,
-    address: Option<String>, // e.g. "127.0.0.1"
-    port: Option<u16>,       // e.g. 8080
+    address: Option<String>,             // e.g. "127.0.0.1"
+    port: Option<u16>,                   // e.g. 8080
+    preview_cache_mb: Option<usize>,     // e.g. 256
+    content_index_cache: Option<String>, // e.g. "cache/content_index.cache"
+}
+
+/// Extract and parse the newest version of an IES table returned by a tree search
+fn load_latest_ies(results: &[(String, FileRef)]) -> Option<IESRoot> {
+    let (full_path, file_table) = results.last()?;
+    match file_table.extract_data() {
+        Ok(raw_data) => match IESRoot::from_bytes(&raw_data) {
+            Ok(ies) => Some(ies),
+            Err(e) => {
+                eprintln!("Failed to parse IESRoot from '{}': {}", full_path, e);
+                None
+            }
+        },
+        Err(e) => {
+            eprintln!("Failed to extract data from '{}': {}", full_path, e);
+            None
+        }
+    }
+}
+
+/// Parse the newest version of every per-map table in `results`, keyed by map class name
+fn load_latest_ies_per_map(
+    prefix: &str,
+    results: &[(String, FileRef)],
+) -> BTreeMap<String, IESRoot> {
+    let mut latest: BTreeMap<String, Vec<(String, FileRef)>> = BTreeMap::new();
+    for (full_path, file_table) in results {
+        if let Some(map) = spawn::map_name_from_table(prefix, file_table.name()) {
+            latest
+                .entry(map)
+                .or_default()
+                .push((full_path.clone(), *file_table));
+        }
+    }
+
+    latest
+        .into_iter()
+        .filter_map(|(map, versions)| Some((map, load_latest_ies(&versions)?)))
+        .collect()
 }
 
 fn load_game_root_from_json(file_path: &str) -> Result<PathsConfig, Box<dyn std::error::Error>> {
@@ -60,6 +122,15 @@ async fn main() -> io::Result<()> {
     let game_root = PathBuf::from(&config.game_root);
     let addr = config.address.unwrap_or_else(|| "127.0.0.1".to_string());
     let port = config.port.unwrap_or(8080);
+    let preview_cache_mb = config
+        .preview_cache_mb
+        .unwrap_or(http_cache::DEFAULT_PREVIEW_CACHE_MB);
+    let content_index_cache = PathBuf::from(
+        config
+            .content_index_cache
+            .as_deref()
+            .unwrap_or(CONTENT_INDEX_CACHE),
+    );
 
     // ---------------------------
     // Derive lang_folder from game_root
@@ -85,9 +156,25 @@ async fn main() -> io::Result<()> {
         ipf::group_file_tables_by_directory(all_files);
     file_stat_data.count_unique = grouped.len() as u32;
 
-    let folder_tree = Arc::new(category::build_tree(grouped));
+    let mut folder_tree = category::build_tree(grouped);
+    folder_tree.register_archives(&parsed_ipfs);
+    let folder_tree = Arc::new(folder_tree);
+    let tree_memory = folder_tree.memory_usage();
+    println!(
+        "File tree: {} folders, {} files in {} archives, ~{:.1} MiB",
+        tree_memory.folders,
+        tree_memory.files,
+        tree_memory.archives,
+        tree_memory.total_bytes as f64 / (1024.0 * 1024.0)
+    );
     println!("IPF parsing completed in {:.2?}", ipf_start.elapsed());
 
+    let patch_timeline = PatchTimeline::build(&folder_tree);
+    println!(
+        "Patch timeline: {} patch archives",
+        patch_timeline.patches.len()
+    );
+
     let file_find_start = Instant::now();
     println!("Find and get files...");
 
@@ -129,15 +216,15 @@ async fn main() -> io::Result<()> {
     let skillicon_xml = folder_tree.search_file_by_full_path("ui/baseskinset/skillicon.xml");
     let wholedicid_xml = folder_tree.search_file_by_full_path("language/wholedicid.xml");
 
-    let ability_name_ies = folder_tree.search_file_by_full_path("ies_ability/ability_ .ies");
-    let item_equip_name_ies = folder_tree.search_file_by_full_path("ies/item_equip_ .ies");
-    let anchor_name_ies = folder_tree.search_file_by_full_path("ies_drop/anchor_ .ies");
-    let gentype_name_ies = folder_tree.search_file_by_full_path("ies_mongem/gentype_ .ies");
-    let map_data_name_tok = folder_tree.search_file_by_full_path("bg/ .tok");
+    let ability_name_ies = folder_tree.search_files_glob("ies_ability/ability_*.ies");
+    let item_equip_name_ies = folder_tree.search_files_glob("ies/item_equip_*.ies");
+    let anchor_ies_list = folder_tree.search_files_by_prefix("ies_drop", "anchor_");
+    let gentype_ies_list = folder_tree.search_files_by_prefix("ies_mongem", "gentype_");
+    let map_data_name_tok = folder_tree.search_files_glob("bg/*.tok");
     let zonedropitemlist_name_ies =
-        folder_tree.search_file_by_full_path("ies_drop/zonedropitemlist_ .ies");
+        folder_tree.search_files_glob("ies_drop/zonedropitemlist_*.ies");
     let zonedropitemlist_f_name =
-        folder_tree.search_file_by_full_path("ies_drop/zonedropitemlist_f_ .ies");
+        folder_tree.search_files_glob("ies_drop/zonedropitemlist_f_*.ies");
 
     println!(
         "File find and get completed in {:.2?}",
@@ -147,7 +234,7 @@ async fn main() -> io::Result<()> {
     let mut mesh_map: HashMap<String, String> = HashMap::new();
 
     if let Some((full_path, file_table)) = xac_ies.last() {
-        println!("IPF Path : {:?}", file_table.file_path);
+        println!("IPF Path : {:?}", file_table.file_path());
         match file_table.extract_data() {
             Ok(raw_data) => match IESRoot::from_bytes(&raw_data) {
                 Ok(ies_data) => {
@@ -175,9 +262,144 @@ async fn main() -> io::Result<()> {
     // ---------------------------
     let lang_start = Instant::now();
     println!("Parsing language data...");
-    let (_etc_data, _item_data) = tsv::parse_language_data(&lang_folder)?;
+    let (etc_data, item_data) = tsv::parse_language_data(&lang_folder)?;
+    let mut dictionary = tsv::build_dictionary(&etc_data);
+    dictionary.extend(tsv::build_dictionary(&item_data));
     println!("Language parsing completed in {:.2?}", lang_start.elapsed());
 
+    // ---------------------------
+    // Build Item Index & Recipe Graph
+    // ---------------------------
+    let recipe_start = Instant::now();
+    println!("Building item index and recipe graph...");
+
+    let mut item_index = ClassIndex::new();
+    for (table, results) in [
+        ("item", &item_ies),
+        ("item_equip", &item_equip_ies),
+        ("item_gem", &item_gem_ies),
+        ("item_premium", &item_premium_ies),
+        ("item_quest", &item_quest_ies),
+        ("item_colorspray", &item_colorspray_ies),
+    ] {
+        if let Some(ies) = load_latest_ies(results) {
+            item_index.add_ies(table, &ies, &dictionary);
+        }
+    }
+
+    let recipe_graph = match load_latest_ies(&recipe_ies) {
+        Some(ies) => RecipeGraph::from_ies(&ies),
+        None => {
+            println!("File 'ies/recipe.ies' not found!");
+            RecipeGraph::default()
+        }
+    };
+
+    println!(
+        "Indexed {} items and {} recipes in {:.2?}",
+        item_index.len(),
+        recipe_graph.len(),
+        recipe_start.elapsed()
+    );
+
+    // ---------------------------
+    // Build Monster Index, Collections & Cards
+    // ---------------------------
+    let collection_start = Instant::now();
+    println!("Building monster index, collections and cards...");
+
+    let mut monster_index = ClassIndex::new();
+    for (table, results) in [
+        ("monster", &monster_ies),
+        ("monster_npc", &monster_npc_ies),
+        ("monster_event", &monster_event_ies),
+        ("monster_solo_dungeon", &monster_solo_dungeon_ies),
+    ] {
+        if let Some(ies) = load_latest_ies(results) {
+            monster_index.add_ies(table, &ies, &dictionary);
+        }
+    }
+
+    let monillust_images = match monillust_xml.last() {
+        Some((full_path, file_table)) => file_table
+            .extract_data()
+            .and_then(|data| xml::parse_skinset_images(&data))
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to parse '{}': {}", full_path, e);
+                Vec::new()
+            }),
+        None => Vec::new(),
+    };
+
+    let collection_data = CollectionData {
+        collections: load_latest_ies(&collection_ies)
+            .map(|ies| collection::parse_collections(&ies, &item_index, &dictionary))
+            .unwrap_or_default(),
+        cards: load_latest_ies(&cardbattle_ies)
+            .map(|ies| {
+                collection::parse_cards(&ies, &monster_index, &monillust_images, &dictionary)
+            })
+            .unwrap_or_default(),
+    };
+
+    println!(
+        "Indexed {} monsters, {} collections and {} cards in {:.2?}",
+        monster_index.len(),
+        collection_data.collections.len(),
+        collection_data.cards.len(),
+        collection_start.elapsed()
+    );
+
+    // ---------------------------
+    // Build Map Spawns & Dungeon Rewards
+    // ---------------------------
+    let spawn_start = Instant::now();
+    println!("Parsing map spawns and dungeon rewards...");
+
+    let mut map_index = ClassIndex::new();
+    if let Some(ies) = load_latest_ies(&map_ies) {
+        map_index.add_ies("map", &ies, &dictionary);
+    }
+
+    // Lowercased map -> (map, gentype, anchor); a map may ship only one of the two
+    // tables, and their names may differ in case
+    let mut map_tables: BTreeMap<String, (String, Option<IESRoot>, Option<IESRoot>)> =
+        BTreeMap::new();
+    for (map, gentype) in load_latest_ies_per_map("gentype_", &gentype_ies_list) {
+        map_tables
+            .entry(map.to_lowercase())
+            .or_insert_with(|| (map, None, None))
+            .1 = Some(gentype);
+    }
+    for (map, anchor) in load_latest_ies_per_map("anchor_", &anchor_ies_list) {
+        map_tables
+            .entry(map.to_lowercase())
+            .or_insert_with(|| (map, None, None))
+            .2 = Some(anchor);
+    }
+
+    let mut spawn_data = SpawnData::default();
+    for (key, (map, gentype, anchor)) in map_tables {
+        spawn_data.maps.insert(
+            key,
+            MapSpawns {
+                name: map_index.display_name(&map),
+                spawns: spawn::parse_map_spawns(gentype.as_ref(), anchor.as_ref(), &monster_index),
+                map,
+            },
+        );
+    }
+    spawn_data.dungeon_rewards = load_latest_ies(&reward_indun_ies)
+        .map(|ies| spawn::parse_dungeon_rewards(&ies, &item_index))
+        .unwrap_or_default();
+
+    println!(
+        "Parsed spawns for {} maps and {} dungeon rewards in {:.2?}",
+        spawn_data.maps.len(),
+        spawn_data.dungeon_rewards.len(),
+        spawn_start.elapsed()
+    );
+
     // ---------------------------
     // Parse Duplicates
     // ---------------------------
@@ -196,15 +418,25 @@ async fn main() -> io::Result<()> {
     let xpm_duplicates = Arc::new(xml::parse_duplicates_xml(
         &game_root.join("release/xpm_duplicates.xml"),
     )?);
-    let dds_duplicates = Arc::new(xml::parse_duplicates_xml(
-        &game_root.join("release/dds_duplicates.xml"),
-    )?);
+    // Normalized like tree paths, the texture resolver looks them up directly
+    let dds_duplicates = Arc::new(
+        xml::parse_duplicates_xml(&game_root.join("release/dds_duplicates.xml"))?
+            .into_iter()
+            .map(|(duplicate, source)| (normalize_path(&duplicate), normalize_path(&source)))
+            .collect(),
+    );
 
     println!(
         "Duplicates parsing completed in {:.2?}",
         dup_start.elapsed()
     );
 
+    let texture_index = TextureIndex::build(&folder_tree, Arc::clone(&dds_duplicates));
+    println!(
+        "Texture index: {} image file names",
+        texture_index.name_count()
+    );
+
     let duplicates_data = web::Data::new(api::Duplicates {
         xac: xac_duplicates,
         xsm: xsm_duplicates,
@@ -213,6 +445,29 @@ async fn main() -> io::Result<()> {
         dds: dds_duplicates,
     });
 
+    // ---------------------------
+    // Build Content Index in the background
+    // ---------------------------
+    let content_index: SharedContentIndex = Arc::new(RwLock::new(None));
+    {
+        let folder_tree = Arc::clone(&folder_tree);
+        let content_index = Arc::clone(&content_index);
+        std::thread::spawn(move || {
+            let content_start = Instant::now();
+            println!("Building content index in the background...");
+            let index = ContentIndex::load_or_build(&folder_tree, &content_index_cache);
+            println!(
+                "Content index ready: {} files, {} words in {:.2?}",
+                index.doc_count(),
+                index.word_count(),
+                content_start.elapsed()
+            );
+            if let Ok(mut shared) = content_index.write() {
+                *shared = Some(index);
+            }
+        });
+    }
+
     // ---------------------------
     // Prepare Actix Web Server
     // ---------------------------
@@ -222,6 +477,21 @@ async fn main() -> io::Result<()> {
     let tera = Tera::new("templates/**/*").expect("Failed to initialize Tera templates");
     let tera_data = web::Data::new(tera);
     let mesh_map_data = web::Data::new(mesh_map);
+    let class_indexes_data = web::Data::new(api::ClassIndexes {
+        items: Arc::new(item_index),
+        monsters: Arc::new(monster_index),
+        maps: Arc::new(map_index),
+    });
+    let spawn_data = web::Data::new(spawn_data);
+    let content_index_data = web::Data::new(content_index);
+    let collection_data = web::Data::new(collection_data);
+    let recipe_graph_data = web::Data::new(recipe_graph);
+    let patch_timeline_data = web::Data::new(patch_timeline);
+    let format_registry_data = web::Data::new(handler::FormatRegistry::with_defaults());
+    let texture_index_data = web::Data::new(texture_index);
+    let preview_cache_data = web::Data::new(http_cache::PreviewCache::new(
+        preview_cache_mb * 1024 * 1024,
+    ));
 
     println!("Starting server at http://{}:{} ...\n", addr, port);
 
@@ -233,9 +503,19 @@ async fn main() -> io::Result<()> {
             .app_data(tera_data.clone())
             .app_data(file_stats.clone())
             .app_data(mesh_map_data.clone())
+            .app_data(class_indexes_data.clone())
+            .app_data(recipe_graph_data.clone())
+            .app_data(collection_data.clone())
+            .app_data(spawn_data.clone())
+            .app_data(content_index_data.clone())
+            .app_data(patch_timeline_data.clone())
+            .app_data(format_registry_data.clone())
+            .app_data(texture_index_data.clone())
+            .app_data(preview_cache_data.clone())
             .configure(api::init_routes)
             .service(web_data::index)
             .service(web_data::home)
+            .service(web_data::recipe_page)
     })
     .bind((addr, port))?
     .run()
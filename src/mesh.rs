@@ -1,8 +1,36 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use serde::{Deserialize, Serialize};
 
-use crate::xac::{XACAttribute, XACChunk, XACChunkData};
+use crate::animation::AnimationClip;
+use crate::texture::{ResolvedTexture, TextureResolver};
+
+use crate::xac::{
+    FileColor, FileQuaternion, FileVector3, XACAttribute, XACChunk, XACChunkData,
+    XACFXBitmapParameter, XACFXBoolParameter, XACFXColorParameter, XACFXFloatParameter,
+    XACFXIntParameter, XACMaterialLayer, XACPMorphTarget, XACPMorphTargetMeshDeltas, XACRoot,
+    XACSkinInfluence, XACSkinningInfoTableEntry, XACStandardMaterialLayer2,
+};
+
+/// 4x4 matrix, column-major like glTF
+pub type Matrix4 = [f32; 16];
+
+pub const IDENTITY_MATRIX: Matrix4 = [
+    1.0, 0.0, 0.0, 0.0, //
+    0.0, 1.0, 0.0, 0.0, //
+    0.0, 0.0, 1.0, 0.0, //
+    0.0, 0.0, 0.0, 1.0,
+];
+
+/// XAC node flag bits
+const NODE_FLAG_INCLUDE_IN_BOUNDS: u8 = 1 << 0;
+const NODE_FLAG_ATTACHMENT: u8 = 1 << 1;
+
+/// Influences kept per vertex, the most glTF's JOINTS_0/WEIGHTS_0 can hold
+const MAX_INFLUENCES: usize = 4;
+
+/// (skeleton node, weight) influences per original vertex
+type SkinTable = Vec<Vec<(u32, f32)>>;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Vector2 {
@@ -36,7 +64,15 @@ pub struct RGBAColor {
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct SubMesh {
     pub name: String,
+    /// Diffuse map of the submesh's material, for viewers drawing a single texture.
+    /// The archive path once resolved, the name from the material otherwise.
     pub textures: String,
+    /// Archive entry of the diffuse map, see `Scene::resolve_textures`
+    #[serde(default)]
+    pub texture: Option<ResolvedTexture>,
+    /// Index into `Scene::materials`
+    #[serde(default)]
+    pub material_index: Option<usize>,
     pub positions: Vec<Vector3>,
     pub normals: Vec<Vector3>,
     pub tangents: Vec<Vector4>,
@@ -46,6 +82,284 @@ pub struct SubMesh {
     pub colors128: Vec<RGBAColor>,
     pub original_vertex_numbers: Vec<u32>,
     pub indices: Vec<u32>,
+    /// Skeleton node indices of up to four influences per vertex, empty for rigid meshes
+    #[serde(default)]
+    pub joints: Vec<[u16; 4]>,
+    /// Influence weights matching `joints`, summing to 1
+    #[serde(default)]
+    pub weights: Vec<[f32; 4]>,
+    /// Every morph target of the mesh, in the same order on each of its submeshes
+    #[serde(default)]
+    pub morph_targets: Vec<MorphTarget>,
+}
+
+/// Blend shape of one submesh, deltas are dense over its vertices
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct MorphTarget {
+    pub name: String,
+    /// Slider range of the weight
+    pub range_min: f32,
+    pub range_max: f32,
+    pub position_deltas: Vec<Vector3>,
+    pub normal_deltas: Vec<Vector3>,
+    pub tangent_deltas: Vec<Vector3>,
+}
+
+/// Texture slot a material layer feeds
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MapType {
+    Ambient,
+    Diffuse,
+    Specular,
+    Opacity,
+    Bump,
+    SelfIllumination,
+    Environment,
+    Shadow,
+}
+
+impl MapType {
+    /// Standard material layers use the `XACMaterialLayer` ids
+    fn from_layer_id(id: u8) -> Option<Self> {
+        match id {
+            x if x == XACMaterialLayer::XACLayeridAmbient as u8 => Some(MapType::Ambient),
+            x if x == XACMaterialLayer::XACLayeridDiffuse as u8 => Some(MapType::Diffuse),
+            x if x == XACMaterialLayer::XACLayeridSpecular as u8 => Some(MapType::Specular),
+            x if x == XACMaterialLayer::XACLayeridOpacity as u8 => Some(MapType::Opacity),
+            x if x == XACMaterialLayer::XACLayeridBump as u8 => Some(MapType::Bump),
+            x if x == XACMaterialLayer::XACLayeridSelfillum as u8 => {
+                Some(MapType::SelfIllumination)
+            }
+            x if x == XACMaterialLayer::XACLayeridEnvironment as u8 => Some(MapType::Environment),
+            _ => None,
+        }
+    }
+
+    /// FX materials name their bitmaps after the shader sampler, e.g. `DiffuseTex`
+    fn from_fx_param(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "diffusetex" => Some(MapType::Diffuse),
+            "speculartex" | "spectex" => Some(MapType::Specular),
+            "opacitytex" | "alphatex" => Some(MapType::Opacity),
+            "bumptex" | "normaltex" => Some(MapType::Bump),
+            "envtex" => Some(MapType::Environment),
+            "shadowtex" => Some(MapType::Shadow),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TextureMap {
+    pub map_type: MapType,
+    pub texture: String,
+    /// Blend amount, between 0 and 1
+    pub amount: f32,
+    pub offset: Vector2,
+    pub tiling: Vector2,
+    /// UV rotation in radians
+    pub rotation: f32,
+    /// Archive entry of `texture`, see `Scene::resolve_textures`
+    #[serde(default)]
+    pub resolved: Option<ResolvedTexture>,
+}
+
+impl TextureMap {
+    fn new(map_type: MapType, texture: String) -> Self {
+        TextureMap {
+            map_type,
+            texture,
+            amount: 1.0,
+            offset: Vector2::default(),
+            tiling: Vector2 { x: 1.0, y: 1.0 },
+            rotation: 0.0,
+            resolved: None,
+        }
+    }
+
+    /// Layers of an unsupported type or without a texture are skipped
+    fn from_layer(map_type: u8, texture: &str, texture_path: &str) -> Option<Self> {
+        let map_type = MapType::from_layer_id(map_type)?;
+        (!texture.is_empty())
+            .then(|| TextureMap::new(map_type, format!("{}{}", texture_path, texture)))
+    }
+}
+
+/// Standard material colors and maps merged with the parameters of FX materials
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Material {
+    pub name: String,
+    pub ambient: RGBAColor,
+    pub diffuse: RGBAColor,
+    pub specular: RGBAColor,
+    pub emissive: RGBAColor,
+    pub shine: f32,
+    pub shine_strength: f32,
+    /// 1.0 = fully opaque
+    pub opacity: f32,
+    pub double_sided: bool,
+    pub alpha_blend: bool,
+    /// Alpha test threshold between 0 and 1, `None` when alpha testing is off
+    pub alpha_cutoff: Option<f32>,
+    pub maps: Vec<TextureMap>,
+    /// FX shader, empty for standard materials
+    pub effect_file: String,
+    pub shader_technique: String,
+    pub int_params: BTreeMap<String, i32>,
+    pub float_params: BTreeMap<String, f32>,
+    pub color_params: BTreeMap<String, RGBAColor>,
+    pub bool_params: BTreeMap<String, bool>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        let color = |v| RGBAColor {
+            r: v,
+            g: v,
+            b: v,
+            a: 1.0,
+        };
+        Material {
+            name: String::new(),
+            ambient: color(0.0),
+            diffuse: color(1.0),
+            specular: color(0.0),
+            emissive: color(0.0),
+            shine: 0.0,
+            shine_strength: 0.0,
+            opacity: 1.0,
+            double_sided: false,
+            alpha_blend: false,
+            alpha_cutoff: None,
+            maps: Vec::new(),
+            effect_file: String::new(),
+            shader_technique: String::new(),
+            int_params: BTreeMap::new(),
+            float_params: BTreeMap::new(),
+            color_params: BTreeMap::new(),
+            bool_params: BTreeMap::new(),
+        }
+    }
+}
+
+impl Material {
+    /// First map feeding the given slot
+    pub fn map(&self, map_type: MapType) -> Option<&TextureMap> {
+        self.maps.iter().find(|map| map.map_type == map_type)
+    }
+
+    /// Colors and flags shared by every standard material version,
+    /// as `[ambient, diffuse, specular, emissive]`
+    fn standard(
+        name: &str,
+        colors: [&FileColor; 4],
+        shine: f32,
+        shine_strength: f32,
+        opacity: f32,
+        double_sided: u8,
+    ) -> Self {
+        let [ambient, diffuse, specular, emissive] = colors.map(|c| RGBAColor {
+            r: c.r,
+            g: c.g,
+            b: c.b,
+            a: c.a,
+        });
+        Material {
+            name: name.to_string(),
+            ambient,
+            diffuse,
+            specular,
+            emissive,
+            shine,
+            shine_strength,
+            opacity,
+            double_sided: double_sided != 0,
+            alpha_blend: opacity < 1.0,
+            ..Material::default()
+        }
+    }
+
+    fn add_layers(&mut self, layers: &[XACStandardMaterialLayer2], texture_path: &str) {
+        for layer in layers {
+            if let Some(map) =
+                TextureMap::from_layer(layer.map_type, &layer.texture_name, texture_path)
+            {
+                self.maps.push(TextureMap {
+                    amount: layer.amount,
+                    offset: Vector2 {
+                        x: layer.u_offset,
+                        y: layer.v_offset,
+                    },
+                    tiling: Vector2 {
+                        x: layer.u_tiling,
+                        y: layer.v_tiling,
+                    },
+                    rotation: layer.rotation_radians,
+                    ..map
+                });
+            }
+        }
+    }
+
+    fn add_fx_params(
+        &mut self,
+        ints: Option<&Vec<XACFXIntParameter>>,
+        floats: Option<&Vec<XACFXFloatParameter>>,
+        colors: Option<&Vec<XACFXColorParameter>>,
+        bools: Option<&Vec<XACFXBoolParameter>>,
+    ) {
+        for param in ints.into_iter().flatten() {
+            self.int_params.insert(param.name.clone(), param.value);
+        }
+        for param in floats.into_iter().flatten() {
+            self.float_params.insert(param.name.clone(), param.value);
+        }
+        for param in colors.into_iter().flatten() {
+            let c = &param.value;
+            self.color_params.insert(
+                param.name.clone(),
+                RGBAColor {
+                    r: c.r,
+                    g: c.g,
+                    b: c.b,
+                    a: c.a,
+                },
+            );
+        }
+        for param in bools.into_iter().flatten() {
+            self.bool_params
+                .insert(param.name.clone(), param.value != 0);
+        }
+
+        // Render state the game's shaders read from their parameters
+        let flag = |name: &str| self.bool_params.get(name).copied().unwrap_or(false);
+        let double_sided = flag("g_isTwoSideOn");
+        let alpha_blend = flag("g_isAlphaBlendOn");
+        let alpha_test = flag("g_isAlphaTestOn");
+        self.double_sided |= double_sided;
+        self.alpha_blend |= alpha_blend;
+        if alpha_test {
+            let threshold = self.int_params.get("g_alphaTestValue").copied();
+            self.alpha_cutoff = Some(threshold.map_or(0.5, |v| v as f32 / 255.0));
+        }
+    }
+
+    /// Bitmaps without a known slot or set to "None" are skipped
+    fn add_fx_bitmaps(&mut self, bitmaps: Option<&Vec<XACFXBitmapParameter>>, texture_path: &str) {
+        for bitmap in bitmaps.into_iter().flatten() {
+            let texture = bitmap.value_name.as_str();
+            if texture.is_empty() || texture.eq_ignore_ascii_case("none") {
+                continue;
+            }
+            if let Some(map_type) = MapType::from_fx_param(&bitmap.name) {
+                self.maps.push(TextureMap::new(
+                    map_type,
+                    format!("{}{}", texture_path, texture),
+                ));
+            }
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -62,82 +376,627 @@ pub struct SceneNode {
     pub scale: Option<Vector3>,    // scale
     pub model: Option<Model>,      // optional model at this node
     pub children: Vec<SceneNode>,  // nested nodes
+    /// Index of the XAC skeleton node this was built from
+    #[serde(default)]
+    pub node_index: Option<u32>,
+    /// Bind pose transform relative to the scene root, column-major
+    #[serde(default)]
+    pub world_matrix: Option<Matrix4>,
+    /// Attachment point for other actors (weapons, effects)
+    #[serde(default)]
+    pub is_attachment: bool,
+    /// Whether the node counts towards the actor's bounding box
+    #[serde(default)]
+    pub include_in_bounds: bool,
 }
 
 // Root scene can either be a single node or multiple nodes
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Scene {
     pub root_nodes: Vec<SceneNode>,
+    /// Materials referenced by `SubMesh::material_index`
+    #[serde(default)]
+    pub materials: Vec<Material>,
+    /// Motions bound to the skeleton nodes, see `Scene::add_animation`
+    #[serde(default)]
+    pub animations: Vec<AnimationClip>,
     pub position: Option<Vector3>, // translation
     pub rotation: Option<Vector4>, // quaternion x,y,z,w
     pub scale: Option<Vector3>,    // scale
 }
 
+/// One skeleton node, whichever chunk version it came from
+struct XacBone<'a> {
+    name: &'a str,
+    parent: Option<usize>,
+    position: Vector3,
+    rotation: Vector4,
+    scale: Vector3,
+    flags: u8,
+}
+
+impl<'a> XacBone<'a> {
+    /// Mirror X to match the vertex data, see `parse_vertex_data`
+    fn new(
+        name: &'a str,
+        parent_index: u32,
+        pos: &FileVector3,
+        quat: &FileQuaternion,
+        scale: &FileVector3,
+        flags: u8,
+    ) -> Self {
+        XacBone {
+            name,
+            parent: (parent_index != u32::MAX).then_some(parent_index as usize),
+            position: Vector3 {
+                x: -pos.x,
+                y: pos.y,
+                z: pos.z,
+            },
+            rotation: Vector4 {
+                x: quat.x,
+                y: -quat.y,
+                z: -quat.z,
+                w: quat.w,
+            },
+            scale: Vector3 {
+                x: scale.x,
+                y: scale.y,
+                z: scale.z,
+            },
+            flags,
+        }
+    }
+}
+
 // --- XAC to Scene converter ---
 impl Scene {
-    pub fn from_xac_root(xac: &crate::xac::XACRoot, texture_path: String) -> Self {
+    pub fn from_xac_root(xac: &XACRoot, texture_path: String) -> Self {
         let mut scene = Scene::default();
+        let bones = Self::xac_bones(xac);
 
+        // Scene index of the materials of each LOD
+        let mut lod_materials: HashMap<u32, Vec<usize>> = HashMap::new();
+        for (index, (lod, material)) in Self::xac_materials(xac, &texture_path)
+            .into_iter()
+            .enumerate()
+        {
+            lod_materials.entry(lod).or_default().push(index);
+            scene.materials.push(material);
+        }
+
+        // node index -> models owned by that node
+        let mut node_models: HashMap<usize, Vec<Model>> = HashMap::new();
         for entry in &xac.chunks {
             if entry.chunk.chunk_id != XACChunk::XACChunkMesh as u32 {
                 continue;
             }
 
-            let model = match entry.chunk.version {
-                1 => {
-                    if let XACChunkData::XACMesh(mesh) = &entry.chunk_data {
-                        let submeshes = Scene::parse_vertex_data(
-                            &mesh.vertex_attribute_layer,
-                            &mesh.sub_meshes,
-                            xac.get_texture_names_with_path(texture_path.clone()),
-                        );
-                        Some(Model {
-                            name: String::new(),
-                            submeshes,
-                        })
-                    } else {
-                        None
-                    }
-                }
-                2 => {
-                    if let XACChunkData::XACMesh2(mesh) = &entry.chunk_data {
-                        let submeshes = Scene::parse_vertex_data(
-                            &mesh.vertex_attribute_layer,
-                            &mesh.sub_meshes,
-                            xac.get_texture_names_with_path(texture_path.clone()),
-                        );
-                        Some(Model {
-                            name: String::new(),
-                            submeshes,
-                        })
-                    } else {
-                        None
-                    }
-                }
+            let mesh = match &entry.chunk_data {
+                XACChunkData::XACMesh(mesh) if entry.chunk.version == 1 => Some((
+                    mesh.node_index,
+                    0,
+                    &mesh.vertex_attribute_layer,
+                    &mesh.sub_meshes,
+                )),
+                XACChunkData::XACMesh2(mesh) if entry.chunk.version == 2 => Some((
+                    mesh.node_index,
+                    mesh.lod,
+                    &mesh.vertex_attribute_layer,
+                    &mesh.sub_meshes,
+                )),
                 _ => None,
             };
+            let Some((node_index, lod, layers, sub_meshes)) = mesh else {
+                continue;
+            };
 
-            // Create a SceneNode to hold the model
-            if let Some(model) = model {
-                let node = SceneNode {
+            let skin = Self::xac_skin(xac, node_index, lod);
+            let mut submeshes = Scene::parse_vertex_data(layers, sub_meshes, skin.as_deref());
+            for (submesh, xac_submesh) in submeshes.iter_mut().zip(sub_meshes) {
+                submesh.material_index = lod_materials
+                    .get(&lod)
+                    .and_then(|indices| indices.get(xac_submesh.material_index as usize))
+                    .copied();
+                submesh.textures = submesh
+                    .material_index
+                    .and_then(|index| scene.materials[index].map(MapType::Diffuse))
+                    .map(|map| map.texture.clone())
+                    .unwrap_or_default();
+            }
+            Self::apply_morph_targets(xac, node_index, lod, &mut submeshes);
+            let owner = bones.get(node_index as usize);
+            let model = Model {
+                name: owner.map(|bone| bone.name.to_string()).unwrap_or_default(),
+                submeshes,
+            };
+
+            if owner.is_some() {
+                node_models
+                    .entry(node_index as usize)
+                    .or_default()
+                    .push(model);
+            } else {
+                // Mesh without a skeleton node, keep it on a root node
+                scene.root_nodes.push(SceneNode {
                     name: model.name.clone(),
                     model: Some(model),
-                    children: Vec::new(),
-                    position: None,
-                    rotation: None,
-                    scale: None,
-                };
+                    ..SceneNode::default()
+                });
+            }
+        }
 
-                scene.root_nodes.push(node);
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); bones.len()];
+        let mut roots = Vec::new();
+        for (index, bone) in bones.iter().enumerate() {
+            match bone.parent {
+                Some(parent) if parent < bones.len() && parent != index => {
+                    children[parent].push(index)
+                }
+                _ => roots.push(index),
             }
         }
+
+        for root in roots {
+            let node =
+                Self::build_node(root, &bones, &children, &mut node_models, &IDENTITY_MATRIX);
+            scene.root_nodes.push(node);
+        }
+        // Owners caught in a parent cycle are unreachable from any root
+        let mut orphans: Vec<(usize, Vec<Model>)> = node_models.into_iter().collect();
+        orphans.sort_by_key(|(index, _)| *index);
+        for model in orphans.into_iter().flat_map(|(_, models)| models) {
+            scene.root_nodes.push(SceneNode {
+                name: model.name.clone(),
+                model: Some(model),
+                ..SceneNode::default()
+            });
+        }
         scene
     }
 
+    /// Look up every material map in the folder tree, and point each submesh at
+    /// the archive entry of its diffuse map
+    pub fn resolve_textures(&mut self, resolver: &mut TextureResolver) {
+        for material in &mut self.materials {
+            for map in &mut material.maps {
+                map.resolved = resolver.resolve(&map.texture);
+            }
+        }
+
+        fn visit(node: &mut SceneNode, materials: &[Material]) {
+            let submeshes = node.model.iter_mut().flat_map(|m| &mut m.submeshes);
+            for submesh in submeshes {
+                let resolved = submesh
+                    .material_index
+                    .and_then(|index| materials.get(index))
+                    .and_then(|material| material.map(MapType::Diffuse))
+                    .and_then(|map| map.resolved.clone());
+                if let Some(resolved) = &resolved {
+                    submesh.textures = resolved.path.clone();
+                }
+                submesh.texture = resolved;
+            }
+            for child in &mut node.children {
+                visit(child, materials);
+            }
+        }
+        for node in &mut self.root_nodes {
+            visit(node, &self.materials);
+        }
+    }
+
+    /// Bind each track to the skeleton node of the same name, ignoring case.
+    /// Tracks of nodes the skeleton lacks are dropped.
+    pub fn add_animation(&mut self, mut animation: AnimationClip) {
+        fn visit(node: &SceneNode, nodes: &mut HashMap<String, u32>) {
+            if let Some(index) = node.node_index {
+                nodes.entry(node.name.to_lowercase()).or_insert(index);
+            }
+            for child in &node.children {
+                visit(child, nodes);
+            }
+        }
+        let mut nodes = HashMap::new();
+        for node in &self.root_nodes {
+            visit(node, &mut nodes);
+        }
+
+        animation.tracks.retain_mut(|track| {
+            track.node_index = nodes.get(&track.name.to_lowercase()).copied();
+            track.node_index.is_some()
+        });
+        self.animations.push(animation);
+    }
+
+    /// Standard and FX materials in file order, each tagged with its LOD.
+    /// Meshes number the materials of their own LOD from zero.
+    fn xac_materials(xac: &XACRoot, texture_path: &str) -> Vec<(u32, Material)> {
+        let mut materials = Vec::new();
+        let mut layers = Vec::new();
+        for entry in &xac.chunks {
+            match &entry.chunk_data {
+                XACChunkData::XACStandardMaterial(m) => materials.push((
+                    0,
+                    Material::standard(
+                        &m.material_name,
+                        [&m.ambient, &m.diffuse, &m.specular, &m.emissive],
+                        m.shine,
+                        m.shine_strength,
+                        m.opacity,
+                        m.double_sided,
+                    ),
+                )),
+                XACChunkData::XACStandardMaterial2(m) => {
+                    let mut material = Material::standard(
+                        &m.material_name,
+                        [&m.ambient, &m.diffuse, &m.specular, &m.emissive],
+                        m.shine,
+                        m.shine_strength,
+                        m.opacity,
+                        m.double_sided,
+                    );
+                    material.add_layers(&m.standard_material_layer2, texture_path);
+                    materials.push((0, material));
+                }
+                XACChunkData::XACStandardMaterial3(m) => {
+                    let mut material = Material::standard(
+                        &m.material_name,
+                        [&m.ambient, &m.diffuse, &m.specular, &m.emissive],
+                        m.shine,
+                        m.shine_strength,
+                        m.opacity,
+                        m.double_sided,
+                    );
+                    material.add_layers(&m.standard_material_layer2, texture_path);
+                    materials.push((m.lod, material));
+                }
+                // Older files store layers in their own chunks after the materials
+                XACChunkData::XACStandardMaterialLayer(layer) => layers.push(layer),
+                XACChunkData::XACFXMaterial(m) => {
+                    let mut material = Material {
+                        name: m.name.clone(),
+                        effect_file: m.effect_file.clone(),
+                        shader_technique: m.shader_technique.clone(),
+                        ..Material::default()
+                    };
+                    material.add_fx_params(
+                        m.xac_fx_int_parameter.as_ref(),
+                        m.xac_fx_float_parameter.as_ref(),
+                        m.xac_fx_color_parameter.as_ref(),
+                        None,
+                    );
+                    material.add_fx_bitmaps(m.xac_fx_bitmap_parameter.as_ref(), texture_path);
+                    materials.push((0, material));
+                }
+                XACChunkData::XACFXMaterial2(m) => {
+                    let mut material = Material {
+                        name: m.name.clone(),
+                        effect_file: m.effect_file.clone(),
+                        shader_technique: m.shader_technique.clone(),
+                        ..Material::default()
+                    };
+                    material.add_fx_params(
+                        m.xac_fx_int_parameter.as_ref(),
+                        m.xac_fx_float_parameter.as_ref(),
+                        m.xac_fx_color_parameter.as_ref(),
+                        m.xac_fx_bool_parameter.as_ref(),
+                    );
+                    material.add_fx_bitmaps(m.xac_fx_bitmap_parameter.as_ref(), texture_path);
+                    materials.push((0, material));
+                }
+                XACChunkData::XACFXMaterial3(m) => {
+                    let mut material = Material {
+                        name: m.name.clone(),
+                        effect_file: m.effect_file.clone(),
+                        shader_technique: m.shader_technique.clone(),
+                        ..Material::default()
+                    };
+                    material.add_fx_params(
+                        m.xac_fx_int_parameter.as_ref(),
+                        m.xac_fx_float_parameter.as_ref(),
+                        m.xac_fx_color_parameter.as_ref(),
+                        m.xac_fx_bool_parameter.as_ref(),
+                    );
+                    material.add_fx_bitmaps(m.xac_fx_bitmap_parameter.as_ref(), texture_path);
+                    materials.push((m.lod, material));
+                }
+                _ => {}
+            }
+        }
+
+        // Layer chunks carry no LOD, their material number indexes the first LOD
+        for layer in layers {
+            let Some(map) =
+                TextureMap::from_layer(layer.map_type, &layer.texture_name, texture_path)
+            else {
+                continue;
+            };
+            let Some((_, material)) = materials
+                .iter_mut()
+                .filter(|(lod, _)| *lod == 0)
+                .nth(layer.material_number as usize)
+            else {
+                continue;
+            };
+            material.maps.push(TextureMap {
+                amount: layer.amount,
+                offset: Vector2 {
+                    x: layer.u_offset,
+                    y: layer.v_offset,
+                },
+                tiling: Vector2 {
+                    x: layer.u_tiling,
+                    y: layer.v_tiling,
+                },
+                rotation: layer.rotation_radians,
+                ..map
+            });
+        }
+        materials
+    }
+
+    /// Skeleton nodes in file order, from either the XACNodes chunk or one chunk per node
+    fn xac_bones(xac: &XACRoot) -> Vec<XacBone<'_>> {
+        let mut bones = Vec::new();
+        for entry in &xac.chunks {
+            match &entry.chunk_data {
+                XACChunkData::XACNodes(nodes) => {
+                    bones.extend(nodes.xac_node.iter().map(|n| {
+                        XacBone::new(
+                            &n.node_name,
+                            n.parent_index,
+                            &n.local_pos,
+                            &n.local_quat,
+                            &n.local_scale,
+                            n.node_flags,
+                        )
+                    }));
+                }
+                XACChunkData::XACNode(n) => bones.push(XacBone::new(
+                    &n.node_name,
+                    n.parent_index,
+                    &n.local_pos,
+                    &n.local_quat,
+                    &n.local_scale,
+                    NODE_FLAG_INCLUDE_IN_BOUNDS,
+                )),
+                XACChunkData::XACNode2(n) => bones.push(XacBone::new(
+                    &n.node_name,
+                    n.parent_index,
+                    &n.local_pos,
+                    &n.local_quat,
+                    &n.local_scale,
+                    n.node_flags,
+                )),
+                XACChunkData::XACNode3(n) => bones.push(XacBone::new(
+                    &n.node_name,
+                    n.parent_index,
+                    &n.local_pos,
+                    &n.local_quat,
+                    &n.local_scale,
+                    n.node_flags,
+                )),
+                XACChunkData::XACNode4(n) => bones.push(XacBone::new(
+                    &n.node_name,
+                    n.parent_index,
+                    &n.local_pos,
+                    &n.local_quat,
+                    &n.local_scale,
+                    n.node_flags,
+                )),
+                _ => {}
+            }
+        }
+        bones
+    }
+
+    /// Influences per original vertex from the skinning chunk of one mesh
+    fn xac_skin(xac: &XACRoot, node_index: u32, lod: u32) -> Option<SkinTable> {
+        // Version 1 lists influences per vertex, later ones index a shared array
+        fn from_table(
+            influences: &[XACSkinInfluence],
+            table: &[XACSkinningInfoTableEntry],
+        ) -> SkinTable {
+            table
+                .iter()
+                .map(|entry| {
+                    let start = entry.start_index as usize;
+                    let end = start + entry.num_elements as usize;
+                    influences
+                        .get(start..end)
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|i| (i.node_number, i.weight))
+                        .collect()
+                })
+                .collect()
+        }
+
+        xac.chunks.iter().find_map(|entry| match &entry.chunk_data {
+            XACChunkData::XACSkinningInfo(skin)
+                if skin.node_index == node_index && lod == 0 && skin.is_for_collision_mesh == 0 =>
+            {
+                Some(
+                    skin.skinning_influence
+                        .iter()
+                        .map(|vertex| {
+                            vertex
+                                .influences
+                                .iter()
+                                .map(|i| (i.node_number, i.weight))
+                                .collect()
+                        })
+                        .collect(),
+                )
+            }
+            XACChunkData::XACSkinningInfo2(skin)
+                if skin.node_index == node_index && lod == 0 && skin.is_for_collision_mesh == 0 =>
+            {
+                Some(from_table(
+                    &skin.skinning_influence,
+                    &skin.skinning_info_table_entry,
+                ))
+            }
+            XACChunkData::XACSkinningInfo3(skin)
+                if skin.node_index == node_index && lod == 0 && skin.is_for_collision_mesh == 0 =>
+            {
+                Some(from_table(
+                    &skin.skinning_influence,
+                    &skin.skinning_info_table_entry,
+                ))
+            }
+            XACChunkData::XACSkinningInfo4(skin)
+                if skin.node_index == node_index
+                    && skin.lod == lod
+                    && skin.is_for_collision_mesh == 0 =>
+            {
+                Some(from_table(
+                    &skin.skinning_influence,
+                    &skin.skinning_info_table_entry,
+                ))
+            }
+            _ => None,
+        })
+    }
+
+    /// Morph targets of one mesh, from the XACPMorphTargets list and standalone chunks
+    fn xac_morph_targets(xac: &XACRoot, lod: u32) -> Vec<&XACPMorphTarget> {
+        let mut targets = Vec::new();
+        for entry in &xac.chunks {
+            match &entry.chunk_data {
+                XACChunkData::XACPMorphTargets(list) if list.lod == lod => {
+                    targets.extend(list.morph_targets.iter());
+                }
+                XACChunkData::XACPMorphTarget(target) if target.lod == lod => {
+                    targets.push(target);
+                }
+                _ => {}
+            }
+        }
+        targets
+    }
+
+    /// Spread the deltas of every morph target deforming this mesh over its
+    /// submeshes. Vertex numbers index the mesh's vertices across all submeshes.
+    fn apply_morph_targets(xac: &XACRoot, node_index: u32, lod: u32, submeshes: &mut [SubMesh]) {
+        let targets: Vec<(&XACPMorphTarget, &XACPMorphTargetMeshDeltas)> =
+            Self::xac_morph_targets(xac, lod)
+                .into_iter()
+                .filter_map(|target| {
+                    target
+                        .morph_target_mesh_deltas
+                        .iter()
+                        .find(|deltas| deltas.node_index == node_index)
+                        .map(|deltas| (target, deltas))
+                })
+                .collect();
+        if targets.is_empty() {
+            return;
+        }
+
+        let mut vertex_offset = 0;
+        for submesh in submeshes.iter_mut() {
+            let count = submesh.positions.len();
+            for (target, deltas) in &targets {
+                let mut morph = MorphTarget {
+                    name: target.name.clone(),
+                    range_min: target.range_min,
+                    range_max: target.range_max,
+                    position_deltas: vec![Vector3::default(); count],
+                    normal_deltas: vec![Vector3::default(); count],
+                    tangent_deltas: vec![Vector3::default(); count],
+                };
+                for (i, &vertex) in deltas.vertex_numbers.iter().enumerate() {
+                    let Some(local) = (vertex as usize)
+                        .checked_sub(vertex_offset)
+                        .filter(|&local| local < count)
+                    else {
+                        continue;
+                    };
+                    let (position, normal, tangent) = decode_morph_delta(deltas, i);
+                    morph.position_deltas[local] = position;
+                    morph.normal_deltas[local] = normal;
+                    morph.tangent_deltas[local] = tangent;
+                }
+                submesh.morph_targets.push(morph);
+            }
+            vertex_offset += count;
+        }
+    }
+
+    /// Strongest `MAX_INFLUENCES` influences of a vertex, renormalized
+    fn vertex_influences(influences: &[(u32, f32)]) -> Option<([u16; 4], [f32; 4])> {
+        let mut sorted: Vec<(u32, f32)> = influences
+            .iter()
+            .copied()
+            .filter(|&(_, weight)| weight > 0.0)
+            .collect();
+        sorted.sort_by(|a, b| b.1.total_cmp(&a.1));
+        sorted.truncate(MAX_INFLUENCES);
+
+        let total: f32 = sorted.iter().map(|(_, weight)| weight).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut joints = [0u16; 4];
+        let mut weights = [0f32; 4];
+        for (slot, (node, weight)) in sorted.into_iter().enumerate() {
+            joints[slot] = node as u16;
+            weights[slot] = weight / total;
+        }
+        Some((joints, weights))
+    }
+
+    fn build_node(
+        index: usize,
+        bones: &[XacBone],
+        children: &[Vec<usize>],
+        node_models: &mut HashMap<usize, Vec<Model>>,
+        parent_world: &Matrix4,
+    ) -> SceneNode {
+        let bone = &bones[index];
+        let local = compose_matrix(&bone.position, &bone.rotation, &bone.scale);
+        let world = multiply_matrix(parent_world, &local);
+
+        let mut models = node_models.remove(&index).unwrap_or_default().into_iter();
+        let mut node = SceneNode {
+            name: bone.name.to_string(),
+            position: Some(bone.position.clone()),
+            rotation: Some(bone.rotation.clone()),
+            scale: Some(bone.scale.clone()),
+            model: models.next(),
+            children: Vec::new(),
+            node_index: Some(index as u32),
+            world_matrix: Some(world),
+            is_attachment: bone.flags & NODE_FLAG_ATTACHMENT != 0,
+            include_in_bounds: bone.flags & NODE_FLAG_INCLUDE_IN_BOUNDS != 0,
+        };
+        // Further meshes of the same node (LODs, collision meshes) sit on plain children
+        for model in models {
+            node.children.push(SceneNode {
+                name: model.name.clone(),
+                model: Some(model),
+                world_matrix: Some(world),
+                ..SceneNode::default()
+            });
+        }
+        for &child in &children[index] {
+            node.children.push(Self::build_node(
+                child,
+                bones,
+                children,
+                node_models,
+                &world,
+            ));
+        }
+        node
+    }
+
     fn parse_vertex_data(
         layers: &[crate::xac::XACVertexAttributeLayer],
         submeshes: &[crate::xac::XACSubMesh],
-        textures_data: Vec<String>,
+        skin: Option<&[Vec<(u32, f32)>]>,
     ) -> Vec<SubMesh> {
         // Helper to find layer by attribute type
         let find_layer = |attr_id| layers.iter().find(|l| l.layer_type_id == attr_id);
@@ -167,16 +1026,6 @@ impl Scene {
         for submesh in submeshes {
             let mut s = SubMesh::default();
 
-            // Assign material/texture
-            s.textures = if submesh.material_index != 0 {
-                textures_data
-                    .get(submesh.material_index as usize)
-                    .cloned()
-                    .unwrap_or_default()
-            } else {
-                String::new()
-            };
-
             for index_indices in 0..submesh.num_indices {
                 s.indices.push(submesh.indices[index_indices as usize]);
             }
@@ -291,6 +1140,35 @@ impl Scene {
                 }
             }
 
+            // Original vertex numbers, which index the skinning table
+            if let Some(data) = original_vertex_numbers_data {
+                for v in 0..submesh.num_verts as usize {
+                    let offset = (vertex_offset + v) * 4;
+                    if offset + 4 <= data.len() {
+                        s.original_vertex_numbers.push(u32::from_le_bytes(
+                            data[offset..offset + 4].try_into().unwrap(),
+                        ));
+                    }
+                }
+            }
+
+            // Skinning, all or nothing so JOINTS_0/WEIGHTS_0 line up with the vertices
+            if let Some(skin) = skin {
+                let influences: Option<Vec<_>> = s
+                    .original_vertex_numbers
+                    .iter()
+                    .map(|&org| {
+                        skin.get(org as usize)
+                            .and_then(|influences| Scene::vertex_influences(influences))
+                    })
+                    .collect();
+                if let Some(influences) = influences
+                    && influences.len() == s.positions.len()
+                {
+                    (s.joints, s.weights) = influences.into_iter().unzip();
+                }
+            }
+
             vertex_offset += submesh.num_verts as usize;
             parsed_submeshes.push(s);
         }
@@ -299,6 +1177,167 @@ impl Scene {
     }
 }
 
+/// Range of the 8-bit normal and tangent deltas
+const MORPH_DIRECTION_RANGE: (f32, f32) = (-2.0, 2.0);
+
+/// Dequantize delta `i` of a morph target; positions span the stored
+/// min/max, normals and tangents a fixed range. X is mirrored like the
+/// vertex positions and normals, tangents stay as stored like `SubMesh::tangents`.
+pub fn decode_morph_delta(
+    deltas: &XACPMorphTargetMeshDeltas,
+    i: usize,
+) -> (Vector3, Vector3, Vector3) {
+    fn scale(value: f32, max_value: f32, (min, max): (f32, f32)) -> f32 {
+        min + (max - min) * (value / max_value)
+    }
+
+    let range = (deltas.min_value, deltas.max_value);
+    let position = deltas
+        .delta_position_values
+        .get(i)
+        .map(|p| Vector3 {
+            x: -scale(p.x as f32, 65535.0, range),
+            y: scale(p.y as f32, 65535.0, range),
+            z: scale(p.z as f32, 65535.0, range),
+        })
+        .unwrap_or_default();
+    let normal = deltas
+        .delta_normal_values
+        .get(i)
+        .map(|n| Vector3 {
+            x: -scale(n.x as f32, 255.0, MORPH_DIRECTION_RANGE),
+            y: scale(n.y as f32, 255.0, MORPH_DIRECTION_RANGE),
+            z: scale(n.z as f32, 255.0, MORPH_DIRECTION_RANGE),
+        })
+        .unwrap_or_default();
+    let tangent = deltas
+        .delta_tangent_values
+        .get(i)
+        .map(|t| Vector3 {
+            x: scale(t.x as f32, 255.0, MORPH_DIRECTION_RANGE),
+            y: scale(t.y as f32, 255.0, MORPH_DIRECTION_RANGE),
+            z: scale(t.z as f32, 255.0, MORPH_DIRECTION_RANGE),
+        })
+        .unwrap_or_default();
+    (position, normal, tangent)
+}
+
+/// Translation * rotation * scale
+pub fn compose_matrix(t: &Vector3, r: &Vector4, s: &Vector3) -> Matrix4 {
+    let (x, y, z, w) = (r.x, r.y, r.z, r.w);
+    let len = (x * x + y * y + z * z + w * w).sqrt();
+    let (x, y, z, w) = if len > 0.0 {
+        (x / len, y / len, z / len, w / len)
+    } else {
+        (0.0, 0.0, 0.0, 1.0)
+    };
+
+    [
+        (1.0 - 2.0 * (y * y + z * z)) * s.x,
+        (2.0 * (x * y + z * w)) * s.x,
+        (2.0 * (x * z - y * w)) * s.x,
+        0.0,
+        (2.0 * (x * y - z * w)) * s.y,
+        (1.0 - 2.0 * (x * x + z * z)) * s.y,
+        (2.0 * (y * z + x * w)) * s.y,
+        0.0,
+        (2.0 * (x * z + y * w)) * s.z,
+        (2.0 * (y * z - x * w)) * s.z,
+        (1.0 - 2.0 * (x * x + y * y)) * s.z,
+        0.0,
+        t.x,
+        t.y,
+        t.z,
+        1.0,
+    ]
+}
+
+/// `a * b`, i.e. `b` applied first
+pub fn multiply_matrix(a: &Matrix4, b: &Matrix4) -> Matrix4 {
+    let mut out = [0.0; 16];
+    for col in 0..4 {
+        for row in 0..4 {
+            out[col * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[col * 4 + k]).sum();
+        }
+    }
+    out
+}
+
+/// General inverse, `None` for a singular matrix
+pub fn invert_matrix(m: &Matrix4) -> Option<Matrix4> {
+    let mut inv = [0.0f32; 16];
+    inv[0] = m[5] * m[10] * m[15] - m[5] * m[11] * m[14] - m[9] * m[6] * m[15]
+        + m[9] * m[7] * m[14]
+        + m[13] * m[6] * m[11]
+        - m[13] * m[7] * m[10];
+    inv[4] = -m[4] * m[10] * m[15] + m[4] * m[11] * m[14] + m[8] * m[6] * m[15]
+        - m[8] * m[7] * m[14]
+        - m[12] * m[6] * m[11]
+        + m[12] * m[7] * m[10];
+    inv[8] = m[4] * m[9] * m[15] - m[4] * m[11] * m[13] - m[8] * m[5] * m[15]
+        + m[8] * m[7] * m[13]
+        + m[12] * m[5] * m[11]
+        - m[12] * m[7] * m[9];
+    inv[12] = -m[4] * m[9] * m[14] + m[4] * m[10] * m[13] + m[8] * m[5] * m[14]
+        - m[8] * m[6] * m[13]
+        - m[12] * m[5] * m[10]
+        + m[12] * m[6] * m[9];
+    inv[1] = -m[1] * m[10] * m[15] + m[1] * m[11] * m[14] + m[9] * m[2] * m[15]
+        - m[9] * m[3] * m[14]
+        - m[13] * m[2] * m[11]
+        + m[13] * m[3] * m[10];
+    inv[5] = m[0] * m[10] * m[15] - m[0] * m[11] * m[14] - m[8] * m[2] * m[15]
+        + m[8] * m[3] * m[14]
+        + m[12] * m[2] * m[11]
+        - m[12] * m[3] * m[10];
+    inv[9] = -m[0] * m[9] * m[15] + m[0] * m[11] * m[13] + m[8] * m[1] * m[15]
+        - m[8] * m[3] * m[13]
+        - m[12] * m[1] * m[11]
+        + m[12] * m[3] * m[9];
+    inv[13] = m[0] * m[9] * m[14] - m[0] * m[10] * m[13] - m[8] * m[1] * m[14]
+        + m[8] * m[2] * m[13]
+        + m[12] * m[1] * m[10]
+        - m[12] * m[2] * m[9];
+    inv[2] = m[1] * m[6] * m[15] - m[1] * m[7] * m[14] - m[5] * m[2] * m[15]
+        + m[5] * m[3] * m[14]
+        + m[13] * m[2] * m[7]
+        - m[13] * m[3] * m[6];
+    inv[6] = -m[0] * m[6] * m[15] + m[0] * m[7] * m[14] + m[4] * m[2] * m[15]
+        - m[4] * m[3] * m[14]
+        - m[12] * m[2] * m[7]
+        + m[12] * m[3] * m[6];
+    inv[10] = m[0] * m[5] * m[15] - m[0] * m[7] * m[13] - m[4] * m[1] * m[15]
+        + m[4] * m[3] * m[13]
+        + m[12] * m[1] * m[7]
+        - m[12] * m[3] * m[5];
+    inv[14] = -m[0] * m[5] * m[14] + m[0] * m[6] * m[13] + m[4] * m[1] * m[14]
+        - m[4] * m[2] * m[13]
+        - m[12] * m[1] * m[6]
+        + m[12] * m[2] * m[5];
+    inv[3] = -m[1] * m[6] * m[11] + m[1] * m[7] * m[10] + m[5] * m[2] * m[11]
+        - m[5] * m[3] * m[10]
+        - m[9] * m[2] * m[7]
+        + m[9] * m[3] * m[6];
+    inv[7] = m[0] * m[6] * m[11] - m[0] * m[7] * m[10] - m[4] * m[2] * m[11]
+        + m[4] * m[3] * m[10]
+        + m[8] * m[2] * m[7]
+        - m[8] * m[3] * m[6];
+    inv[11] = -m[0] * m[5] * m[11] + m[0] * m[7] * m[9] + m[4] * m[1] * m[11]
+        - m[4] * m[3] * m[9]
+        - m[8] * m[1] * m[7]
+        + m[8] * m[3] * m[5];
+    inv[15] = m[0] * m[5] * m[10] - m[0] * m[6] * m[9] - m[4] * m[1] * m[10]
+        + m[4] * m[2] * m[9]
+        + m[8] * m[1] * m[6]
+        - m[8] * m[2] * m[5];
+
+    let det = m[0] * inv[0] + m[1] * inv[4] + m[2] * inv[8] + m[3] * inv[12];
+    if det == 0.0 {
+        return None;
+    }
+    Some(inv.map(|v| v / det))
+}
+
 pub fn to_vec3(s: &str) -> Vector3 {
     let mut it = s.split_whitespace().filter_map(|n| n.parse::<f32>().ok());
     Vector3 {
@@ -368,17 +1407,222 @@ mod tests {
             "Scene should contain at least one root node"
         );
 
-        // Optionally check if the first root node has a model
-        if let Some(first_node) = scene.root_nodes.first() {
-            assert!(
-                first_node.model.is_some(),
-                "First root node should have a model"
+        // Meshes hang off their owning skeleton nodes
+        fn walk<'a>(node: &'a SceneNode, out: &mut Vec<&'a SceneNode>) {
+            out.push(node);
+            for child in &node.children {
+                walk(child, out);
+            }
+        }
+        let mut nodes = Vec::new();
+        for root in &scene.root_nodes {
+            walk(root, &mut nodes);
+        }
+        assert!(
+            nodes.iter().any(|n| n.model.is_some()),
+            "Some node should have a model"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scene_skeleton_world_matrices() -> io::Result<()> {
+        let xac_root = crate::xac::XACRoot::from_file("tests/npc_lecifer_set.xac")?;
+        let scene = Scene::from_xac_root(&xac_root, String::new());
+
+        fn check(node: &SceneNode, parent_world: &Matrix4, count: &mut usize) {
+            *count += 1;
+            let local = compose_matrix(
+                node.position.as_ref().unwrap(),
+                node.rotation.as_ref().unwrap(),
+                node.scale.as_ref().unwrap(),
             );
+            let expected = multiply_matrix(parent_world, &local);
+            let world = node.world_matrix.unwrap();
+            for (a, b) in world.iter().zip(expected) {
+                assert!((a - b).abs() < 1e-4);
+            }
+            for child in node.children.iter().filter(|c| c.node_index.is_some()) {
+                check(child, &world, count);
+            }
         }
 
+        let mut count = 0;
+        for root in &scene.root_nodes {
+            check(root, &IDENTITY_MATRIX, &mut count);
+        }
+        // 134 nodes, 21 of them roots
+        assert_eq!(scene.root_nodes.len(), 21);
+        assert_eq!(count, 134);
         Ok(())
     }
 
+    #[test]
+    fn test_scene_skinning_weights() -> io::Result<()> {
+        let xac_root = crate::xac::XACRoot::from_file("tests/npc_lecifer_set.xac")?;
+        let scene = Scene::from_xac_root(&xac_root, String::new());
+
+        fn submeshes<'a>(node: &'a SceneNode, out: &mut Vec<&'a SubMesh>) {
+            if let Some(model) = &node.model {
+                out.extend(model.submeshes.iter());
+            }
+            for child in &node.children {
+                submeshes(child, out);
+            }
+        }
+        let mut all = Vec::new();
+        for root in &scene.root_nodes {
+            submeshes(root, &mut all);
+        }
+
+        let skinned: Vec<&SubMesh> = all.into_iter().filter(|s| !s.joints.is_empty()).collect();
+        assert!(!skinned.is_empty(), "lecifer should be skinned");
+        for submesh in skinned {
+            assert_eq!(submesh.joints.len(), submesh.positions.len());
+            for (joints, weights) in submesh.joints.iter().zip(&submesh.weights) {
+                let total: f32 = weights.iter().sum();
+                assert!((total - 1.0).abs() < 1e-4);
+                assert!(joints.iter().all(|&j| j < 134));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_scene_add_animation() -> io::Result<()> {
+        let xac_root = crate::xac::XACRoot::from_file("tests/npc_lecifer_set.xac")?;
+        let mut scene = Scene::from_xac_root(&xac_root, String::new());
+        let xsm = crate::xsm::XSMRoot::from_file("tests/npc_lecifer_run.xsm")?;
+        let animation = AnimationClip::from_xsm_root(&xsm)?;
+        let track_count = animation.tracks.len();
+        scene.add_animation(animation);
+
+        let tracks = &scene.animations[0].tracks;
+        assert!(!tracks.is_empty() && tracks.len() <= track_count);
+        assert!(tracks.iter().all(|track| track.node_index.is_some()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scene_materials() -> io::Result<()> {
+        let xac_root = crate::xac::XACRoot::from_file("tests/d_abbey_trap.xac")?;
+        let scene = Scene::from_xac_root(&xac_root, "bg/".to_string());
+
+        // Standard and FX materials share one index space in file order
+        assert_eq!(scene.materials.len(), 10);
+        assert_eq!(scene.materials[0].name, "EMFX Default");
+        let trap = scene
+            .materials
+            .iter()
+            .find(|m| m.name == "dun_abbey_Trap01")
+            .expect("FX material");
+        assert_eq!(trap.effect_file, "Default.fx");
+        assert_eq!(trap.int_params.get("g_alphaTestValue"), Some(&127));
+        assert_eq!(trap.bool_params.get("g_isLightOff"), Some(&true));
+        assert!(!trap.double_sided && trap.alpha_cutoff.is_none());
+        // "None" bitmaps are dropped, known slots kept with the texture path
+        let maps: Vec<(MapType, &str)> = trap
+            .maps
+            .iter()
+            .map(|map| (map.map_type, map.texture.as_str()))
+            .collect();
+        assert_eq!(
+            maps,
+            vec![
+                (MapType::Diffuse, "bg/dun_abbey_Trap01.dds"),
+                (MapType::Environment, "bg/r1_Tenv01.dds"),
+            ]
+        );
+
+        // Submeshes point at their material and keep its diffuse map
+        fn submeshes(node: &SceneNode, out: &mut Vec<SubMesh>) {
+            out.extend(node.model.iter().flat_map(|m| m.submeshes.clone()));
+            for child in &node.children {
+                submeshes(child, out);
+            }
+        }
+        let mut all = Vec::new();
+        for node in &scene.root_nodes {
+            submeshes(node, &mut all);
+        }
+        assert!(!all.is_empty());
+        for submesh in &all {
+            let material = &scene.materials[submesh.material_index.expect("material")];
+            let diffuse = material.map(MapType::Diffuse).expect("diffuse map");
+            assert_eq!(submesh.textures, diffuse.texture);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_standard_material_layers() -> io::Result<()> {
+        let xac_root = crate::xac::XACRoot::from_file("tests/boss_wastrel_set.xac")?;
+        let scene = Scene::from_xac_root(&xac_root, String::new());
+
+        let material = &scene.materials[2];
+        assert_eq!(material.name, "boss_wastrel");
+        assert!((material.diffuse.r - 0.588).abs() < 1e-6);
+        assert_eq!(material.opacity, 1.0);
+        assert!(!material.alpha_blend);
+        let diffuse = material.map(MapType::Diffuse).expect("diffuse layer");
+        assert_eq!(diffuse.texture, "boss_wastrel");
+        assert_eq!(diffuse.tiling.x, 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_morph_delta() {
+        use crate::xac::{File8BitVector3, File16BitVector3, XACPMorphTargetMeshDeltas};
+
+        let deltas = XACPMorphTargetMeshDeltas {
+            min_value: -2.0,
+            max_value: 6.0,
+            num_vertices: 1,
+            delta_position_values: vec![File16BitVector3 {
+                x: 65535,
+                y: 0,
+                z: 16384,
+            }],
+            delta_normal_values: vec![File8BitVector3 { x: 255, y: 0, z: 0 }],
+            delta_tangent_values: vec![File8BitVector3 { x: 255, y: 0, z: 0 }],
+            ..XACPMorphTargetMeshDeltas::default()
+        };
+        let (position, normal, tangent) = decode_morph_delta(&deltas, 0);
+        // X is mirrored like the vertex positions
+        assert_eq!(position.x, -6.0);
+        assert_eq!(position.y, -2.0);
+        assert!((position.z - 0.0).abs() < 1e-3);
+        assert_eq!((normal.x, normal.y), (-2.0, -2.0));
+        assert_eq!(tangent.x, 2.0);
+    }
+
+    #[test]
+    fn test_invert_matrix() {
+        let m = compose_matrix(
+            &Vector3 {
+                x: 1.0,
+                y: -2.0,
+                z: 3.0,
+            },
+            &Vector4 {
+                x: 0.0,
+                y: std::f32::consts::FRAC_1_SQRT_2,
+                z: 0.0,
+                w: std::f32::consts::FRAC_1_SQRT_2,
+            },
+            &Vector3 {
+                x: 2.0,
+                y: 2.0,
+                z: 2.0,
+            },
+        );
+        let product = multiply_matrix(&m, &invert_matrix(&m).unwrap());
+        for (a, b) in product.iter().zip(IDENTITY_MATRIX) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
     #[test]
     fn test_scene_from_xac_memory() -> io::Result<()> {
         // Load file into memory
@@ -388,11 +1632,11 @@ mod tests {
         let xac_root = crate::xac::XACRoot::from_bytes(&data)?;
         let texture_path = String::new();
 
-        println!("Textures Name: {:#?}", xac_root.get_texture_names());
-
         // Convert to Scene
         let scene = Scene::from_xac_root(&xac_root, texture_path);
 
+        assert!(!scene.materials.is_empty());
+
         // Debug print
         // println!("Scene root nodes: {:?}", scene.root_nodes);
 
@@ -0,0 +1,217 @@
+//! Patch timeline built from the archives in the game's `patch` folder.
+//!
+//! Every patch IPF header carries the version it applies to (`version_to_patch`)
+//! and the version it produces (`new_version`). Patches are ordered by those
+//! versions, with the base `data` archives ranked before all of them, and every
+//! file a patch ships is classified as added (no earlier archive has the path)
+//! or overridden (an earlier archive already had it).
+
+use serde::Serialize;
+
+use crate::category::{Archive, FileRef, FileVersions, Folder, FolderRef, archive_name_matches};
+
+/// Rank of every archive outside the `patch` folder
+const BASE_RANK: u32 = 0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PatchEntry {
+    pub archive_id: u16,
+    pub name: String,
+    pub version_to_patch: u32,
+    pub new_version: u32,
+    /// Virtual paths no earlier archive contained
+    pub added: Vec<String>,
+    /// Virtual paths replacing a version from an earlier archive
+    pub overridden: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct PatchTimeline {
+    /// Ordered by version, oldest first
+    pub patches: Vec<PatchEntry>,
+    // archive id -> position in the timeline, BASE_RANK for data archives
+    ranks: Vec<u32>,
+}
+
+impl PatchTimeline {
+    pub fn build(tree: &Folder) -> Self {
+        let archives: Vec<&Archive> = tree.archives().iter().collect();
+
+        let mut order: Vec<u16> = (0..archives.len() as u16)
+            .filter(|&id| archives[id as usize].is_patch())
+            .collect();
+        order.sort_by_key(|&id| {
+            let archive = archives[id as usize];
+            let (from, to) = archive
+                .header
+                .as_ref()
+                .map(|h| (h.version_to_patch, h.new_version))
+                .unwrap_or_default();
+            (to, from, archive.name.clone())
+        });
+
+        let mut ranks = vec![BASE_RANK; archives.len()];
+        for (position, &id) in order.iter().enumerate() {
+            ranks[id as usize] = position as u32 + 1;
+        }
+
+        let mut patches: Vec<PatchEntry> = order
+            .iter()
+            .map(|&id| {
+                let archive = archives[id as usize];
+                let header = archive.header.as_ref();
+                PatchEntry {
+                    archive_id: id,
+                    name: archive.name.clone(),
+                    version_to_patch: header.map_or(0, |h| h.version_to_patch),
+                    new_version: header.map_or(0, |h| h.new_version),
+                    added: Vec::new(),
+                    overridden: Vec::new(),
+                }
+            })
+            .collect();
+
+        let mut timeline = PatchTimeline {
+            patches: Vec::new(),
+            ranks,
+        };
+        timeline.classify(tree.root(), &mut patches);
+        timeline.patches = patches;
+        timeline
+    }
+
+    /// Position of an archive in the timeline, 0 for the base archives
+    pub fn rank(&self, archive_id: u16) -> u32 {
+        self.ranks
+            .get(archive_id as usize)
+            .copied()
+            .unwrap_or(BASE_RANK)
+    }
+
+    /// Case-insensitive lookup by archive file name, with or without ".ipf"
+    pub fn patch(&self, name: &str) -> Option<&PatchEntry> {
+        self.patches
+            .iter()
+            .find(|p| archive_name_matches(&p.name, name))
+    }
+
+    /// Versions of one file ordered by timeline rank, oldest first
+    pub fn history<'a>(&self, versions: &[FileRef<'a>]) -> Vec<FileRef<'a>> {
+        let mut ordered = versions.to_vec();
+        ordered.sort_by_key(|file| self.rank(file.archive_id()));
+        ordered
+    }
+
+    fn classify(&self, folder: FolderRef, patches: &mut [PatchEntry]) {
+        let folder_path = folder.path();
+        for versions in folder.file_versions() {
+            self.classify_file(&folder_path, &versions, patches);
+        }
+        for subfolder in folder.subfolders() {
+            self.classify(subfolder, patches);
+        }
+    }
+
+    fn classify_file(
+        &self,
+        folder_path: &str,
+        versions: &FileVersions,
+        patches: &mut [PatchEntry],
+    ) {
+        let ranks: Vec<u32> = versions
+            .versions()
+            .map(|file| self.rank(file.archive_id()))
+            .collect();
+        let Some(&first) = ranks.iter().min() else {
+            return;
+        };
+
+        let full_path = if folder_path.is_empty() {
+            versions.name().to_string()
+        } else {
+            format!("{}/{}", folder_path, versions.name())
+        };
+
+        let mut seen = Vec::new();
+        for rank in ranks {
+            // rank 0 is a base archive, the same patch may list a path twice
+            if rank == BASE_RANK || seen.contains(&rank) {
+                continue;
+            }
+            seen.push(rank);
+            let patch = &mut patches[rank as usize - 1];
+            if rank == first {
+                patch.added.push(full_path.clone());
+            } else {
+                patch.overridden.push(full_path.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::category::build_tree;
+    use crate::ipf::{IPFFileTable, IPFHeader, IPFRoot};
+
+    fn file(name: &str, archive: &str) -> IPFFileTable {
+        IPFFileTable {
+            directory_name: name.to_string(),
+            file_path: Some(PathBuf::from(archive)),
+            ..IPFFileTable::default()
+        }
+    }
+
+    fn patch_root(archive: &str, from: u32, to: u32) -> IPFRoot {
+        IPFRoot {
+            header: IPFHeader {
+                version_to_patch: from,
+                new_version: to,
+                ..IPFHeader::default()
+            },
+            file_table: Vec::new(),
+            file_path: Some(PathBuf::from(archive)),
+        }
+    }
+
+    #[test]
+    fn test_timeline_orders_patches_by_version() {
+        let mut grouped = BTreeMap::new();
+        // File names sort the patches opposite to their versions
+        grouped.insert(
+            "ies/item.ies".to_string(),
+            vec![
+                file("item.ies", "data/ies.ipf"),
+                file("item.ies", "patch/a.ipf"),
+            ],
+        );
+        grouped.insert(
+            "ies/skill.ies".to_string(),
+            vec![
+                file("skill.ies", "patch/a.ipf"),
+                file("skill.ies", "patch/b.ipf"),
+            ],
+        );
+        let mut tree = build_tree(grouped);
+        tree.register_archives(&[
+            patch_root("patch/a.ipf", 20, 30),
+            patch_root("patch/b.ipf", 10, 20),
+        ]);
+
+        let timeline = PatchTimeline::build(&tree);
+        let names: Vec<&str> = timeline.patches.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["b.ipf", "a.ipf"]);
+
+        let b = timeline.patch("b").unwrap();
+        assert_eq!(b.added, vec!["ies/skill.ies"]);
+        assert!(b.overridden.is_empty());
+
+        let a = timeline.patch("a.ipf").unwrap();
+        assert!(a.added.is_empty());
+        assert_eq!(a.overridden, vec!["ies/item.ies", "ies/skill.ies"]);
+    }
+}
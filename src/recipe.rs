@@ -0,0 +1,308 @@
+//! Crafting graph built from `ies/recipe.ies`.
+//!
+//! Each recipe produces one target item from a list of ingredients. The graph
+//! is indexed both ways so we can expand an item into its full crafting tree
+//! (down to raw materials) and look up every recipe an item is used in.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::class_index::ClassIndex;
+use crate::ies::IESRoot;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Ingredient {
+    pub item: String,
+    pub count: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Recipe {
+    pub class_id: i32,
+    pub class_name: String,
+    pub target_item: String,
+    pub target_count: u32,
+    pub ingredients: Vec<Ingredient>,
+}
+
+/// One node of an expanded crafting tree
+#[derive(Debug, Clone, Serialize)]
+pub struct CraftNode {
+    pub item: String,
+    pub name: String,
+    pub count: u64,
+    /// Recipe used to craft this node, `None` for raw materials
+    pub recipe: Option<String>,
+    /// Set when expansion stopped because the item is already being crafted higher up
+    pub cyclic: bool,
+    pub children: Vec<CraftNode>,
+}
+
+#[derive(Debug, Default)]
+pub struct RecipeGraph {
+    recipes: Vec<Recipe>,
+    // lowercased item class name -> recipe indices
+    by_target: HashMap<String, Vec<usize>>,
+    by_ingredient: HashMap<String, Vec<usize>>,
+}
+
+impl RecipeGraph {
+    /// Parse recipes from `recipe.ies`.
+    ///
+    /// Ingredients are read from every `Item_<n>_<m>` string column, with the
+    /// amount taken from the matching `Item_<n>_<m>_Cnt` column (default 1).
+    pub fn from_ies(ies: &IESRoot) -> Self {
+        let lookup = ies.column_lookup();
+
+        let mut ingredient_columns: Vec<&String> = lookup
+            .names()
+            .iter()
+            .filter(|name| name.starts_with("Item_") && !name.ends_with("_Cnt"))
+            .collect();
+        ingredient_columns.sort();
+
+        let mut recipes = Vec::new();
+        for row in &ies.data {
+            let target_item = lookup.text(row, "TargetItem").unwrap_or_default();
+            if target_item.is_empty() {
+                continue;
+            }
+
+            let ingredients = ingredient_columns
+                .iter()
+                .filter_map(|column| {
+                    let item = lookup.text(row, column)?;
+                    if item.is_empty() || item == "None" {
+                        return None;
+                    }
+                    let count = lookup
+                        .number(row, &format!("{}_Cnt", column))
+                        .map(|c| c as u32)
+                        .filter(|&c| c > 0)
+                        .unwrap_or(1);
+                    Some(Ingredient {
+                        item: item.to_string(),
+                        count,
+                    })
+                })
+                .collect();
+
+            recipes.push(Recipe {
+                class_id: row.index_data,
+                class_name: lookup
+                    .text(row, "ClassName")
+                    .unwrap_or(&row.row_text.text_data)
+                    .to_string(),
+                target_item: target_item.to_string(),
+                target_count: lookup
+                    .number(row, "TargetItemCnt")
+                    .map(|c| c as u32)
+                    .filter(|&c| c > 0)
+                    .unwrap_or(1),
+                ingredients,
+            });
+        }
+
+        Self::from_recipes(recipes)
+    }
+
+    pub fn from_recipes(recipes: Vec<Recipe>) -> Self {
+        let mut by_target: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut by_ingredient: HashMap<String, Vec<usize>> = HashMap::new();
+
+        for (i, recipe) in recipes.iter().enumerate() {
+            by_target
+                .entry(recipe.target_item.to_lowercase())
+                .or_default()
+                .push(i);
+
+            let mut seen = HashSet::new();
+            for ingredient in &recipe.ingredients {
+                let key = ingredient.item.to_lowercase();
+                if seen.insert(key.clone()) {
+                    by_ingredient.entry(key).or_default().push(i);
+                }
+            }
+        }
+
+        Self {
+            recipes,
+            by_target,
+            by_ingredient,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.recipes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.recipes.is_empty()
+    }
+
+    pub fn recipes(&self) -> &[Recipe] {
+        &self.recipes
+    }
+
+    /// Recipes that produce `item`
+    pub fn recipes_for(&self, item: &str) -> Vec<&Recipe> {
+        self.lookup(&self.by_target, item)
+    }
+
+    /// Recipes that consume `item` ("what can this item be used in")
+    pub fn used_in(&self, item: &str) -> Vec<&Recipe> {
+        self.lookup(&self.by_ingredient, item)
+    }
+
+    fn lookup(&self, index: &HashMap<String, Vec<usize>>, item: &str) -> Vec<&Recipe> {
+        index
+            .get(&item.to_lowercase())
+            .map(|ids| ids.iter().map(|&i| &self.recipes[i]).collect())
+            .unwrap_or_default()
+    }
+
+    /// Expand `count` of `item` into a crafting tree using the first recipe of each item
+    pub fn expand(&self, item: &str, count: u64, items: &ClassIndex) -> CraftNode {
+        let mut path = HashSet::new();
+        self.expand_node(item, count, items, &mut path)
+    }
+
+    fn expand_node(
+        &self,
+        item: &str,
+        count: u64,
+        items: &ClassIndex,
+        path: &mut HashSet<String>,
+    ) -> CraftNode {
+        let key = item.to_lowercase();
+        let mut node = CraftNode {
+            item: item.to_string(),
+            name: items.display_name(item),
+            count,
+            recipe: None,
+            cyclic: false,
+            children: Vec::new(),
+        };
+
+        let Some(recipe) = self.recipes_for(item).into_iter().next() else {
+            return node;
+        };
+        if !path.insert(key.clone()) {
+            node.cyclic = true;
+            return node;
+        }
+
+        // Number of times the recipe must run to produce `count` items. `count`
+        // comes straight from the query, so saturate rather than overflow
+        let crafts = count.div_ceil(recipe.target_count as u64);
+        node.recipe = Some(recipe.class_name.clone());
+        node.children = recipe
+            .ingredients
+            .iter()
+            .map(|ing| {
+                self.expand_node(
+                    &ing.item,
+                    (ing.count as u64).saturating_mul(crafts),
+                    items,
+                    path,
+                )
+            })
+            .collect();
+
+        path.remove(&key);
+        node
+    }
+
+    /// Total raw (non-craftable) materials needed for `count` of `item`
+    pub fn raw_materials(
+        &self,
+        item: &str,
+        count: u64,
+        items: &ClassIndex,
+    ) -> BTreeMap<String, u64> {
+        let mut totals = BTreeMap::new();
+        collect_leaves(&self.expand(item, count, items), &mut totals);
+        totals
+    }
+}
+
+fn collect_leaves(node: &CraftNode, totals: &mut BTreeMap<String, u64>) {
+    if node.children.is_empty() {
+        let total = totals.entry(node.item.clone()).or_insert(0);
+        *total = total.saturating_add(node.count);
+    } else {
+        for child in &node.children {
+            collect_leaves(child, totals);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipe(name: &str, target: &str, target_count: u32, ingredients: &[(&str, u32)]) -> Recipe {
+        Recipe {
+            class_id: 0,
+            class_name: name.to_string(),
+            target_item: target.to_string(),
+            target_count,
+            ingredients: ingredients
+                .iter()
+                .map(|&(item, count)| Ingredient {
+                    item: item.to_string(),
+                    count,
+                })
+                .collect(),
+        }
+    }
+
+    fn sample_graph() -> RecipeGraph {
+        RecipeGraph::from_recipes(vec![
+            recipe("R_Sword", "Sword", 1, &[("Ingot", 2), ("Leather", 1)]),
+            recipe("R_Ingot", "Ingot", 2, &[("Ore", 3)]),
+            recipe("R_Shield", "Shield", 1, &[("Ingot", 1), ("Wood", 4)]),
+        ])
+    }
+
+    #[test]
+    fn test_raw_materials_expansion() {
+        let graph = sample_graph();
+        let items = ClassIndex::new();
+
+        // 3 swords need 6 ingots = 3 crafts of R_Ingot = 9 ore
+        let raw = graph.raw_materials("Sword", 3, &items);
+        assert_eq!(raw.get("Ore"), Some(&9));
+        assert_eq!(raw.get("Leather"), Some(&3));
+        assert!(!raw.contains_key("Ingot"));
+    }
+
+    #[test]
+    fn test_huge_counts_saturate() {
+        let graph = sample_graph();
+        let raw = graph.raw_materials("Sword", u64::MAX, &ClassIndex::new());
+        assert_eq!(raw.get("Ore"), Some(&u64::MAX));
+    }
+
+    #[test]
+    fn test_used_in_lookup_is_case_insensitive() {
+        let graph = sample_graph();
+        let used: Vec<&str> = graph
+            .used_in("ingot")
+            .iter()
+            .map(|r| r.class_name.as_str())
+            .collect();
+        assert_eq!(used, vec!["R_Sword", "R_Shield"]);
+    }
+
+    #[test]
+    fn test_cyclic_recipe_stops_expanding() {
+        let graph = RecipeGraph::from_recipes(vec![
+            recipe("R_A", "A", 1, &[("B", 1)]),
+            recipe("R_B", "B", 1, &[("A", 1)]),
+        ]);
+        let tree = graph.expand("A", 1, &ClassIndex::new());
+        assert!(tree.children[0].children[0].cyclic);
+    }
+}
@@ -0,0 +1,360 @@
+//! File format detection from content rather than file names.
+//!
+//! Most formats shipped in the IPF archives start with a fourcc or another
+//! fixed signature. TGA, IES and tok files have none, so their headers are
+//! checked for plausible values instead. Text is classified last, with the
+//! extension only used to tell Lua scripts from other plain text.
+//!
+//! Sorting a whole folder by type cannot afford to read every file, so it
+//! uses `FileFormat::from_extension` instead.
+
+use serde::Serialize;
+
+use crate::ipf;
+
+/// Bytes needed by `detect` to recognize every format
+pub const SNIFF_LEN: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileFormat {
+    Xac,
+    Xsm,
+    Xpm,
+    Dds,
+    Png,
+    Jpeg,
+    Bmp,
+    Tga,
+    Fsb5,
+    Mp3,
+    Ttf,
+    Ies,
+    Tok,
+    Xml,
+    Lua,
+    Text,
+    Binary,
+}
+
+impl FileFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileFormat::Xac => "xac",
+            FileFormat::Xsm => "xsm",
+            FileFormat::Xpm => "xpm",
+            FileFormat::Dds => "dds",
+            FileFormat::Png => "png",
+            FileFormat::Jpeg => "jpeg",
+            FileFormat::Bmp => "bmp",
+            FileFormat::Tga => "tga",
+            FileFormat::Fsb5 => "fsb5",
+            FileFormat::Mp3 => "mp3",
+            FileFormat::Ttf => "ttf",
+            FileFormat::Ies => "ies",
+            FileFormat::Tok => "tok",
+            FileFormat::Xml => "xml",
+            FileFormat::Lua => "lua",
+            FileFormat::Text => "text",
+            FileFormat::Binary => "binary",
+        }
+    }
+
+    /// MIME type used when the raw bytes are served as-is
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            FileFormat::Png => "image/png",
+            FileFormat::Jpeg => "image/jpeg",
+            FileFormat::Bmp => "image/bmp",
+            FileFormat::Dds => "image/dds",
+            FileFormat::Tga => "image/x-tga",
+            FileFormat::Mp3 => "audio/mpeg",
+            FileFormat::Ttf => "font/ttf",
+            FileFormat::Xml => "application/xml",
+            FileFormat::Lua | FileFormat::Text => "text/plain",
+            _ => "application/octet-stream",
+        }
+    }
+
+    pub fn is_image(&self) -> bool {
+        matches!(
+            self,
+            FileFormat::Dds
+                | FileFormat::Png
+                | FileFormat::Jpeg
+                | FileFormat::Bmp
+                | FileFormat::Tga
+        )
+    }
+
+    pub fn is_text(&self) -> bool {
+        matches!(self, FileFormat::Xml | FileFormat::Lua | FileFormat::Text)
+    }
+
+    /// Coarse file type shown in listings, e.g. "model" for XAC
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FileFormat::Xac => "model",
+            FileFormat::Xsm => "animation",
+            FileFormat::Xpm => "morph",
+            FileFormat::Dds | FileFormat::Tga => "texture",
+            FileFormat::Png | FileFormat::Jpeg | FileFormat::Bmp => "image",
+            FileFormat::Fsb5 | FileFormat::Mp3 => "audio",
+            FileFormat::Ttf => "font",
+            FileFormat::Ies => "table",
+            FileFormat::Tok => "navmesh",
+            FileFormat::Xml => "xml",
+            FileFormat::Lua => "script",
+            FileFormat::Text => "text",
+            FileFormat::Binary => "binary",
+        }
+    }
+
+    /// Format guessed from the file name alone, without reading any content
+    pub fn from_extension(file_name: &str) -> FileFormat {
+        match ipf::file_extension(file_name).as_str() {
+            "xac" => FileFormat::Xac,
+            "xsm" => FileFormat::Xsm,
+            "xpm" => FileFormat::Xpm,
+            "dds" => FileFormat::Dds,
+            "png" => FileFormat::Png,
+            "jpg" | "jpeg" => FileFormat::Jpeg,
+            "bmp" => FileFormat::Bmp,
+            "tga" => FileFormat::Tga,
+            "fsb" => FileFormat::Fsb5,
+            "mp3" => FileFormat::Mp3,
+            "ttf" | "otf" => FileFormat::Ttf,
+            "ies" => FileFormat::Ies,
+            "tok" => FileFormat::Tok,
+            "xml" => FileFormat::Xml,
+            ext if ext.starts_with("3d") => FileFormat::Xml,
+            "lua" => FileFormat::Lua,
+            "txt" | "skn" | "effect" | "sani" | "x" | "fx" | "fxh" => FileFormat::Text,
+            _ => FileFormat::Binary,
+        }
+    }
+}
+
+/// Detect the format of a file from its first bytes.
+///
+/// `head` may be a prefix of the file (at least `SNIFF_LEN` bytes when the file
+/// is that large), `file_size` is the full uncompressed size.
+pub fn detect(file_name: &str, head: &[u8], file_size: u64) -> FileFormat {
+    if let Some(format) = sniff_signature(head) {
+        return format;
+    }
+    if is_mp3_frame(head) {
+        return FileFormat::Mp3;
+    }
+    if is_ies(head, file_size) {
+        return FileFormat::Ies;
+    }
+    if is_tga(head) {
+        return FileFormat::Tga;
+    }
+    if is_tok(head) {
+        return FileFormat::Tok;
+    }
+    if is_bmp(head, file_size) {
+        return FileFormat::Bmp;
+    }
+    classify_text(file_name, head)
+}
+
+/// Formats identified by a fixed signature at the start of the file
+fn sniff_signature(head: &[u8]) -> Option<FileFormat> {
+    const SIGNATURES: &[(&[u8], FileFormat)] = &[
+        (b"XAC ", FileFormat::Xac),
+        (b"XSM ", FileFormat::Xsm),
+        (b"XPM ", FileFormat::Xpm),
+        (b"DDS ", FileFormat::Dds),
+        (b"\x89PNG\r\n\x1a\n", FileFormat::Png),
+        (&[0xFF, 0xD8, 0xFF], FileFormat::Jpeg),
+        (b"FSB5", FileFormat::Fsb5),
+        (b"ID3", FileFormat::Mp3),
+        (&[0x00, 0x01, 0x00, 0x00, 0x00], FileFormat::Ttf),
+        (b"OTTO", FileFormat::Ttf),
+        (b"\x1bLua", FileFormat::Lua),
+    ];
+
+    SIGNATURES
+        .iter()
+        .find(|(signature, _)| head.starts_with(signature))
+        .map(|&(_, format)| format)
+}
+
+/// MP3 without an ID3 tag: the stream starts right at an MPEG audio frame header
+fn is_mp3_frame(head: &[u8]) -> bool {
+    let Some(&[sync, flags, rates, ..]) = head.get(..4) else {
+        return false;
+    };
+    let version = (flags >> 3) & 0b11;
+    let layer = (flags >> 1) & 0b11;
+    let bitrate = rates >> 4;
+    let sample_rate = (rates >> 2) & 0b11;
+
+    sync == 0xFF
+        && flags & 0xE0 == 0xE0
+        && version != 0b01
+        && layer != 0b00
+        && bitrate != 0b1111
+        && sample_rate != 0b11
+}
+
+/// IES: NUL padded ASCII idspace followed by a header whose total size matches the file
+fn is_ies(head: &[u8], file_size: u64) -> bool {
+    const TOTAL_SIZE_OFFSET: usize = 64 + 64 + 2 + 2 + 4 + 4;
+    let Some(total) = head.get(TOTAL_SIZE_OFFSET..TOTAL_SIZE_OFFSET + 4) else {
+        return false;
+    };
+    let total = u32::from_le_bytes(total.try_into().unwrap()) as u64;
+
+    let idspace = &head[..64];
+    let name_len = idspace.iter().position(|&b| b == 0).unwrap_or(64);
+    name_len > 0
+        && idspace[..name_len].iter().all(|b| b.is_ascii_graphic())
+        && idspace[name_len..].iter().all(|&b| b == 0)
+        && total == file_size
+}
+
+/// TGA has no signature, so every header field must hold a value the format allows
+fn is_tga(head: &[u8]) -> bool {
+    let Some(header) = head.get(..18) else {
+        return false;
+    };
+    let color_map_type = header[1];
+    let image_type = header[2];
+    let width = u16::from_le_bytes([header[12], header[13]]);
+    let height = u16::from_le_bytes([header[14], header[15]]);
+    let depth = header[16];
+
+    let color_map_ok = match color_map_type {
+        0 => header[3..8].iter().all(|&b| b == 0),
+        1 => matches!(image_type, 1 | 9),
+        _ => false,
+    };
+
+    color_map_ok
+        && matches!(image_type, 1 | 2 | 3 | 9 | 10 | 11)
+        && width > 0
+        && height > 0
+        && matches!(depth, 8 | 15 | 16 | 24 | 32)
+        && header[17] & 0xC0 == 0
+}
+
+/// tok: a table of NUL terminated element names, closed by an empty name
+fn is_tok(head: &[u8]) -> bool {
+    let mut names = 0;
+    let mut rest = head;
+    while let Some(end) = rest.iter().position(|&b| b == 0) {
+        if end == 0 {
+            return names >= 2;
+        }
+        let name = &rest[..end];
+        if !name.iter().all(|&b| b.is_ascii_alphanumeric() || b == b'_') {
+            return false;
+        }
+        names += 1;
+        rest = &rest[end + 1..];
+    }
+    false
+}
+
+/// BMP: "BM" is too short on its own, so the stored file size must match too
+fn is_bmp(head: &[u8], file_size: u64) -> bool {
+    head.starts_with(b"BM")
+        && head
+            .get(2..6)
+            .is_some_and(|size| u32::from_le_bytes(size.try_into().unwrap()) as u64 == file_size)
+}
+
+fn classify_text(file_name: &str, head: &[u8]) -> FileFormat {
+    let text = head.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(head);
+    // The head may cut a multi-byte character in half
+    let valid = match std::str::from_utf8(text) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    };
+    if !valid || text.contains(&0) {
+        return FileFormat::Binary;
+    }
+
+    let trimmed = text.trim_ascii_start();
+    if trimmed.starts_with(b"<?xml") || (trimmed.starts_with(b"<") && !trimmed.starts_with(b"<<")) {
+        return FileFormat::Xml;
+    }
+
+    if ipf::file_extension(file_name) == "lua" {
+        FileFormat::Lua
+    } else {
+        FileFormat::Text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect_test_file(name: &str) -> FileFormat {
+        let data = std::fs::read(format!("tests/{}", name)).expect("missing test file");
+        let head = &data[..data.len().min(SNIFF_LEN)];
+        detect(name, head, data.len() as u64)
+    }
+
+    #[test]
+    fn test_detect_test_files() {
+        assert_eq!(detect_test_file("npc_lecifer_set.xac"), FileFormat::Xac);
+        assert_eq!(detect_test_file("npc_lecifer_run.xsm"), FileFormat::Xsm);
+        assert_eq!(detect_test_file("npc_lecifer_hair.xpm"), FileFormat::Xpm);
+        assert_eq!(detect_test_file("cell.ies"), FileFormat::Ies);
+        assert_eq!(detect_test_file("enclass.tga"), FileFormat::Tga);
+        assert_eq!(detect_test_file("barrack4.tok"), FileFormat::Tok);
+        assert_eq!(detect_test_file("barrack.3dworld"), FileFormat::Xml);
+        assert_eq!(
+            detect_test_file("npc_lecifer_idle.xsmtime"),
+            FileFormat::Binary
+        );
+    }
+
+    #[test]
+    fn test_detect_ignores_extension_for_signatures() {
+        let dds = b"DDS \x7c\x00\x00\x00";
+        assert_eq!(detect("nocolor.tga", dds, 8), FileFormat::Dds);
+        assert_eq!(
+            detect("init.lua", b"-- comment\nlocal a = 1", 22),
+            FileFormat::Lua
+        );
+        assert_eq!(detect("notes.txt", b"-- comment", 10), FileFormat::Text);
+    }
+
+    #[test]
+    fn test_detect_mp3_without_id3() {
+        // MPEG-1 Layer III, 128 kbit/s, 44.1 kHz
+        let frame = [0xFF, 0xFB, 0x90, 0x64, 0x00, 0x00];
+        assert_eq!(detect("bgm.mp3", &frame, 417), FileFormat::Mp3);
+        assert_eq!(detect("bgm.mp3", b"ID3\x04\x00", 5), FileFormat::Mp3);
+        // JPEG also starts with 0xFF but its second byte is no frame sync
+        assert_eq!(
+            detect("a.bin", &[0xFF, 0xD8, 0xFF, 0xE0], 4),
+            FileFormat::Jpeg
+        );
+        // Reserved layer bits
+        assert_eq!(
+            detect("a.bin", &[0xFF, 0xF9, 0x90, 0x64], 4),
+            FileFormat::Binary
+        );
+    }
+
+    #[test]
+    fn test_from_extension() {
+        assert_eq!(FileFormat::from_extension("npc.XAC"), FileFormat::Xac);
+        assert_eq!(
+            FileFormat::from_extension("barrack.3dworld"),
+            FileFormat::Xml
+        );
+        assert_eq!(FileFormat::from_extension("bgm.mp3"), FileFormat::Mp3);
+        assert_eq!(FileFormat::from_extension("noext"), FileFormat::Binary);
+        assert_eq!(FileFormat::from_extension("npc.xac").kind(), "model");
+        assert_eq!(FileFormat::from_extension("bg.tga").kind(), "texture");
+    }
+}
@@ -0,0 +1,297 @@
+//! Per-map monster spawns and dungeon rewards.
+//!
+//! Every map ships two tables: `ies_mongem/gentype_<map>.ies` describes the
+//! monster groups spawned on the map (`GenType` -> monster class, population,
+//! respawn time) and `ies_drop/anchor_<map>.ies` lists the anchors where each
+//! `GenType` can appear. They are joined here into one spawn list per map.
+//! `ies/reward_indun.ies` holds the dungeon (indun) reward table.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::class_index::ClassIndex;
+use crate::collection::LinkedClass;
+use crate::ies::{IESColumnData, IESColumnLookup, IESRoot};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpawnPoint {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub direction: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpawnGroup {
+    pub gen_type: i32,
+    pub monster: LinkedClass,
+    pub max_pop: u32,
+    pub respawn_ms: u32,
+    pub faction: String,
+    pub anchors: Vec<SpawnPoint>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MapSpawns {
+    pub map: String,
+    pub name: String,
+    pub spawns: Vec<SpawnGroup>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DungeonReward {
+    pub class_id: i32,
+    pub class_name: String,
+    /// Every text and number column of the row, keyed by column name
+    pub properties: BTreeMap<String, String>,
+    /// Text cells that name a known item class
+    pub items: Vec<LinkedClass>,
+}
+
+#[derive(Debug, Default)]
+pub struct SpawnData {
+    // keyed by lowercased map class name
+    pub maps: BTreeMap<String, MapSpawns>,
+    pub dungeon_rewards: Vec<DungeonReward>,
+}
+
+impl SpawnData {
+    pub fn map(&self, map: &str) -> Option<&MapSpawns> {
+        self.maps.get(&map.to_lowercase())
+    }
+
+    /// Every map spawn group of `monster`, as (map, group) pairs
+    pub fn monster_spawns(&self, monster: &str) -> Vec<(&MapSpawns, &SpawnGroup)> {
+        self.maps
+            .values()
+            .flat_map(|map| map.spawns.iter().map(move |group| (map, group)))
+            .filter(|(_, group)| group.monster.class_name.eq_ignore_ascii_case(monster))
+            .collect()
+    }
+}
+
+/// Map class name from a per-map table name, e.g. ("anchor_", "anchor_f_siauliai_west.ies")
+pub fn map_name_from_table(prefix: &str, file_name: &str) -> Option<String> {
+    let lower = file_name.to_ascii_lowercase();
+    let stem = lower.strip_suffix(".ies")?;
+    let map = stem.strip_prefix(prefix)?;
+    (!map.is_empty()).then(|| file_name[prefix.len()..prefix.len() + map.len()].to_string())
+}
+
+fn first_number(lookup: &IESColumnLookup, row: &IESColumnData, columns: &[&str]) -> Option<f32> {
+    columns.iter().find_map(|c| lookup.number(row, c))
+}
+
+fn first_text<'a>(
+    lookup: &IESColumnLookup,
+    row: &'a IESColumnData,
+    columns: &[&str],
+) -> Option<&'a str> {
+    columns
+        .iter()
+        .find_map(|c| lookup.text(row, c).filter(|t| !t.is_empty()))
+}
+
+/// Join a map's gentype and anchor tables into spawn groups. Anchors without a
+/// gentype row come last, with an unresolved monster.
+pub fn parse_map_spawns(
+    gentype: Option<&IESRoot>,
+    anchor: Option<&IESRoot>,
+    monsters: &ClassIndex,
+) -> Vec<SpawnGroup> {
+    let mut anchors: HashMap<i32, Vec<SpawnPoint>> = HashMap::new();
+    if let Some(ies) = anchor {
+        let lookup = ies.column_lookup();
+        for row in &ies.data {
+            let Some(gen_type) = first_number(&lookup, row, &["GenType"]) else {
+                continue;
+            };
+            anchors
+                .entry(gen_type as i32)
+                .or_default()
+                .push(SpawnPoint {
+                    x: first_number(&lookup, row, &["PosX"]).unwrap_or(0.0),
+                    y: first_number(&lookup, row, &["PosY"]).unwrap_or(0.0),
+                    z: first_number(&lookup, row, &["PosZ"]).unwrap_or(0.0),
+                    direction: first_number(&lookup, row, &["Direction", "Dir"]).unwrap_or(0.0),
+                });
+        }
+    }
+
+    let mut groups: Vec<SpawnGroup> = match gentype {
+        Some(ies) => {
+            let lookup = ies.column_lookup();
+            ies.data
+                .iter()
+                .filter_map(|row| {
+                    let gen_type = first_number(&lookup, row, &["GenType"])? as i32;
+                    let monster = first_text(&lookup, row, &["ClassType", "MonsterName"])?;
+
+                    Some(SpawnGroup {
+                        gen_type,
+                        monster: LinkedClass::new(monster, monsters),
+                        max_pop: first_number(&lookup, row, &["MaxPop"]).unwrap_or(0.0) as u32,
+                        respawn_ms: first_number(&lookup, row, &["RespawnTime"]).unwrap_or(0.0)
+                            as u32,
+                        faction: first_text(&lookup, row, &["Faction"])
+                            .unwrap_or_default()
+                            .to_string(),
+                        anchors: anchors.remove(&gen_type).unwrap_or_default(),
+                    })
+                })
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    // Anchors whose GenType has no gentype row still show where something spawns
+    let mut orphans: Vec<(i32, Vec<SpawnPoint>)> = anchors.into_iter().collect();
+    orphans.sort_by_key(|&(gen_type, _)| gen_type);
+    groups.extend(orphans.into_iter().map(|(gen_type, anchors)| SpawnGroup {
+        gen_type,
+        monster: LinkedClass::new("", monsters),
+        max_pop: 0,
+        respawn_ms: 0,
+        faction: String::new(),
+        anchors,
+    }));
+    groups
+}
+
+/// Parse `reward_indun.ies`, linking every cell that names an item
+pub fn parse_dungeon_rewards(ies: &IESRoot, items: &ClassIndex) -> Vec<DungeonReward> {
+    let lookup = ies.column_lookup();
+
+    ies.data
+        .iter()
+        .map(|row| {
+            let properties: BTreeMap<String, String> = lookup
+                .names()
+                .iter()
+                .filter_map(|column| Some((column.clone(), lookup.value(row, column)?)))
+                .collect();
+
+            let items = lookup
+                .names()
+                .iter()
+                .filter(|column| column.as_str() != "ClassName")
+                .filter_map(|column| lookup.text(row, column))
+                .filter(|value| items.get(value).is_some())
+                .map(|value| LinkedClass::new(value, items))
+                .collect();
+
+            DungeonReward {
+                class_id: row.index_data,
+                class_name: lookup
+                    .text(row, "ClassName")
+                    .unwrap_or(&row.row_text.text_data)
+                    .to_string(),
+                properties,
+                items,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ies::{IESColumn, IESRowFloat, IESRowText};
+
+    /// Table with number columns first, then text columns, one row per entry
+    fn table(numbers: &[&str], texts: &[&str], rows: &[(&[f32], &[&str])]) -> IESRoot {
+        let column = |name: &str, type_data, decl_idx| IESColumn {
+            column: name.to_string(),
+            name: name.to_string(),
+            type_data,
+            decl_idx,
+            ..IESColumn::default()
+        };
+        let number_columns = numbers
+            .iter()
+            .enumerate()
+            .map(|(i, name)| column(name, 0, i as u16));
+        let text_columns = texts
+            .iter()
+            .enumerate()
+            .map(|(i, name)| column(name, 1, i as u16));
+        let columns = number_columns.chain(text_columns).collect();
+        let data = rows
+            .iter()
+            .map(|(floats, texts)| IESColumnData {
+                floats: floats
+                    .iter()
+                    .map(|&float_data| IESRowFloat { float_data })
+                    .collect(),
+                texts: texts
+                    .iter()
+                    .map(|text| IESRowText {
+                        text_length: text.len() as u16,
+                        text_data: text.to_string(),
+                    })
+                    .collect(),
+                ..IESColumnData::default()
+            })
+            .collect();
+        IESRoot {
+            columns,
+            data,
+            ..IESRoot::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_map_spawns_joins_anchors() {
+        let gentype = table(
+            &["GenType", "MaxPop", "RespawnTime"],
+            &["ClassType", "Faction"],
+            &[(&[1.0, 5.0, 30000.0], &["Onion", "Monster"])],
+        );
+        let anchor = table(
+            &["GenType", "PosX", "PosY", "PosZ", "Direction"],
+            &[],
+            &[
+                (&[1.0, 10.0, 0.0, 20.0, 90.0], &[]),
+                (&[1.0, 11.0, 0.0, 21.0, 0.0], &[]),
+                // No gentype row for this one
+                (&[7.0, -5.0, 1.0, 3.0, 0.0], &[]),
+            ],
+        );
+
+        let spawns = parse_map_spawns(Some(&gentype), Some(&anchor), &ClassIndex::new());
+        assert_eq!(spawns.len(), 2);
+        assert_eq!(spawns[0].gen_type, 1);
+        assert_eq!(spawns[0].monster.class_name, "Onion");
+        assert_eq!(spawns[0].max_pop, 5);
+        assert_eq!(spawns[0].respawn_ms, 30000);
+        assert_eq!(spawns[0].faction, "Monster");
+        assert_eq!(spawns[0].anchors.len(), 2);
+        assert_eq!(spawns[0].anchors[0].z, 20.0);
+        assert_eq!(spawns[0].anchors[0].direction, 90.0);
+
+        assert_eq!(spawns[1].gen_type, 7);
+        assert!(!spawns[1].monster.resolved);
+        assert_eq!(spawns[1].anchors.len(), 1);
+        assert_eq!(spawns[1].anchors[0].x, -5.0);
+
+        // Anchor table alone
+        let spawns = parse_map_spawns(None, Some(&anchor), &ClassIndex::new());
+        let gen_types: Vec<i32> = spawns.iter().map(|g| g.gen_type).collect();
+        assert_eq!(gen_types, vec![1, 7]);
+    }
+
+    #[test]
+    fn test_map_name_from_table() {
+        assert_eq!(
+            map_name_from_table("anchor_", "anchor_f_Siauliai_west.ies").as_deref(),
+            Some("f_Siauliai_west")
+        );
+        assert_eq!(
+            map_name_from_table("gentype_", "GenType_d_cmine_01.IES").as_deref(),
+            Some("d_cmine_01")
+        );
+        assert_eq!(map_name_from_table("anchor_", "anchor_.ies"), None);
+        assert_eq!(map_name_from_table("anchor_", "gentype_x.ies"), None);
+    }
+}
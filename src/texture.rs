@@ -0,0 +1,285 @@
+//! Resolution of XAC material texture names to archive entries.
+//!
+//! Materials only name a file, often with an extension the game no longer
+//! ships (.tga for what is a .dds) or none at all. The folder comes from the
+//! outside: the model's `xac.ies` Path, a world's TexDir/SubTexDir entries or
+//! the model's own folder. Textures listed in `dds_duplicates.xml` only exist
+//! under the path of the file they duplicate. Names found in none of those
+//! folders fall back to a file name index over the whole tree.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::category::{FileRef, Folder, encode_query_path, normalize_path};
+use crate::threedworld::World;
+
+/// Extensions material texture names come with
+const IMAGE_EXTENSIONS: &[&str] = &["dds", "tga", "png", "bmp", "jpg"];
+
+/// A texture found in the folder tree
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ResolvedTexture {
+    /// Full path inside the tree
+    pub path: String,
+    /// Latest version of the path
+    pub version: usize,
+    pub preview_url: String,
+}
+
+/// Lookups shared by every resolver, built once when the tree is loaded
+pub struct TextureIndex {
+    /// Normalized duplicate path -> normalized path of the file it duplicates
+    dds_duplicates: Arc<HashMap<String, String>>,
+    /// Lowercased image file name -> full path of its first occurrence in the tree
+    by_name: HashMap<String, String>,
+}
+
+impl TextureIndex {
+    pub fn build(tree: &Folder, dds_duplicates: Arc<HashMap<String, String>>) -> Self {
+        let mut by_name = HashMap::new();
+        tree.for_each_file(|full_path, file| {
+            let name = file.name().to_lowercase();
+            let is_image = name
+                .rsplit_once('.')
+                .is_some_and(|(_, ext)| IMAGE_EXTENSIONS.contains(&ext));
+            if is_image && !by_name.contains_key(&name) {
+                by_name.insert(name, full_path.to_string());
+            }
+        });
+        TextureIndex {
+            dds_duplicates,
+            by_name,
+        }
+    }
+
+    /// Distinct image file names in the tree
+    pub fn name_count(&self) -> usize {
+        self.by_name.len()
+    }
+}
+
+pub struct TextureResolver<'a> {
+    tree: &'a Folder,
+    index: &'a TextureIndex,
+    /// Normalized folders, searched in order
+    dirs: Vec<String>,
+    // texture name -> result, textures are shared by many submeshes
+    cache: HashMap<String, Option<ResolvedTexture>>,
+}
+
+impl<'a> TextureResolver<'a> {
+    pub fn new(tree: &'a Folder, index: &'a TextureIndex) -> Self {
+        TextureResolver {
+            tree,
+            index,
+            dirs: Vec::new(),
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Folders for one model: its `xac.ies` Path, then the folder it lives in
+    pub fn for_model(
+        tree: &'a Folder,
+        index: &'a TextureIndex,
+        model_path: &str,
+        mesh_map: &HashMap<String, String>,
+    ) -> Self {
+        let mut resolver = Self::new(tree, index);
+        let model_path = normalize_path(model_path);
+        let file_name = model_path.rsplit('/').next().unwrap_or(&model_path);
+        if let Some(dir) = mesh_map
+            .get(&model_path)
+            .or_else(|| mesh_map.get(file_name))
+        {
+            resolver.add_dir(dir);
+        }
+        if let Some((dir, _)) = model_path.rsplit_once('/') {
+            resolver.add_dir(dir);
+        }
+        resolver
+    }
+
+    /// Search `dir` after the folders added so far
+    pub fn add_dir(&mut self, dir: &str) {
+        let dir = normalize_path(dir);
+        if !self.dirs.contains(&dir) {
+            self.dirs.push(dir);
+            self.cache.clear();
+        }
+    }
+
+    /// TexDir then SubTexDir entries of a world, as "ipf name/path"
+    pub fn add_world_dirs(&mut self, world: &World) {
+        let dirs = world
+            .tex_dirs
+            .iter()
+            .map(|d| (&d.ipf_name, &d.path))
+            .chain(world.sub_tex_dirs.iter().map(|d| (&d.ipf_name, &d.path)));
+        for (ipf_name, path) in dirs {
+            let ipf_name = ipf_name.trim_end_matches(".ipf");
+            self.add_dir(&format!("{}/{}", ipf_name, path));
+        }
+    }
+
+    /// Find the archive entry for a material texture name. Falls back to the
+    /// file name index over the whole tree when no folder has it.
+    pub fn resolve(&mut self, texture: &str) -> Option<ResolvedTexture> {
+        if let Some(resolved) = self.cache.get(texture) {
+            return resolved.clone();
+        }
+
+        let candidates = Self::candidate_names(texture);
+        let in_dirs = self.dirs.iter().find_map(|dir| {
+            candidates
+                .iter()
+                .find_map(|name| self.lookup(&format!("{}/{}", dir, name)))
+        });
+        let resolved = in_dirs
+            .or_else(|| {
+                // Names that already carry their folder
+                texture
+                    .contains(['/', '\\'])
+                    .then(|| self.lookup(texture))
+                    .flatten()
+            })
+            .or_else(|| candidates.iter().find_map(|name| self.search(name)));
+
+        self.cache.insert(texture.to_string(), resolved.clone());
+        resolved
+    }
+
+    /// File name as given, then as .dds and .tga
+    fn candidate_names(texture: &str) -> Vec<String> {
+        let name = normalize_path(texture);
+        let name = name.rsplit('/').next().unwrap_or_default().to_string();
+        if name.is_empty() {
+            return Vec::new();
+        }
+        let stem = match name.rsplit_once('.') {
+            Some((stem, ext)) if IMAGE_EXTENSIONS.contains(&ext) => stem.to_string(),
+            _ => name.clone(),
+        };
+        let mut names = vec![name];
+        for ext in ["dds", "tga"] {
+            let candidate = format!("{}.{}", stem, ext);
+            if !names.contains(&candidate) {
+                names.push(candidate);
+            }
+        }
+        names
+    }
+
+    /// Exact path, redirected to the original when it is a known duplicate
+    fn lookup(&self, path: &str) -> Option<ResolvedTexture> {
+        let path = normalize_path(path);
+        let path = self.index.dds_duplicates.get(&path).unwrap_or(&path);
+        Self::latest(self.tree.search_file_by_full_path(path))
+    }
+
+    fn search(&self, name: &str) -> Option<ResolvedTexture> {
+        self.lookup(self.index.by_name.get(name)?)
+    }
+
+    fn latest(versions: Vec<(String, FileRef<'_>)>) -> Option<ResolvedTexture> {
+        let version = versions.len().checked_sub(1)?;
+        let (path, _) = versions.into_iter().next_back()?;
+        Some(ResolvedTexture {
+            preview_url: format!(
+                "/api/file/preview?path={}&version={}",
+                encode_query_path(&path),
+                version
+            ),
+            path,
+            version,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    use crate::category::build_tree;
+    use crate::ipf::IPFFileTable;
+
+    fn tree(paths: &[&str]) -> Folder {
+        let mut grouped = BTreeMap::new();
+        for path in paths {
+            let name = path.rsplit('/').next().unwrap();
+            grouped.insert(
+                path.to_string(),
+                vec![IPFFileTable {
+                    directory_name: name.to_string(),
+                    ..IPFFileTable::default()
+                }],
+            );
+        }
+        build_tree(grouped)
+    }
+
+    #[test]
+    fn test_resolve_model_textures() {
+        let tree = tree(&[
+            "char_texture/npc/npc_lecifer.dds",
+            "char_hi/npc/npc_lecifer_set.xac",
+            "char_hi/npc/lecifer_eye.tga",
+            "bg_texture/other/stray.dds",
+        ]);
+        let mut mesh_map = HashMap::new();
+        mesh_map.insert(
+            "char_hi/npc/npc_lecifer_set.xac".to_string(),
+            "char_texture/npc/".to_string(),
+        );
+        let index = TextureIndex::build(&tree, Arc::default());
+        let mut resolver =
+            TextureResolver::for_model(&tree, &index, "char_hi/npc/npc_lecifer_set.xac", &mesh_map);
+
+        // .tga names ship as .dds in the xac.ies folder
+        let resolved = resolver.resolve("npc_lecifer.tga").unwrap();
+        assert_eq!(resolved.path, "char_texture/npc/npc_lecifer.dds");
+        assert_eq!(resolved.version, 0);
+        assert_eq!(
+            resolved.preview_url,
+            "/api/file/preview?path=char_texture/npc/npc_lecifer.dds&version=0"
+        );
+        // Next to the model, without an extension
+        let resolved = resolver.resolve("Lecifer_Eye").unwrap();
+        assert_eq!(resolved.path, "char_hi/npc/lecifer_eye.tga");
+        // Anywhere in the tree as a last resort
+        let resolved = resolver.resolve("stray.tga").unwrap();
+        assert_eq!(resolved.path, "bg_texture/other/stray.dds");
+        assert!(resolver.resolve("missing.dds").is_none());
+    }
+
+    #[test]
+    fn test_resolve_world_textures_and_duplicates() {
+        let tree = tree(&["bg_texture/barrack/wall.dds", "bg_hi/barrack3/floor.dds"]);
+        let world = World::from_bytes(
+            br#"<World>
+                <TexDir IpfName="bg_texture" Path="\barrack\"/>
+                <SubTexDir IpfName="bg_hi" Path="\barrack3\"/>
+            </World>"#,
+        )
+        .unwrap();
+        let mut duplicates = HashMap::new();
+        duplicates.insert(
+            "bg_texture/barrack/wall_copy.dds".to_string(),
+            "bg_texture/barrack/wall.dds".to_string(),
+        );
+
+        let index = TextureIndex::build(&tree, Arc::new(duplicates));
+        let mut resolver = TextureResolver::new(&tree, &index);
+        resolver.add_world_dirs(&world);
+        assert_eq!(
+            resolver.resolve("floor.tga").unwrap().path,
+            "bg_hi/barrack3/floor.dds"
+        );
+        assert_eq!(
+            resolver.resolve("wall_copy.dds").unwrap().path,
+            "bg_texture/barrack/wall.dds"
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     fs::File,
     io::{self, BufRead, BufReader},
     path::Path,
@@ -37,3 +38,61 @@ pub fn parse_language_data_parallel(
 pub fn parse_language_data(lang_folder: &Path) -> io::Result<(Vec<Vec<String>>, Vec<Vec<String>>)> {
     parse_language_data_parallel(lang_folder)
 }
+
+/// Build a key -> text dictionary from parsed language rows (first column is the key)
+pub fn build_dictionary(rows: &[Vec<String>]) -> HashMap<String, String> {
+    rows.iter()
+        .filter(|cols| cols.len() >= 2 && !cols[0].is_empty())
+        .map(|cols| (cols[0].clone(), cols[1].clone()))
+        .collect()
+}
+
+/// Replace every `@dicID_^*$KEY$*^` reference in `text` with its dictionary entry.
+///
+/// Unknown keys are left as the bare key so the output stays readable.
+pub fn resolve_dic_ids(text: &str, dictionary: &HashMap<String, String>) -> String {
+    const OPEN: &str = "@dicID_^*$";
+    const CLOSE: &str = "$*^";
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find(OPEN) {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + OPEN.len()..];
+        match after.find(CLOSE) {
+            Some(end) => {
+                let key = &after[..end];
+                out.push_str(dictionary.get(key).map(String::as_str).unwrap_or(key));
+                rest = &after[end + CLOSE.len()..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_dic_ids() {
+        let rows = vec![
+            vec!["ITEM_1".to_string(), "Red Potion".to_string()],
+            vec!["ITEM_2".to_string(), "Blue Potion".to_string()],
+        ];
+        let dict = build_dictionary(&rows);
+
+        assert_eq!(resolve_dic_ids("@dicID_^*$ITEM_1$*^", &dict), "Red Potion");
+        assert_eq!(
+            resolve_dic_ids("@dicID_^*$ITEM_1$*^ / @dicID_^*$ITEM_3$*^", &dict),
+            "Red Potion / ITEM_3"
+        );
+        assert_eq!(resolve_dic_ids("Plain", &dict), "Plain");
+    }
+}
@@ -0,0 +1,676 @@
+//! Decompression of wavelet compressed XSM motions.
+//!
+//! A wavelet motion stores its tracks in chunks of `samples_per_chunk`
+//! evenly spaced samples. Each chunk holds three entropy coded streams
+//! (rotations, positions, scales) of 16-bit quantized wavelet coefficients.
+//! Decoding a stream gives, per track component, one signal of
+//! `samples_per_chunk` coefficients: divided by the chunk's quant scale and
+//! inverse transformed they become the sampled values.
+//!
+//! Rotation streams hold 4 components per track, the regular rotation tracks
+//! first and the scale rotation tracks from `scale_rot_offset` on; position
+//! and scale streams hold 3. The Huffman and Rice streams use the bit layout
+//! of the Basic Compression Library coders.
+
+use std::io;
+
+use crate::xsm::{
+    CompressorType, FileQuaternion, FileVector3, WaveletType, XSMQuaternionKey,
+    XSMSkeletalSubMotion2, XSMVector3Key, XSMWaveletChunk, XSMWaveletInfo,
+};
+
+/// Mapping index of a submotion without a track of that kind
+const NO_TRACK: u16 = u16::MAX;
+
+impl WaveletType {
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(WaveletType::Haar),
+            1 => Some(WaveletType::D4),
+            2 => Some(WaveletType::CDF97),
+            _ => None,
+        }
+    }
+
+    /// Shortest signal one transform level works on
+    fn min_len(self) -> usize {
+        match self {
+            WaveletType::D4 => 4,
+            WaveletType::Haar | WaveletType::CDF97 => 2,
+        }
+    }
+
+    /// Signal lengths of the levels, longest first. A level halves the
+    /// signal, so odd lengths end the decomposition.
+    fn levels(self, len: usize) -> Vec<usize> {
+        let mut levels = Vec::new();
+        let mut len = len;
+        while len >= self.min_len() && len.is_multiple_of(2) {
+            levels.push(len);
+            len /= 2;
+        }
+        levels
+    }
+
+    /// Full decomposition: approximation first, then the details of each level
+    pub fn forward(self, data: &mut [f32]) {
+        for len in self.levels(data.len()) {
+            let signal = &mut data[..len];
+            match self {
+                WaveletType::Haar => haar_forward(signal),
+                WaveletType::D4 => d4_forward(signal),
+                WaveletType::CDF97 => cdf97_forward(signal),
+            }
+        }
+    }
+
+    pub fn inverse(self, data: &mut [f32]) {
+        for len in self.levels(data.len()).into_iter().rev() {
+            let signal = &mut data[..len];
+            match self {
+                WaveletType::Haar => haar_inverse(signal),
+                WaveletType::D4 => d4_inverse(signal),
+                WaveletType::CDF97 => cdf97_inverse(signal),
+            }
+        }
+    }
+}
+
+fn haar_forward(data: &mut [f32]) {
+    let half = data.len() / 2;
+    let mut out = vec![0.0; data.len()];
+    for i in 0..half {
+        let (a, b) = (data[2 * i], data[2 * i + 1]);
+        out[i] = (a + b) * std::f32::consts::FRAC_1_SQRT_2;
+        out[half + i] = (a - b) * std::f32::consts::FRAC_1_SQRT_2;
+    }
+    data.copy_from_slice(&out);
+}
+
+fn haar_inverse(data: &mut [f32]) {
+    let half = data.len() / 2;
+    let mut out = vec![0.0; data.len()];
+    for i in 0..half {
+        let (s, d) = (data[i], data[half + i]);
+        out[2 * i] = (s + d) * std::f32::consts::FRAC_1_SQRT_2;
+        out[2 * i + 1] = (s - d) * std::f32::consts::FRAC_1_SQRT_2;
+    }
+    data.copy_from_slice(&out);
+}
+
+// Daubechies 4 filter, periodic at the signal ends
+const D4: [f32; 4] = [0.482_962_9, 0.836_516_3, 0.224_143_87, -0.129_409_52];
+
+fn d4_forward(data: &mut [f32]) {
+    let n = data.len();
+    let half = n / 2;
+    let [c0, c1, c2, c3] = D4;
+    let mut out = vec![0.0; n];
+    for i in 0..half {
+        let [a, b, c, d] = [0, 1, 2, 3].map(|k| data[(2 * i + k) % n]);
+        out[i] = c0 * a + c1 * b + c2 * c + c3 * d;
+        out[half + i] = c3 * a - c2 * b + c1 * c - c0 * d;
+    }
+    data.copy_from_slice(&out);
+}
+
+fn d4_inverse(data: &mut [f32]) {
+    let n = data.len();
+    let half = n / 2;
+    let [c0, c1, c2, c3] = D4;
+    let mut out = vec![0.0; n];
+    for i in 0..half {
+        // Pairs i - 1 and i overlap output samples 2i and 2i + 1
+        let prev = (i + half - 1) % half;
+        let (s0, d0, s1, d1) = (data[prev], data[half + prev], data[i], data[half + i]);
+        out[2 * i] = c2 * s0 + c1 * d0 + c0 * s1 + c3 * d1;
+        out[2 * i + 1] = c3 * s0 - c0 * d0 + c1 * s1 - c2 * d1;
+    }
+    data.copy_from_slice(&out);
+}
+
+// CDF 9/7 lifting steps and scaling, symmetric at the signal ends
+const CDF97_LIFT: [f32; 4] = [-1.586_134_3, -0.052_980_117, 0.882_911_1, 0.443_506_87];
+const CDF97_SCALE: f32 = 1.149_604_4;
+
+/// Lift the odd samples from their even neighbours, or the even from the odd
+fn cdf97_lift(data: &mut [f32], odd: bool, coefficient: f32) {
+    let n = data.len();
+    if odd {
+        for i in (1..n.saturating_sub(2)).step_by(2) {
+            data[i] += coefficient * (data[i - 1] + data[i + 1]);
+        }
+        data[n - 1] += 2.0 * coefficient * data[n - 2];
+    } else {
+        for i in (2..n).step_by(2) {
+            data[i] += coefficient * (data[i - 1] + data[i + 1]);
+        }
+        data[0] += 2.0 * coefficient * data[1];
+    }
+}
+
+fn cdf97_forward(data: &mut [f32]) {
+    for (step, &coefficient) in CDF97_LIFT.iter().enumerate() {
+        cdf97_lift(data, step % 2 == 0, coefficient);
+    }
+    let half = data.len() / 2;
+    let mut out = vec![0.0; data.len()];
+    for i in 0..half {
+        out[i] = data[2 * i] * CDF97_SCALE;
+        out[half + i] = data[2 * i + 1] / CDF97_SCALE;
+    }
+    data.copy_from_slice(&out);
+}
+
+fn cdf97_inverse(data: &mut [f32]) {
+    let half = data.len() / 2;
+    let mut out = vec![0.0; data.len()];
+    for i in 0..half {
+        out[2 * i] = data[i] / CDF97_SCALE;
+        out[2 * i + 1] = data[half + i] * CDF97_SCALE;
+    }
+    data.copy_from_slice(&out);
+    for (step, &coefficient) in CDF97_LIFT.iter().enumerate().rev() {
+        cdf97_lift(data, step % 2 == 0, -coefficient);
+    }
+}
+
+/// Most significant bit first, reading zeros past the end like the encoder
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl BitReader<'_> {
+    fn bit(&mut self) -> u8 {
+        let bit = self
+            .data
+            .get(self.pos / 8)
+            .map_or(0, |byte| (byte >> (7 - self.pos % 8)) & 1);
+        self.pos += 1;
+        bit
+    }
+
+    fn bits(&mut self, count: usize) -> u64 {
+        (0..count).fold(0, |value, _| (value << 1) | self.bit() as u64)
+    }
+
+    fn exhausted(&self) -> bool {
+        self.pos > self.data.len() * 8
+    }
+
+    fn remaining(&self) -> usize {
+        (self.data.len() * 8).saturating_sub(self.pos)
+    }
+}
+
+impl CompressorType {
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(CompressorType::Huffman),
+            1 => Some(CompressorType::Rice),
+            _ => None,
+        }
+    }
+
+    /// Decode `count` 16-bit values
+    pub fn decompress(self, data: &[u8], count: usize) -> io::Result<Vec<i16>> {
+        match self {
+            CompressorType::Huffman => {
+                let bytes = huffman_decompress(data, count * 2)?;
+                Ok(bytes
+                    .chunks_exact(2)
+                    .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+                    .collect())
+            }
+            CompressorType::Rice => rice_decompress(data, count),
+        }
+    }
+}
+
+enum HuffmanNode {
+    Leaf(u8),
+    Branch(usize, usize),
+}
+
+/// The tree is stored depth first: a 1 bit and 8 bit symbol per leaf, a 0
+/// bit per branch followed by its two subtrees
+fn huffman_decompress(data: &[u8], size: usize) -> io::Result<Vec<u8>> {
+    fn read_tree(reader: &mut BitReader, nodes: &mut Vec<HuffmanNode>) -> io::Result<usize> {
+        // 256 leaves need 511 nodes, more means a corrupt tree
+        if nodes.len() >= 511 || reader.exhausted() {
+            return Err(invalid("Invalid Huffman tree"));
+        }
+        let index = nodes.len();
+        if reader.bit() == 1 {
+            nodes.push(HuffmanNode::Leaf(reader.bits(8) as u8));
+        } else {
+            nodes.push(HuffmanNode::Branch(0, 0));
+            let a = read_tree(reader, nodes)?;
+            let b = read_tree(reader, nodes)?;
+            nodes[index] = HuffmanNode::Branch(a, b);
+        }
+        Ok(index)
+    }
+
+    if size == 0 {
+        return Ok(Vec::new());
+    }
+    let mut reader = BitReader { data, pos: 0 };
+    let mut nodes = Vec::new();
+    read_tree(&mut reader, &mut nodes)?;
+    // Every symbol costs at least one bit, so the size in the header can not
+    // be trusted past what is left of the stream
+    if size > reader.remaining() {
+        return Err(invalid("Huffman stream shorter than its size"));
+    }
+
+    let mut out = Vec::with_capacity(size);
+    while out.len() < size {
+        let mut node = 0;
+        loop {
+            match nodes[node] {
+                HuffmanNode::Leaf(symbol) => break out.push(symbol),
+                HuffmanNode::Branch(a, b) => node = if reader.bit() == 1 { b } else { a },
+            }
+        }
+        if reader.exhausted() {
+            return Err(invalid("Huffman stream ended early"));
+        }
+    }
+    Ok(out)
+}
+
+// Words coded with a Rice code of `k` bits, `k` following the mean bit length
+// of the last RICE_HISTORY words. Unary prefixes past RICE_THRESHOLD switch to
+// a binary coded overflow.
+const RICE_HISTORY: usize = 16;
+const RICE_THRESHOLD: u64 = 8;
+
+/// First byte is the initial `k` plus one, or zero for raw little endian words
+fn rice_decompress(data: &[u8], count: usize) -> io::Result<Vec<i16>> {
+    let Some((&first, rest)) = data.split_first() else {
+        return if count == 0 {
+            Ok(Vec::new())
+        } else {
+            Err(invalid("Empty Rice stream"))
+        };
+    };
+    if first == 0 {
+        if rest.len() < count * 2 {
+            return Err(invalid("Rice stream ended early"));
+        }
+        return Ok(rest
+            .chunks_exact(2)
+            .take(count)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect());
+    }
+
+    // Every word ends its unary prefix with a zero bit
+    if count > rest.len() * 8 {
+        return Err(invalid("Rice stream shorter than its size"));
+    }
+    let mut reader = BitReader { data: rest, pos: 0 };
+    let mut k = (first - 1) as usize;
+    let mut history = [0usize; RICE_HISTORY];
+    let mut out = Vec::with_capacity(count);
+    for i in 0..count {
+        if i >= RICE_HISTORY {
+            k = (history.iter().sum::<usize>() + RICE_HISTORY / 2) / RICE_HISTORY;
+        }
+
+        let mut q: u64 = 0;
+        while reader.bit() == 1 {
+            q += 1;
+            // Prefixes of 16-bit words stay well below this
+            if q > 16 + RICE_THRESHOLD || reader.exhausted() {
+                return Err(invalid("Invalid Rice stream"));
+            }
+        }
+        let high = if q > RICE_THRESHOLD {
+            let extra = (q - RICE_THRESHOLD - 1) as usize;
+            ((1 << extra) | reader.bits(extra)) + RICE_THRESHOLD
+        } else {
+            q
+        };
+        if k > 16 || reader.exhausted() {
+            return Err(invalid("Invalid Rice stream"));
+        }
+        let x = (high << k) | reader.bits(k);
+
+        history[i % RICE_HISTORY] = (u64::BITS - x.leading_zeros()) as usize;
+        // Signed words are stored as 2v for v >= 0 and -2v - 1 below zero
+        let value = if x & 1 == 1 {
+            -(x.div_ceil(2) as i64)
+        } else {
+            (x / 2) as i64
+        };
+        out.push(value as i16);
+    }
+    Ok(out)
+}
+
+fn track(index: u16) -> Option<usize> {
+    (index != NO_TRACK).then_some(index as usize)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Sampled values of one stream: `[track][component][sample]`
+struct Signals {
+    values: Vec<f32>,
+    components: usize,
+    samples: usize,
+}
+
+impl Signals {
+    fn get(&self, track: usize, component: usize, sample: usize) -> Option<f32> {
+        let index = (track * self.components + component) * self.samples + sample;
+        self.values.get(index).copied()
+    }
+}
+
+impl XSMWaveletInfo {
+    /// Keyframe tracks equal to those of the keyframed submotion chunks, one
+    /// key per sample
+    pub fn decompress(&self) -> io::Result<Vec<XSMSkeletalSubMotion2>> {
+        let wavelet = WaveletType::from_id(self.wavelet_id)
+            .ok_or_else(|| invalid(&format!("Unknown wavelet {}", self.wavelet_id)))?;
+        let compressor = CompressorType::from_id(self.compressor_id)
+            .ok_or_else(|| invalid(&format!("Unknown compressor {}", self.compressor_id)))?;
+        let samples = self.samples_per_chunk as usize;
+
+        let mut sub_motions: Vec<XSMSkeletalSubMotion2> = self
+            .sub_motions
+            .iter()
+            .map(|s| XSMSkeletalSubMotion2 {
+                pose_rot: s.pose_rot.to_quaternion(),
+                bind_pose_rot: s.bind_pose_rot.to_quaternion(),
+                pose_scale_rot: s.pose_scale_rot.to_quaternion(),
+                bind_pose_scale_rot: s.bind_pose_scale_rot.to_quaternion(),
+                pose_pos: s.pose_pos,
+                pose_scale: s.pose_scale,
+                bind_pose_pos: s.bind_pose_pos,
+                bind_pose_scale: s.bind_pose_scale,
+                max_error: s.max_error,
+                name: s.name.clone(),
+                ..XSMSkeletalSubMotion2::default()
+            })
+            .collect();
+        if samples == 0 {
+            return Ok(sub_motions);
+        }
+
+        for chunk in &self.chunks {
+            let decode = |data: &[u8], bytes: u32, quant_scale: f32, components: usize| {
+                self.decode_stream(compressor, wavelet, data, bytes, quant_scale, components)
+            };
+            let rotations = decode(
+                &chunk.compressed_rot_data,
+                self.decompressed_rot_num_bytes,
+                chunk.rot_quant_scale,
+                4,
+            )?;
+            let positions = decode(
+                &chunk.compressed_pos_data,
+                self.decompressed_pos_num_bytes,
+                chunk.pos_quant_scale,
+                3,
+            )?;
+            let scales = decode(
+                &chunk.compressed_scale_data,
+                self.decompressed_scale_num_bytes,
+                chunk.scale_quant_scale,
+                3,
+            )?;
+
+            for (sub_motion, mapping) in sub_motions.iter_mut().zip(&self.mappings) {
+                let pos_track = track(mapping.pos_index);
+                let rot_track = track(mapping.rot_index);
+                let scale_track = track(mapping.scale_index);
+                let scale_rot_track = track(mapping.scale_rot_index)
+                    .map(|index| self.scale_rot_offset as usize + index);
+                for sample in 0..samples {
+                    let Some(time) = self.sample_time(chunk, sample) else {
+                        continue;
+                    };
+                    let vector = |signals: &Signals, track: Option<usize>| {
+                        Self::vector(signals, track?, sample)
+                            .map(|value| XSMVector3Key { value, time })
+                    };
+                    let quaternion = |track: Option<usize>| {
+                        Self::quaternion(&rotations, track?, sample)
+                            .map(|value| XSMQuaternionKey { value, time })
+                    };
+                    sub_motion.pos_keys.extend(vector(&positions, pos_track));
+                    sub_motion.rot_keys.extend(quaternion(rot_track));
+                    sub_motion.scale_keys.extend(vector(&scales, scale_track));
+                    sub_motion
+                        .scale_rot_keys
+                        .extend(quaternion(scale_rot_track));
+                }
+            }
+        }
+
+        for sub_motion in &mut sub_motions {
+            sub_motion.num_pos_keys = sub_motion.pos_keys.len() as u32;
+            sub_motion.num_rot_keys = sub_motion.rot_keys.len() as u32;
+            sub_motion.num_scale_keys = sub_motion.scale_keys.len() as u32;
+            sub_motion.num_scale_rot_keys = sub_motion.scale_rot_keys.len() as u32;
+        }
+        Ok(sub_motions)
+    }
+
+    /// Entropy decode, dequantize and inverse transform one stream of a chunk
+    fn decode_stream(
+        &self,
+        compressor: CompressorType,
+        wavelet: WaveletType,
+        data: &[u8],
+        num_bytes: u32,
+        quant_scale: f32,
+        components: usize,
+    ) -> io::Result<Signals> {
+        let samples = self.samples_per_chunk as usize;
+        let count = num_bytes as usize / 2;
+        if count == 0 {
+            return Ok(Signals {
+                values: Vec::new(),
+                components,
+                samples,
+            });
+        }
+        if !count.is_multiple_of(samples) {
+            return Err(invalid("Wavelet stream is not a whole number of signals"));
+        }
+
+        let quantized = compressor.decompress(data, count)?;
+        let dequantize = if quant_scale != 0.0 {
+            1.0 / quant_scale
+        } else {
+            0.0
+        };
+        let mut values: Vec<f32> = quantized.iter().map(|&q| q as f32 * dequantize).collect();
+        for signal in values.chunks_exact_mut(samples) {
+            wavelet.inverse(signal);
+        }
+        Ok(Signals {
+            values,
+            components,
+            samples,
+        })
+    }
+
+    /// Time of a sample, `None` past the end of the motion
+    fn sample_time(&self, chunk: &XSMWaveletChunk, sample: usize) -> Option<f32> {
+        let time = chunk.start_time + sample as f32 * self.sample_spacing;
+        (time <= self.max_time + self.sample_spacing * 0.5).then(|| time.min(self.max_time))
+    }
+
+    fn vector(signals: &Signals, track: usize, sample: usize) -> Option<FileVector3> {
+        let component = |c| signals.get(track, c, sample);
+        Some(FileVector3 {
+            x: component(0)?,
+            y: component(1)?,
+            z: component(2)?,
+        })
+    }
+
+    fn quaternion(signals: &Signals, track: usize, sample: usize) -> Option<FileQuaternion> {
+        let component = |c| signals.get(track, c, sample);
+        let (x, y, z, w) = (component(0)?, component(1)?, component(2)?, component(3)?);
+        let len = (x * x + y * y + z * z + w * w).sqrt();
+        if len == 0.0 || !len.is_finite() {
+            return None;
+        }
+        Some(FileQuaternion {
+            x: x / len,
+            y: y / len,
+            z: z / len,
+            w: w / len,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use binrw::BinReaderExt;
+    use std::io::Cursor;
+
+    fn signal() -> Vec<f32> {
+        (0..16)
+            .map(|i| (i as f32 * 0.7).sin() * 3.0 + i as f32)
+            .collect()
+    }
+
+    #[test]
+    fn test_wavelet_round_trip() {
+        for wavelet in [WaveletType::Haar, WaveletType::D4, WaveletType::CDF97] {
+            let original = signal();
+            let mut data = original.clone();
+            wavelet.forward(&mut data);
+            assert!(
+                data.iter().zip(&original).any(|(a, b)| (a - b).abs() > 0.1),
+                "{:?} left the signal as it was",
+                wavelet
+            );
+            wavelet.inverse(&mut data);
+            for (a, b) in data.iter().zip(&original) {
+                assert!((a - b).abs() < 1e-3, "{:?}: {} != {}", wavelet, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rice_decompress() {
+        // k = 1: 0 -> "0 0", 1 -> "10 0", -1 -> "0 1", 3 -> "1110 0"
+        let values = CompressorType::Rice
+            .decompress(&[2, 0x23, 0xC0], 4)
+            .unwrap();
+        assert_eq!(values, [0, 1, -1, 3]);
+        // k = 0, 10 is word 20: eight ones, four overflow bits, "100"
+        let values = CompressorType::Rice
+            .decompress(&[1, 0xFF, 0xF4], 1)
+            .unwrap();
+        assert_eq!(values, [10]);
+        // Incompressible data is stored raw
+        let values = CompressorType::Rice
+            .decompress(&[0, 0x34, 0x12], 1)
+            .unwrap();
+        assert_eq!(values, [0x1234]);
+        assert!(CompressorType::Rice.decompress(&[0, 0x34], 1).is_err());
+        // Sizes past what the stream can hold fail before allocating
+        let huge = CompressorType::Rice.decompress(&[2, 0x23, 0xC0], usize::MAX / 4);
+        assert!(huge.is_err());
+    }
+
+    #[test]
+    fn test_huffman_decompress() {
+        // Tree of leaves 0x00 ("0") and 0x05 ("1"), then the bytes 05 00 00 00
+        let values = CompressorType::Huffman
+            .decompress(&[0x40, 0x20, 0xB0], 2)
+            .unwrap();
+        assert_eq!(values, [5, 0]);
+        assert!(CompressorType::Huffman.decompress(&[], 2).is_err());
+        let huge = CompressorType::Huffman.decompress(&[0x40, 0x20, 0xB0], usize::MAX / 4);
+        assert!(huge.is_err());
+    }
+
+    /// Coefficients of one track, quantized with `quant_scale`, as a raw Rice stream
+    fn raw_stream(components: &[[f32; 4]], quant_scale: f32) -> Vec<u8> {
+        let mut stream = vec![0];
+        for component in components {
+            let mut data = component.to_vec();
+            WaveletType::Haar.forward(&mut data);
+            for value in data {
+                let quantized = (value * quant_scale).round() as i16;
+                stream.extend(quantized.to_le_bytes());
+            }
+        }
+        stream
+    }
+
+    #[test]
+    fn test_decompress_wavelet_info() {
+        let rot = raw_stream(&[[0.0; 4], [0.0; 4], [0.0; 4], [1.0; 4]], 1000.0);
+        let pos = raw_stream(&[[0.0, 1.0, 2.0, 3.0], [5.0; 4], [0.0; 4]], 1000.0);
+
+        let mut bytes = Vec::new();
+        let u32s = |bytes: &mut Vec<u8>, values: &[u32]| {
+            bytes.extend(values.iter().flat_map(|v| v.to_le_bytes()))
+        };
+        // chunks, samples, decompressed rot/pos/scale bytes, rot/scale rot/scale/pos tracks
+        u32s(&mut bytes, &[1, 4, 32, 24, 0, 1, 0, 0, 1]);
+        // overhead and sizes, scale rot offset, submotions
+        u32s(&mut bytes, &[0, 0, 0, 0, 1, 1]);
+        // quant factors, spacing, seconds per chunk, max time
+        for value in [1000.0f32, 1000.0, 1000.0, 0.1, 0.4, 0.3] {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.extend([0, 1, 0, 0]); // Haar, Rice
+        for index in [0u16, 0, NO_TRACK, NO_TRACK] {
+            bytes.extend(index.to_le_bytes());
+        }
+        for _ in 0..4 {
+            bytes.extend([0i16, 0, 0, i16::MAX].iter().flat_map(|v| v.to_le_bytes()));
+        }
+        for value in [
+            0.0f32, 0.0, 0.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 0.0,
+        ] {
+            bytes.extend(value.to_le_bytes());
+        }
+        u32s(&mut bytes, &[5]);
+        bytes.extend(b"Bip01");
+        for value in [1000.0f32, 1000.0, 1000.0, 0.0] {
+            bytes.extend(value.to_le_bytes());
+        }
+        u32s(
+            &mut bytes,
+            &[rot.len() as u32, pos.len() as u32, 0, 0, 0, 0],
+        );
+        bytes.extend(&rot);
+        bytes.extend(&pos);
+
+        let info: XSMWaveletInfo = Cursor::new(bytes).read_le().unwrap();
+        let sub_motions = info.decompress().unwrap();
+        assert_eq!(sub_motions.len(), 1);
+        let sub_motion = &sub_motions[0];
+        assert_eq!(sub_motion.name, "Bip01");
+        assert_eq!(sub_motion.num_pos_keys, 4);
+        assert_eq!(sub_motion.num_rot_keys, 4);
+        assert!(sub_motion.scale_keys.is_empty() && sub_motion.scale_rot_keys.is_empty());
+
+        for (i, key) in sub_motion.pos_keys.iter().enumerate() {
+            assert!((key.time - i as f32 * 0.1).abs() < 1e-5);
+            assert!((key.value.x - i as f32).abs() < 1e-2);
+            assert!((key.value.y - 5.0).abs() < 1e-2);
+        }
+        for key in &sub_motion.rot_keys {
+            assert!((key.value.w - 1.0).abs() < 1e-4);
+        }
+    }
+}
@@ -54,3 +54,18 @@ pub async fn index(tera: web::Data<Tera>) -> impl Responder {
         }
     }
 }
+
+#[get("/recipe")]
+pub async fn recipe_page(tera: web::Data<Tera>) -> impl Responder {
+    let mut ctx = Context::new();
+    ctx.insert("title", "Recipe Explorer");
+
+    // Render template
+    match tera.render("recipe.html", &ctx) {
+        Ok(rendered) => HttpResponse::Ok().content_type("text/html").body(rendered),
+        Err(e) => {
+            println!("Tera render error: {:?}", e);
+            HttpResponse::InternalServerError().body(format!("Failed to render template: {}", e))
+        }
+    }
+}
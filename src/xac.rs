@@ -1,5 +1,6 @@
 use binrw::{BinRead, BinReaderExt, BinResult, binread};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{self, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
 use std::path::Path;
@@ -871,7 +872,7 @@ pub struct XACPMorphTargets {
     pub num_morph_targets: u32, // number of morph targets
     pub lod: u32,               // LOD level
     #[br(count = num_morph_targets)]
-    pub morph_targets: Vec<XACPMorphTargets>,
+    pub morph_targets: Vec<XACPMorphTarget>,
 }
 
 #[binread]
@@ -1201,12 +1202,15 @@ impl XACRoot {
 
     fn read_chunks<R: Read + Seek>(reader: &mut R) -> io::Result<Vec<XACChunkEntry>> {
         let mut chunks = Vec::new();
+        // (node index, lod) -> num_org_verts of the meshes read so far, which
+        // sizes the per-vertex tables of the skinning chunk that follows a mesh
+        let mut org_verts: HashMap<(u32, u32), u32> = HashMap::new();
 
         while let Ok(chunk) = FileChunk::read(reader) {
             let start_pos = reader.seek(SeekFrom::Current(0))?;
 
             // Attempt to parse directly from the reader
-            let chunk_data = match Self::parse_chunk_data(&chunk, reader) {
+            let chunk_data = match Self::parse_chunk_data(&chunk, reader, &org_verts) {
                 Ok(data) => data,
                 Err(e) => {
                     eprintln!("Failed to parse chunk {}: {:?}", chunk.chunk_id, e);
@@ -1229,15 +1233,51 @@ impl XACRoot {
                 reader.seek(SeekFrom::Start(fallback_end))?;
             }
 
+            match &chunk_data {
+                XACChunkData::XACMesh(mesh) => {
+                    org_verts.insert((mesh.node_index, 0), mesh.num_org_verts);
+                }
+                XACChunkData::XACMesh2(mesh) => {
+                    org_verts.insert((mesh.node_index, mesh.lod), mesh.num_org_verts);
+                }
+                _ => {}
+            }
+
             chunks.push(XACChunkEntry { chunk, chunk_data });
         }
 
         Ok(chunks)
     }
 
+    /// num_org_verts of the mesh a skinning chunk belongs to, read from the
+    /// chunk's leading node index (and lod for version 4) without consuming it
+    fn skinning_org_verts<R: Read + Seek>(
+        chunk: &FileChunk,
+        reader: &mut R,
+        org_verts: &HashMap<(u32, u32), u32>,
+    ) -> Result<u32, binrw::Error> {
+        let start = reader.stream_position()?;
+        let node_index: u32 = reader.read_le()?;
+        let lod: u32 = if chunk.version == 4 {
+            reader.read_le()?
+        } else {
+            0
+        };
+        reader.seek(SeekFrom::Start(start))?;
+
+        org_verts
+            .get(&(node_index, lod))
+            .copied()
+            .ok_or_else(|| binrw::Error::AssertFail {
+                pos: start,
+                message: format!("Skinning info for node {} has no mesh", node_index),
+            })
+    }
+
     fn parse_chunk_data<R: Read + Seek>(
         chunk: &FileChunk,
         reader: &mut R,
+        org_verts: &HashMap<(u32, u32), u32>,
     ) -> Result<XACChunkData, binrw::Error> {
         match chunk.chunk_id {
             x if x == XACChunk::XACChunkInfo as u32 => match chunk.version {
@@ -1257,14 +1297,18 @@ impl XACRoot {
                 _ => Self::unsupported(chunk, reader),
             },
 
-            x if x == XACChunk::XACChunkSkinninginfo as u32 => match chunk.version {
-                1 => Ok(XACChunkData::XACSkinningInfo(reader.read_le()?)),
-                2 => Ok(XACChunkData::XACSkinningInfo2(reader.read_le()?)),
-                3 => Ok(XACChunkData::XACSkinningInfo3(reader.read_le()?)),
-                4 => Ok(XACChunkData::XACSkinningInfo4(reader.read_le()?)),
-
-                _ => Self::unsupported(chunk, reader),
-            },
+            x if x == XACChunk::XACChunkSkinninginfo as u32 => {
+                let args = match chunk.version {
+                    1..=4 => (Self::skinning_org_verts(chunk, reader, org_verts)?,),
+                    _ => return Self::unsupported(chunk, reader),
+                };
+                match chunk.version {
+                    1 => Ok(XACChunkData::XACSkinningInfo(reader.read_le_args(args)?)),
+                    2 => Ok(XACChunkData::XACSkinningInfo2(reader.read_le_args(args)?)),
+                    3 => Ok(XACChunkData::XACSkinningInfo3(reader.read_le_args(args)?)),
+                    _ => Ok(XACChunkData::XACSkinningInfo4(reader.read_le_args(args)?)),
+                }
+            }
 
             x if x == XACChunk::XACChunkStdmaterial as u32 => match chunk.version {
                 1 => Ok(XACChunkData::XACStandardMaterial(reader.read_le()?)),
@@ -1352,76 +1396,6 @@ impl XACRoot {
             ),
         })
     }
-
-    pub fn get_texture_names(&self) -> Vec<String> {
-        let mut textures = Vec::new();
-
-        for entry in &self.chunks {
-            match entry.chunk.chunk_id {
-                x if x == XACChunk::XACChunkStdmaterial as u32 => match entry.chunk.version {
-                    1 => {
-                        if let XACChunkData::XACStandardMaterial(mat) = &entry.chunk_data {
-                            textures.push(mat.material_name.clone());
-                        }
-                    }
-                    2 => {
-                        if let XACChunkData::XACStandardMaterial2(mat) = &entry.chunk_data {
-                            textures.push(mat.material_name.clone());
-                        }
-                    }
-                    3 => {
-                        if let XACChunkData::XACStandardMaterial3(mat) = &entry.chunk_data {
-                            textures.push(mat.material_name.clone());
-                        }
-                    }
-                    _ => {}
-                },
-
-                x if x == XACChunk::XACChunkFxmaterial as u32 => match entry.chunk.version {
-                    1 => {
-                        if let XACChunkData::XACFXMaterial(mat) = &entry.chunk_data {
-                            if let Some(bitmaps) = &mat.xac_fx_bitmap_parameter {
-                                for bitmap in bitmaps {
-                                    textures.push(bitmap.value_name.clone());
-                                }
-                            }
-                        }
-                    }
-                    2 => {
-                        if let XACChunkData::XACFXMaterial2(mat) = &entry.chunk_data {
-                            if let Some(bitmaps) = &mat.xac_fx_bitmap_parameter {
-                                for bitmap in bitmaps {
-                                    textures.push(bitmap.value_name.clone());
-                                }
-                            }
-                        }
-                    }
-                    3 => {
-                        if let XACChunkData::XACFXMaterial3(mat) = &entry.chunk_data {
-                            if let Some(bitmaps) = &mat.xac_fx_bitmap_parameter {
-                                for bitmap in bitmaps {
-                                    textures.push(bitmap.value_name.clone());
-                                }
-                            }
-                        }
-                    }
-                    _ => {}
-                },
-
-                _ => {}
-            }
-        }
-
-        textures
-    }
-
-    /// Returns all texture names with a given path prepended
-    pub fn get_texture_names_with_path(&self, texture_path: String) -> Vec<String> {
-        self.get_texture_names()
-            .iter()
-            .map(|name| format!("{}{}", texture_path, name))
-            .collect()
-    }
 }
 
 #[cfg(test)]
@@ -1440,8 +1414,6 @@ mod tests {
         // Print for debugging (optional)
         println!("Header: {:#?}", root.header);
 
-        println!("Textures Name: {:#?}", root.get_texture_names());
-
         Ok(())
     }
 
@@ -1481,4 +1453,42 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_read_pmorph_targets() -> io::Result<()> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&1u32.to_le_bytes()); // num_morph_targets
+        bytes.extend_from_slice(&0u32.to_le_bytes()); // lod
+        bytes.extend_from_slice(&0f32.to_le_bytes()); // range_min
+        bytes.extend_from_slice(&1f32.to_le_bytes()); // range_max
+        bytes.extend_from_slice(&0u32.to_le_bytes()); // lod
+        bytes.extend_from_slice(&1u32.to_le_bytes()); // num_mesh_deform_deltas
+        bytes.extend_from_slice(&0u32.to_le_bytes()); // num_transformations
+        bytes.extend_from_slice(&0u32.to_le_bytes()); // phoneme_sets
+        bytes.extend_from_slice(&5u32.to_le_bytes());
+        bytes.extend_from_slice(b"smile");
+        bytes.extend_from_slice(&3u32.to_le_bytes()); // node_index
+        bytes.extend_from_slice(&(-1f32).to_le_bytes());
+        bytes.extend_from_slice(&1f32.to_le_bytes());
+        bytes.extend_from_slice(&2u32.to_le_bytes()); // num_vertices
+        for value in [0u16, 65535, 0, 65535, 0, 65535] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(&[0, 255, 0, 255, 0, 255]); // normals
+        bytes.extend_from_slice(&[0, 0, 0, 255, 255, 255]); // tangents
+        bytes.extend_from_slice(&7u32.to_le_bytes());
+        bytes.extend_from_slice(&9u32.to_le_bytes());
+
+        let targets: XACPMorphTargets = Cursor::new(&bytes)
+            .read_le()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        assert_eq!(targets.morph_targets.len(), 1);
+        let target = &targets.morph_targets[0];
+        assert_eq!(target.name, "smile");
+        let deltas = &target.morph_target_mesh_deltas[0];
+        assert_eq!(deltas.node_index, 3);
+        assert_eq!(deltas.delta_position_values[1].y, 0);
+        assert_eq!(deltas.vertex_numbers, vec![7, 9]);
+        Ok(())
+    }
 }
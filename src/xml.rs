@@ -1,4 +1,8 @@
-use quick_xml::{Reader, events::Event};
+use quick_xml::{
+    Reader,
+    events::{BytesStart, Event},
+};
+use serde::Serialize;
 use std::{collections::HashMap, fs::File, io::BufReader, path::Path};
 
 pub fn parse_duplicates_xml(path: &Path) -> std::io::Result<HashMap<String, String>> {
@@ -58,3 +62,83 @@ pub fn parse_duplicates_xml(path: &Path) -> std::io::Result<HashMap<String, Stri
 
     Ok(map)
 }
+
+/// One `<image>` entry of a `ui/baseskinset/*.xml` skin set
+#[derive(Debug, Clone, Serialize)]
+pub struct SkinImage {
+    pub name: String,
+    pub file: String,
+    pub imgrect: String,
+    pub category: String,
+}
+
+/// Parse every `<image name=".." file=".." imgrect=".."/>` entry of a skin set
+pub fn parse_skinset_images(bytes: &[u8]) -> std::io::Result<Vec<SkinImage>> {
+    let mut reader = Reader::from_reader(bytes);
+    reader.config_mut().trim_text(true);
+
+    let mut buf = Vec::new();
+    let mut images = Vec::new();
+    let mut current_category = String::new();
+
+    let attr = |e: &BytesStart, key: &[u8]| -> String {
+        e.attributes()
+            .flatten()
+            .find(|a| a.key.as_ref() == key)
+            .map(|a| String::from_utf8_lossy(&a.value).replace('\\', "/"))
+            .unwrap_or_default()
+    };
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            // <imagelist category="...">
+            Ok(Event::Start(ref e)) if e.name().as_ref() == b"imagelist" => {
+                current_category = attr(e, b"category");
+            }
+
+            // <image name="..." file="..." imgrect="..."/>
+            Ok(Event::Empty(ref e)) | Ok(Event::Start(ref e)) if e.name().as_ref() == b"image" => {
+                images.push(SkinImage {
+                    name: attr(e, b"name"),
+                    file: attr(e, b"file").trim_start_matches('/').to_string(),
+                    imgrect: attr(e, b"imgrect"),
+                    category: current_category.clone(),
+                });
+            }
+
+            Ok(Event::Eof) => break,
+
+            Err(e) => {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e));
+            }
+            _ => {}
+        }
+
+        buf.clear();
+    }
+
+    Ok(images)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_skinset_images() -> std::io::Result<()> {
+        let xml = br#"<?xml version="1.0" encoding="UTF-8"?>
+<skinset>
+    <imagelist category="monillust">
+        <image name="Onion" file="\monillust\onion.tga" imgrect="0 0 300 300"/>
+        <image name="Hanaming" file="\monillust\hanaming.tga" imgrect="0 0 300 300"/>
+    </imagelist>
+</skinset>"#;
+
+        let images = parse_skinset_images(xml)?;
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].name, "Onion");
+        assert_eq!(images[0].file, "monillust/onion.tga");
+        assert_eq!(images[1].category, "monillust");
+        Ok(())
+    }
+}
@@ -95,6 +95,19 @@ pub struct File16BitQuaternion {
     pub w: i16,
 }
 
+impl File16BitQuaternion {
+    /// Components are stored scaled by `i16::MAX`
+    pub fn to_quaternion(self) -> FileQuaternion {
+        let scale = 1.0 / i16::MAX as f32;
+        FileQuaternion {
+            x: self.x as f32 * scale,
+            y: self.y as f32 * scale,
+            z: self.z as f32 * scale,
+            w: self.w as f32 * scale,
+        }
+    }
+}
+
 /// XSM Info chunk (version 1)
 #[binread]
 #[derive(Default, Debug, Serialize, Deserialize)]
@@ -0,0 +1,308 @@
+//! Minimal streaming ZIP writer for batch downloads.
+//!
+//! Entries are extracted whole before they are written, so the CRC and sizes
+//! are known up front and every local header is final; nothing has to be
+//! patched afterwards, which lets the archive go straight to a socket. Data is
+//! deflated unless that does not make it smaller. ZIP64 records are added once
+//! the entry count or offsets outgrow the classic format.
+
+use std::io::{self, Write};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use actix_web::web::Bytes;
+use flate2::Compression;
+use flate2::write::DeflateEncoder;
+use tokio::sync::mpsc;
+
+use crate::ipf;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIR_SIGNATURE: u32 = 0x06054b50;
+const ZIP64_END_OF_CENTRAL_DIR_SIGNATURE: u32 = 0x06064b50;
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x07064b50;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+/// Names are UTF-8
+const FLAG_UTF8: u16 = 0x0800;
+const VERSION_DEFAULT: u16 = 20;
+const VERSION_ZIP64: u16 = 45;
+/// 1980-01-01 00:00, the earliest DOS date
+const DOS_DATE: u16 = 0x0021;
+const DOS_TIME: u16 = 0;
+
+struct CentralEntry {
+    name: String,
+    method: u16,
+    crc32: u32,
+    compressed_size: u32,
+    uncompressed_size: u32,
+    offset: u64,
+}
+
+pub struct ZipWriter<W: Write> {
+    out: W,
+    offset: u64,
+    entries: Vec<CentralEntry>,
+}
+
+impl<W: Write> ZipWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            offset: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes)?;
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Append one file; `name` uses '/' separators, e.g. "ies/item.ies"
+    pub fn add_file(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "ZIP entry over 4 GiB");
+        let uncompressed_size = u32::try_from(data.len()).map_err(|_| too_large())?;
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::fast());
+        encoder.write_all(data)?;
+        let deflated = encoder.finish()?;
+        let (method, stored) = if deflated.len() < data.len() {
+            (METHOD_DEFLATED, deflated.as_slice())
+        } else {
+            (METHOD_STORED, data)
+        };
+
+        let entry = CentralEntry {
+            name: name.to_string(),
+            method,
+            crc32: ipf::crc32(data),
+            compressed_size: stored.len() as u32,
+            uncompressed_size,
+            offset: self.offset,
+        };
+
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend_from_slice(&LOCAL_HEADER_SIGNATURE.to_le_bytes());
+        header.extend_from_slice(&VERSION_DEFAULT.to_le_bytes());
+        header.extend_from_slice(&FLAG_UTF8.to_le_bytes());
+        header.extend_from_slice(&method.to_le_bytes());
+        header.extend_from_slice(&DOS_TIME.to_le_bytes());
+        header.extend_from_slice(&DOS_DATE.to_le_bytes());
+        header.extend_from_slice(&entry.crc32.to_le_bytes());
+        header.extend_from_slice(&entry.compressed_size.to_le_bytes());
+        header.extend_from_slice(&entry.uncompressed_size.to_le_bytes());
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(name.as_bytes());
+
+        self.write_all(&header)?;
+        self.write_all(stored)?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Write the central directory and return the underlying writer
+    pub fn finish(mut self) -> io::Result<W> {
+        let central_start = self.offset;
+        let entries = std::mem::take(&mut self.entries);
+
+        for entry in &entries {
+            let zip64_offset = entry.offset >= u32::MAX as u64;
+            let extra: Vec<u8> = if zip64_offset {
+                let mut extra = Vec::with_capacity(12);
+                extra.extend_from_slice(&1u16.to_le_bytes());
+                extra.extend_from_slice(&8u16.to_le_bytes());
+                extra.extend_from_slice(&entry.offset.to_le_bytes());
+                extra
+            } else {
+                Vec::new()
+            };
+            let version = if zip64_offset {
+                VERSION_ZIP64
+            } else {
+                VERSION_DEFAULT
+            };
+
+            let mut header = Vec::with_capacity(46 + entry.name.len() + extra.len());
+            header.extend_from_slice(&CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+            header.extend_from_slice(&version.to_le_bytes());
+            header.extend_from_slice(&version.to_le_bytes());
+            header.extend_from_slice(&FLAG_UTF8.to_le_bytes());
+            header.extend_from_slice(&entry.method.to_le_bytes());
+            header.extend_from_slice(&DOS_TIME.to_le_bytes());
+            header.extend_from_slice(&DOS_DATE.to_le_bytes());
+            header.extend_from_slice(&entry.crc32.to_le_bytes());
+            header.extend_from_slice(&entry.compressed_size.to_le_bytes());
+            header.extend_from_slice(&entry.uncompressed_size.to_le_bytes());
+            header.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+            header.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+            header.extend_from_slice(&0u16.to_le_bytes()); // comment length
+            header.extend_from_slice(&0u16.to_le_bytes()); // disk number
+            header.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+            header.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+            header.extend_from_slice(&(entry.offset.min(u32::MAX as u64) as u32).to_le_bytes());
+            header.extend_from_slice(entry.name.as_bytes());
+            header.extend_from_slice(&extra);
+            self.write_all(&header)?;
+        }
+
+        let central_size = self.offset - central_start;
+        let count = entries.len() as u64;
+        let needs_zip64 = count >= u16::MAX as u64
+            || central_start >= u32::MAX as u64
+            || central_size >= u32::MAX as u64;
+
+        if needs_zip64 {
+            let zip64_end = self.offset;
+            let mut record = Vec::with_capacity(56 + 20);
+            record.extend_from_slice(&ZIP64_END_OF_CENTRAL_DIR_SIGNATURE.to_le_bytes());
+            record.extend_from_slice(&44u64.to_le_bytes());
+            record.extend_from_slice(&VERSION_ZIP64.to_le_bytes());
+            record.extend_from_slice(&VERSION_ZIP64.to_le_bytes());
+            record.extend_from_slice(&0u32.to_le_bytes());
+            record.extend_from_slice(&0u32.to_le_bytes());
+            record.extend_from_slice(&count.to_le_bytes());
+            record.extend_from_slice(&count.to_le_bytes());
+            record.extend_from_slice(&central_size.to_le_bytes());
+            record.extend_from_slice(&central_start.to_le_bytes());
+
+            record.extend_from_slice(&ZIP64_LOCATOR_SIGNATURE.to_le_bytes());
+            record.extend_from_slice(&0u32.to_le_bytes());
+            record.extend_from_slice(&zip64_end.to_le_bytes());
+            record.extend_from_slice(&1u32.to_le_bytes());
+            self.write_all(&record)?;
+        }
+
+        let mut end = Vec::with_capacity(22);
+        end.extend_from_slice(&END_OF_CENTRAL_DIR_SIGNATURE.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes());
+        end.extend_from_slice(&(count.min(u16::MAX as u64) as u16).to_le_bytes());
+        end.extend_from_slice(&(count.min(u16::MAX as u64) as u16).to_le_bytes());
+        end.extend_from_slice(&(central_size.min(u32::MAX as u64) as u32).to_le_bytes());
+        end.extend_from_slice(&(central_start.min(u32::MAX as u64) as u32).to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes());
+        self.write_all(&end)?;
+
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Chunk size handed to the response stream
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Blocking writer feeding a response stream, for use off the async runtime.
+/// Writes fail with `BrokenPipe` once the client has gone away.
+pub struct ChannelWriter {
+    sender: mpsc::Sender<io::Result<Bytes>>,
+    buffer: Vec<u8>,
+}
+
+impl ChannelWriter {
+    /// Writer and the stream to pass to `HttpResponseBuilder::streaming`
+    pub fn channel() -> (Self, ByteStream) {
+        let (sender, receiver) = mpsc::channel(4);
+        let writer = Self {
+            sender,
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+        };
+        (writer, ByteStream { receiver })
+    }
+
+    /// Report an error to the client by aborting the stream
+    pub fn abort(&self, error: io::Error) {
+        let _ = self.sender.blocking_send(Err(error));
+    }
+
+    fn send_buffer(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::replace(&mut self.buffer, Vec::with_capacity(CHUNK_SIZE));
+        self.sender
+            .blocking_send(Ok(Bytes::from(chunk)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "download cancelled"))
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= CHUNK_SIZE {
+            self.send_buffer()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_buffer()
+    }
+}
+
+/// Receiving end of a `ChannelWriter`
+pub struct ByteStream {
+    receiver: mpsc::Receiver<io::Result<Bytes>>,
+}
+
+impl futures_core::Stream for ByteStream {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(bytes: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([bytes[at], bytes[at + 1]])
+    }
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(ipf::crc32(b"123456789"), 0xCBF43926);
+    }
+
+    #[test]
+    fn test_zip_layout() -> io::Result<()> {
+        let text = "xml ".repeat(100);
+        let mut zip = ZipWriter::new(Vec::new());
+        zip.add_file("ui/a.xml", text.as_bytes())?;
+        zip.add_file("b.bin", &[1, 2, 3])?;
+        let bytes = zip.finish()?;
+
+        // First entry is deflated, the tiny second one stored
+        assert_eq!(u32_at(&bytes, 0), LOCAL_HEADER_SIGNATURE);
+        assert_eq!(u16_at(&bytes, 8), METHOD_DEFLATED);
+        assert_eq!(u32_at(&bytes, 14), ipf::crc32(text.as_bytes()));
+        assert_eq!(u32_at(&bytes, 22), text.len() as u32);
+        assert_eq!(&bytes[30..38], b"ui/a.xml");
+
+        let end = bytes.len() - 22;
+        assert_eq!(u32_at(&bytes, end), END_OF_CENTRAL_DIR_SIGNATURE);
+        assert_eq!(u16_at(&bytes, end + 10), 2);
+        let central_start = u32_at(&bytes, end + 16) as usize;
+        assert_eq!(u32_at(&bytes, central_start), CENTRAL_HEADER_SIGNATURE);
+
+        // Inflating the first entry gives the input back
+        let compressed_size = u32_at(&bytes, 18) as usize;
+        let mut inflated = Vec::new();
+        let mut decoder = flate2::write::DeflateDecoder::new(&mut inflated);
+        decoder.write_all(&bytes[38..38 + compressed_size])?;
+        decoder.finish()?;
+        assert_eq!(inflated, text.as_bytes());
+        Ok(())
+    }
+}